@@ -11,6 +11,12 @@ macro_rules! id_type {
             pub fn nil() -> $name {
                 $name(uuid::Uuid::nil())
             }
+            pub fn from_u128(value: u128) -> $name {
+                $name(uuid::Uuid::from_u128(value))
+            }
+            pub fn as_u128(&self) -> u128 {
+                self.0.as_u128()
+            }
             pub fn is_nil(&self) -> bool {
                 self.0 == uuid::Uuid::nil()
             }
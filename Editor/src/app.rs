@@ -325,12 +325,17 @@ impl NodeshopApp {
                         .get(*editor_input_id).unwrap();
                     let editor_value = &editor_input.value.0;
 
-                    assert_eq!(editor_input.typ, editor_value.data_type());
+                    assert!(editor_value.has_type(&editor_input.typ));
+
+                    let is_required = editor_node.user_data.template.0.inputs
+                        .iter()
+                        .find(|input_info| input_info.name == *editor_input_name)
+                        .is_none_or(|input_info| input_info.is_required);
 
                     node.inputs.push(Input {
                         name: editor_input_name.clone(),
-                        data_type: editor_input.typ,
-                        is_required: true,
+                        data_type: editor_input.typ.clone(),
+                        is_required,
                         binding: Binding::None,
                         const_value: Some(editor_value.clone()),
                     });
@@ -347,7 +352,8 @@ impl NodeshopApp {
 
                     node.outputs.push(Output {
                         name: editor_output_name.clone(),
-                        data_type: editor_output.typ,
+                        data_type: editor_output.typ.clone(),
+                        data_type_override: None,
                     });
 
                     output_addresses.insert(*editor_output_id, ArgAddress {
@@ -410,12 +416,12 @@ impl NodeshopApp {
                 |editor_graph: &mut EditorGraph, editor_node_id: eng::NodeId| {
                     for (index, input) in node.inputs.iter().enumerate() {
                         let default_value = input.const_value.clone()
-                            .unwrap_or(Value::from(input.data_type));
+                            .unwrap_or(Value::from(input.data_type.clone()));
 
                         let input_id = editor_graph.add_input_param(
                             editor_node_id,
                             input.name.clone(),
-                            input.data_type,
+                            input.data_type.clone(),
                             EditorValue(default_value),
                             eng::InputParamKind::ConnectionOrConstant,
                             true);
@@ -432,7 +438,7 @@ impl NodeshopApp {
                         let output_id = editor_graph.add_output_param(
                             editor_node_id,
                             output.name.clone(),
-                            output.data_type);
+                            output.data_type.clone());
 
                         output_addresses.insert(
                             ArgAddress {
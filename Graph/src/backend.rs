@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::graph::{Graph, NodeBackend, NodeId};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TransitionKind {
+    // image to texture, for a Gpu consumer of a Cpu output
+    Upload,
+    // texture to image, for a Cpu consumer of a Gpu output
+    Download,
+}
+
+// conversion of an output for the consumers on the other backend,
+// made once and shared by all of them
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BackendTransition {
+    pub kind: TransitionKind,
+    pub node_id: NodeId,
+    pub output_index: u32,
+    // node id and input index
+    pub consumers: Vec<(NodeId, u32)>,
+}
+
+impl TransitionKind {
+    pub fn between(producer: NodeBackend, consumer: NodeBackend) -> Option<TransitionKind> {
+        match (producer, consumer) {
+            (NodeBackend::Cpu, NodeBackend::Gpu) => Some(TransitionKind::Upload),
+            (NodeBackend::Gpu, NodeBackend::Cpu) => Some(TransitionKind::Download),
+            _ => None,
+        }
+    }
+}
+
+impl Graph {
+    // Any nodes run on the backend of the first of their inputs bound to an output, on Cpu if there is none,
+    // so values stay where they are produced
+    pub fn resolved_backends(&self) -> anyhow::Result<HashMap<NodeId, NodeBackend>> {
+        let mut backends: HashMap<NodeId, NodeBackend> = HashMap::new();
+        for node_id in self.topological_order()? {
+            let node = self.node_by_id(node_id).unwrap();
+            let backend = match node.backend {
+                NodeBackend::Any => node.inputs
+                    .iter()
+                    .filter_map(|input| input.binding.as_output_binding())
+                    .map(|output_binding| backends[&output_binding.output_node_id])
+                    .next()
+                    .unwrap_or(NodeBackend::Cpu),
+                backend => backend,
+            };
+            backends.insert(node_id, backend);
+        }
+
+        Ok(backends)
+    }
+
+    // in topological order of the producing nodes, then by output index and kind
+    pub fn backend_transitions(&self) -> anyhow::Result<Vec<BackendTransition>> {
+        let backends = self.resolved_backends()?;
+        let order = self.topological_order()?;
+        let positions: HashMap<NodeId, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(position, &node_id)| (node_id, position))
+            .collect();
+
+        let mut transitions: Vec<BackendTransition> = Vec::new();
+        for &node_id in order.iter() {
+            let node = self.node_by_id(node_id).unwrap();
+            for (input_index, input) in node.inputs.iter().enumerate() {
+                let Some(output_binding) = input.binding.as_output_binding() else {
+                    continue;
+                };
+                let Some(kind) = TransitionKind::between(
+                    backends[&output_binding.output_node_id],
+                    backends[&node_id],
+                ) else {
+                    continue;
+                };
+
+                let consumer = (node_id, input_index as u32);
+                match transitions.iter_mut().find(|transition| {
+                    transition.kind == kind
+                        && transition.node_id == output_binding.output_node_id
+                        && transition.output_index == output_binding.output_index
+                }) {
+                    Some(transition) => transition.consumers.push(consumer),
+                    None => transitions.push(BackendTransition {
+                        kind,
+                        node_id: output_binding.output_node_id,
+                        output_index: output_binding.output_index,
+                        consumers: vec![consumer],
+                    }),
+                }
+            }
+        }
+
+        transitions.sort_by_key(|transition| {
+            (positions[&transition.node_id], transition.output_index, transition.kind as u8)
+        });
+
+        Ok(transitions)
+    }
+}
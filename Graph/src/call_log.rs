@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::TransitionKind;
+use crate::data::Value;
+use crate::functions::FunctionId;
+use crate::invoke::{InvokeArgs, Invoker};
+use crate::runtime_graph::InvokeContext;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InvokeRecord {
+    pub function_id: FunctionId,
+    pub inputs: Vec<Option<Value>>,
+    pub outputs: Vec<Option<Value>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallLog {
+    pub calls: Vec<InvokeRecord>,
+}
+
+pub struct RecordingInvoker {
+    invoker: Box<dyn Invoker>,
+    log: Rc<RefCell<CallLog>>,
+}
+
+pub struct ReplayInvoker {
+    log: CallLog,
+}
+
+
+impl CallLog {
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        let yaml = serde_yaml::to_string(&self)?;
+        Ok(yaml)
+    }
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<CallLog> {
+        let log: CallLog = serde_yaml::from_str(yaml)?;
+        Ok(log)
+    }
+
+    pub fn find(&self, function_id: FunctionId, inputs: &InvokeArgs) -> Option<&InvokeRecord> {
+        self.calls
+            .iter()
+            .find(|record| record.function_id == function_id && record.inputs == inputs)
+    }
+}
+
+impl RecordingInvoker {
+    pub fn new(invoker: Box<dyn Invoker>) -> RecordingInvoker {
+        RecordingInvoker {
+            invoker,
+            log: Rc::new(RefCell::new(CallLog::default())),
+        }
+    }
+
+    // shared handle, stays valid after the invoker is moved into Compute
+    pub fn log(&self) -> Rc<RefCell<CallLog>> {
+        Rc::clone(&self.log)
+    }
+}
+
+impl Invoker for RecordingInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        self.invoker.all_functions()
+    }
+
+    fn invoke(&self,
+              function_id: FunctionId,
+              ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        self.invoker.invoke(function_id, ctx, inputs, outputs)?;
+
+        self.log.borrow_mut().calls.push(InvokeRecord {
+            function_id,
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    fn transition(&self, function_id: FunctionId, kind: TransitionKind, value: &Value) -> anyhow::Result<Value> {
+        self.invoker.transition(function_id, kind, value)
+    }
+}
+
+impl ReplayInvoker {
+    pub fn new(log: CallLog) -> ReplayInvoker {
+        ReplayInvoker {
+            log,
+        }
+    }
+}
+
+impl Invoker for ReplayInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        let mut function_ids = self.log.calls
+            .iter()
+            .map(|record| record.function_id)
+            .collect::<Vec<FunctionId>>();
+        function_ids.sort();
+        function_ids.dedup();
+
+        function_ids
+    }
+
+    fn invoke(&self,
+              function_id: FunctionId,
+              _ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        let record = self.log
+            .find(function_id, inputs)
+            .ok_or_else(|| anyhow::anyhow!("No recorded call for function {} with given inputs", function_id))?;
+
+        if record.outputs.len() != outputs.len() {
+            return Err(anyhow::anyhow!("Recorded output count mismatch for function {}", function_id));
+        }
+        outputs.clone_from_slice(&record.outputs);
+
+        Ok(())
+    }
+}
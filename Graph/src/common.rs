@@ -1,3 +1,41 @@
 pub fn is_debug() -> bool {
     cfg!(debug_assertions)
-}
\ No newline at end of file
+}
+
+// 64 bit FNV-1a over explicitly encoded values; unlike DefaultHasher
+// the result doesn't change between Rust releases, so it can be persisted
+pub(crate) struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    pub(crate) fn new() -> StableHasher {
+        StableHasher {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    pub(crate) fn write_u8(&mut self, value: u8) {
+        self.write(&[value]);
+    }
+    pub(crate) fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+    pub(crate) fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+    pub(crate) fn write_i32(&mut self, value: i32) {
+        self.write(&value.to_le_bytes());
+    }
+    pub(crate) fn write_u128(&mut self, value: u128) {
+        self.write(&value.to_le_bytes());
+    }
+    pub(crate) fn finish(&self) -> u64 {
+        self.state
+    }
+}
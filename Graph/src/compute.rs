@@ -1,20 +1,52 @@
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
+use std::time::{Duration, Instant};
 
-use crate::data::Value;
+use crate::backend::TransitionKind;
+use crate::data::{DataType, Value};
 use crate::functions::FunctionId;
-use crate::graph::{Binding, Graph};
-use crate::invoke::Invoker;
+use crate::graph::{Binding, Graph, Node, NodeBackend, NodeId};
+use crate::invoke::{InvokeArgs, Invoker};
+use crate::run_snapshot::{NodeSnapshot, RunSnapshot};
 use crate::runtime_graph::RuntimeGraph;
 
 #[derive(Default)]
 pub(crate) struct ArgSet(Vec<Option<Value>>);
 
+// lowest quality hint handed to functions by run_within_budget
+pub const MIN_QUALITY: f32 = 0.1;
+
+
+// accumulated over all runs since creation or the last reset
+#[derive(Clone, Default, Debug)]
+pub struct RuntimeStats {
+    pub run_count: u32,
+    pub executed_node_count: u32,
+    // nodes that didn't need to execute, their cached outputs were reused
+    pub skipped_node_count: u32,
+    // total seconds spent per function
+    pub function_run_time: HashMap<FunctionId, f64>,
+}
+
+// what to do with nodes whose function no invoker provides
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum MissingFunctionPolicy {
+    #[default]
+    Error,
+    // each output receives the input at the same index if the types match, otherwise stays empty
+    PassthroughInputs,
+    // outputs are filled with Value::default_for their type
+    Zero,
+}
 
 #[derive(Default)]
 pub struct Compute {
     invokers: Vec<Box<dyn Invoker>>,
     functions: HashMap<FunctionId, u32>,
+    stats: RefCell<RuntimeStats>,
+    missing_function_policy: MissingFunctionPolicy,
 }
 
 impl Compute {
@@ -43,12 +75,97 @@ impl Compute {
     where T: Invoker + 'static {
         self.add_invoker(Box::new(invoker));
     }
+    pub fn set_missing_function_policy(&mut self, missing_function_policy: MissingFunctionPolicy) {
+        self.missing_function_policy = missing_function_policy;
+    }
+    pub fn stats(&self) -> RuntimeStats {
+        self.stats.borrow().clone()
+    }
+    pub fn reset_stats(&self) {
+        *self.stats.borrow_mut() = RuntimeStats::default();
+    }
     pub fn run(
         &self,
         graph: &Graph,
         runtime_graph: &mut RuntimeGraph,
     ) -> anyhow::Result<()>
     {
+        self.run_impl(graph, runtime_graph, None, &mut |_, _, _| {})
+    }
+    // lowers InvokeContext::quality of the remaining nodes once the time spent so far
+    // plus their run times from the previous run would exceed the budget
+    pub fn run_within_budget(
+        &self,
+        graph: &Graph,
+        runtime_graph: &mut RuntimeGraph,
+        budget: Duration,
+    ) -> anyhow::Result<()>
+    {
+        self.run_impl(graph, runtime_graph, Some(budget), &mut |_, _, _| {})
+    }
+    // calls on_node_done with the node outputs right after each node is executed
+    pub fn run_with_progress<F>(
+        &self,
+        graph: &Graph,
+        runtime_graph: &mut RuntimeGraph,
+        mut on_node_done: F,
+    ) -> anyhow::Result<()>
+    where F: FnMut(NodeId, &InvokeArgs)
+    {
+        self.run_impl(graph, runtime_graph, None, &mut |node_id, _, outputs| on_node_done(node_id, outputs))
+    }
+    // same as run, additionally capturing inputs and outputs of every executed node
+    pub fn run_snapshot(
+        &self,
+        graph: &Graph,
+        runtime_graph: &mut RuntimeGraph,
+    ) -> anyhow::Result<RunSnapshot>
+    {
+        let mut snapshot = RunSnapshot {
+            structural_hash: graph.structural_hash(),
+            nodes: Vec::new(),
+        };
+        self.run_impl(graph, runtime_graph, None, &mut |node_id, inputs, outputs| {
+            snapshot.nodes.push(NodeSnapshot {
+                node_id,
+                inputs: inputs.to_vec(),
+                outputs: outputs.to_vec(),
+            });
+        })?;
+
+        Ok(snapshot)
+    }
+
+    fn run_impl(
+        &self,
+        graph: &Graph,
+        runtime_graph: &mut RuntimeGraph,
+        budget: Option<Duration>,
+        on_node_done: &mut dyn FnMut(NodeId, &InvokeArgs, &InvokeArgs),
+    ) -> anyhow::Result<()>
+    {
+        let result = self.run_nodes(graph, runtime_graph, budget, on_node_done);
+        if result.is_err() {
+            // a run stopped partway keeps consumer counts the next preprocess expects to be zero
+            runtime_graph.nodes
+                .iter_mut()
+                .for_each(|r_node| {
+                    r_node.total_binding_count = 0;
+                    r_node.output_binding_count.fill(0);
+                });
+        }
+
+        result
+    }
+    fn run_nodes(
+        &self,
+        graph: &Graph,
+        runtime_graph: &mut RuntimeGraph,
+        budget: Option<Duration>,
+        on_node_done: &mut dyn FnMut(NodeId, &InvokeArgs, &InvokeArgs),
+    ) -> anyhow::Result<()>
+    {
+        let run_start = Instant::now();
         let mut inputs: ArgSet = ArgSet::default();
 
         let active_node_indexes =
@@ -64,40 +181,81 @@ impl Compute {
                 })
                 .collect::<Vec<usize>>();
 
-        for index in active_node_indexes {
+        // borrowed briefly, invokers and callbacks may read the stats during the run
+        {
+            let mut stats = self.stats.borrow_mut();
+            stats.run_count += 1;
+            stats.skipped_node_count += runtime_graph.nodes
+                .iter()
+                .filter(|r_node| !r_node.has_missing_inputs && !r_node.should_execute)
+                .count() as u32;
+        }
+
+        // only resolved when a node pins a backend, all nodes run on Cpu otherwise
+        let backends = if graph.nodes().iter().any(|node| node.backend != NodeBackend::Any) {
+            Some(graph.resolved_backends()?)
+        } else {
+            None
+        };
+        // converted once per run and output, shared by all consumers on the other backend
+        let mut transitioned: HashMap<(NodeId, u32, TransitionKind), Option<Value>> = HashMap::new();
+
+        for (order, &index) in active_node_indexes.iter().enumerate() {
             let node = graph
                 .node_by_id(runtime_graph.nodes[index].node_id()).unwrap();
 
+            let quality = match budget {
+                None => 1.0,
+                Some(budget) => {
+                    let estimated_time: f64 = active_node_indexes[order..]
+                        .iter()
+                        .map(|&index| runtime_graph.nodes[index].run_time)
+                        .sum();
+                    Self::budget_quality(budget, run_start.elapsed(), estimated_time)
+                }
+            };
+            runtime_graph.nodes[index].invoke_context.set_quality(quality);
+
             inputs.resize_and_fill(node.inputs.len());
-            node.inputs
-                .iter()
-                .map(|input| {
-                    match &input.binding {
-                        Binding::None => None,
-                        Binding::Const => input.const_value.clone(),
-
-                        Binding::Output(output_binding) => {
-                            let output_r_node = runtime_graph
-                                .node_by_id_mut(output_binding.output_node_id).unwrap();
-
-                            output_r_node.decrement_binding_count(output_binding.output_index);
-
-                            let output_values =
-                                output_r_node.output_values
-                                    .as_mut().unwrap();
-                            let value =
-                                output_values
-                                    .get_mut(output_binding.output_index as usize).unwrap()
-                                    .clone();
-
-                            value
+            for (input_index, input) in node.inputs.iter().enumerate() {
+                inputs[input_index] = match &input.binding {
+                    Binding::None => None,
+                    Binding::Const => input.const_value.clone(),
+
+                    Binding::Output(output_binding) => {
+                        let output_r_node = runtime_graph
+                            .node_by_id_mut(output_binding.output_node_id).unwrap();
+
+                        output_r_node.decrement_binding_count(output_binding.output_index);
+
+                        let output_values =
+                            output_r_node.output_values
+                                .as_mut().unwrap();
+                        let value =
+                            output_values
+                                .get_mut(output_binding.output_index as usize).unwrap()
+                                .clone();
+
+                        let kind = backends.as_ref().and_then(|backends| TransitionKind::between(
+                            backends[&output_binding.output_node_id],
+                            backends[&node.id()],
+                        ));
+                        match kind {
+                            None => value,
+                            Some(kind) => {
+                                let key = (output_binding.output_node_id, output_binding.output_index, kind);
+                                match transitioned.entry(key) {
+                                    Entry::Occupied(entry) => entry.get().clone(),
+                                    Entry::Vacant(entry) => {
+                                        let producer = graph.node_by_id(output_binding.output_node_id).unwrap();
+                                        entry.insert(self.transition(producer, node, kind, value)?).clone()
+                                    }
+                                }
+                            }
                         }
                     }
-                })
-                .enumerate()
-                .for_each(|(index, value)| {
-                    inputs[index] = value
-                });
+                };
+            }
 
             let r_node = &mut runtime_graph.nodes[index];
             let outputs =
@@ -105,19 +263,62 @@ impl Compute {
                     .get_or_insert_with(|| vec![None; node.outputs.len()]);
 
             r_node.run_time = {
-                let invoker = self.get_invoker(node.function_id);
-
                 let start = std::time::Instant::now();
-                invoker.invoke(
-                    node.function_id,
-                    &mut r_node.invoke_context,
-                    inputs.as_slice(),
-                    outputs.as_mut_slice(),
-                )?;
+                match self.get_invoker(node.function_id) {
+                    Some(invoker) => invoker.invoke(
+                        node.function_id,
+                        &mut r_node.invoke_context,
+                        inputs.as_slice(),
+                        outputs.as_mut_slice(),
+                    )?,
+                    None => self.invoke_missing(node, inputs.as_slice(), outputs.as_mut_slice())?,
+                }
+                r_node.invoke_context.mark_param_hash_executed();
 
                 start.elapsed().as_secs_f64()
             };
 
+            // outputs with a type override are marshalled to the overridden type
+            for (output, value) in node.outputs.iter().zip(outputs.iter_mut()) {
+                let data_type_override = match &output.data_type_override {
+                    Some(data_type_override) => data_type_override,
+                    None => continue,
+                };
+                if let Some(produced) = value.as_ref() {
+                    if produced.has_type(data_type_override) {
+                        continue;
+                    }
+                    let coercion = DataType::coercion(&produced.data_type(), data_type_override)
+                        .ok_or_else(|| anyhow::anyhow!(
+                            "Node {} produced {} for output {} overridden as {}",
+                            node.name,
+                            produced.data_type(),
+                            output.name,
+                            data_type_override
+                        ))?;
+                    *value = Some(coercion.apply(produced));
+                }
+            }
+            {
+                let mut stats = self.stats.borrow_mut();
+                stats.executed_node_count += 1;
+                *stats.function_run_time
+                    .entry(node.function_id)
+                    .or_default() += r_node.run_time;
+            }
+
+            on_node_done(node.id(), inputs.as_slice(), outputs.as_slice());
+
+            runtime_graph.probes
+                .iter_mut()
+                .filter(|probe| probe.node_id == node.id())
+                .for_each(|probe| {
+                    probe.value = outputs
+                        .get(probe.output_index as usize)
+                        .cloned()
+                        .flatten();
+                });
+
             inputs.fill();
         }
 
@@ -130,16 +331,70 @@ impl Compute {
         Ok(())
     }
 
-    fn get_invoker(&self, function_id: FunctionId) -> &dyn Invoker {
-        let &invoker_index =
-            self.functions
-                .get(&function_id)
-                .unwrap();
+    fn budget_quality(budget: Duration, elapsed: Duration, estimated_time: f64) -> f32 {
+        let remaining_time = budget.saturating_sub(elapsed).as_secs_f64();
+        if elapsed < budget && estimated_time <= remaining_time {
+            return 1.0;
+        }
+        if remaining_time == 0.0 {
+            return MIN_QUALITY;
+        }
+
+        ((remaining_time / estimated_time) as f32).clamp(MIN_QUALITY, 1.0)
+    }
+
+    fn get_invoker(&self, function_id: FunctionId) -> Option<&dyn Invoker> {
+        let &invoker_index = self.functions.get(&function_id)?;
         let invoker = self.invokers
             .get(invoker_index as usize)
             .unwrap();
 
-        invoker.as_ref()
+        Some(invoker.as_ref())
+    }
+    // runs the transition hook of the Gpu side invoker, values of missing functions pass through
+    fn transition(
+        &self,
+        producer: &Node,
+        consumer: &Node,
+        kind: TransitionKind,
+        value: Option<Value>,
+    ) -> anyhow::Result<Option<Value>> {
+        let function_id = match kind {
+            TransitionKind::Upload => consumer.function_id,
+            TransitionKind::Download => producer.function_id,
+        };
+
+        match (value, self.get_invoker(function_id)) {
+            (Some(value), Some(invoker)) => Ok(Some(invoker.transition(function_id, kind, &value)?)),
+            (value, _) => Ok(value),
+        }
+    }
+    fn invoke_missing(&self, node: &Node, inputs: &InvokeArgs, outputs: &mut InvokeArgs) -> anyhow::Result<()> {
+        match self.missing_function_policy {
+            MissingFunctionPolicy::Error => {
+                return Err(anyhow::anyhow!(
+                    "Function {} of node {} is not provided by any invoker",
+                    node.function_id,
+                    node.name
+                ));
+            }
+            MissingFunctionPolicy::PassthroughInputs => {
+                for (index, output) in node.outputs.iter().enumerate() {
+                    outputs[index] = inputs
+                        .get(index)
+                        .and_then(|input| input.as_ref())
+                        .filter(|input| input.has_type(output.effective_data_type()))
+                        .cloned();
+                }
+            }
+            MissingFunctionPolicy::Zero => {
+                for (index, output) in node.outputs.iter().enumerate() {
+                    outputs[index] = Some(Value::default_for(output.effective_data_type()));
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
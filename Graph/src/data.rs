@@ -1,9 +1,14 @@
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::common::StableHasher;
+
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug, Serialize, Deserialize)]
 pub enum DataType {
     #[default]
     Null,
@@ -11,25 +16,106 @@ pub enum DataType {
     Int,
     Bool,
     String,
+    // elements of the same type, any number of them unless a length is given
+    Array(Box<DataType>, Option<u32>),
+    // opaque image produced and consumed by invokers, see ImageHandle
+    Image,
+    // type of generic ports, resolved from their bindings by Graph::infer_types
+    Generic,
+}
+
+// lossless conversion applied when binding an output to an input of another type
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Coercion {
+    IntToFloat,
 }
 
 impl DataType {
-    pub fn can_assign(from: DataType, to: DataType) -> bool {
-        assert_ne!(from, DataType::Null);
-        assert_ne!(to, DataType::Null);
+    // non-array types available for node inputs and outputs, arrays are built from these
+    pub fn all() -> &'static [DataType] {
+        &[DataType::Float, DataType::Int, DataType::Bool, DataType::String, DataType::Image]
+    }
+    // arrays are named "array" regardless of the element type, see Display for the full name
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::Null => "null",
+            DataType::Float => "float",
+            DataType::Int => "int",
+            DataType::Bool => "bool",
+            DataType::String => "string",
+            DataType::Array(..) => "array",
+            DataType::Image => "image",
+            DataType::Generic => "generic",
+        }
+    }
+    pub fn array_of(element_type: DataType) -> DataType {
+        DataType::Array(Box::new(element_type), None)
+    }
+    pub fn fixed_array_of(element_type: DataType, len: u32) -> DataType {
+        DataType::Array(Box::new(element_type), Some(len))
+    }
+    // tags are part of persisted hashes, don't renumber them
+    pub(crate) fn stable_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            DataType::Null => hasher.write_u8(0),
+            DataType::Float => hasher.write_u8(1),
+            DataType::Int => hasher.write_u8(2),
+            DataType::Bool => hasher.write_u8(3),
+            DataType::String => hasher.write_u8(4),
+            DataType::Array(element_type, len) => {
+                hasher.write_u8(5);
+                element_type.stable_hash(hasher);
+                match len {
+                    None => hasher.write_u8(0),
+                    Some(len) => {
+                        hasher.write_u8(1);
+                        hasher.write_u32(*len);
+                    }
+                }
+            }
+            DataType::Image => hasher.write_u8(6),
+            DataType::Generic => hasher.write_u8(7),
+        }
+    }
+
+    // arrays are assignable if their element types are, elements are not converted;
+    // a fixed length target only accepts arrays of exactly that length;
+    // generic ports accept and feed any type, see Graph::infer_types
+    pub fn can_assign(from: &DataType, to: &DataType) -> bool {
+        match (from, to) {
+            (DataType::Generic, _) | (_, DataType::Generic) => true,
+            (DataType::Array(from, from_len), DataType::Array(to, to_len)) => {
+                let len_matches = to_len.is_none() || from_len == to_len;
+                // an empty array has no element type yet
+                len_matches && (**from == DataType::Null || DataType::can_assign(from, to))
+            }
+            _ => from == to,
+        }
+    }
 
-        from == to
+    // none for generic ports, they don't need one
+    pub fn coercion(from: &DataType, to: &DataType) -> Option<Coercion> {
+        match (from, to) {
+            (DataType::Int, DataType::Float) => Some(Coercion::IntToFloat),
+            _ => None,
+        }
     }
 }
 
-impl ToString for DataType {
-    fn to_string(&self) -> String {
+impl Coercion {
+    pub fn apply(&self, value: &Value) -> Value {
         match self {
-            DataType::Float => "float".to_string(),
-            DataType::Int => "int".to_string(),
-            DataType::Bool => "bool".to_string(),
-            DataType::String => "string".to_string(),
-            _ => panic!("No string representation for {:?}", self),
+            Coercion::IntToFloat => Value::Float(value.as_int() as f64),
+        }
+    }
+}
+
+impl Display for DataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataType::Array(element_type, None) => write!(f, "array<{}>", element_type),
+            DataType::Array(element_type, Some(len)) => write!(f, "array<{}, {}>", element_type, len),
+            _ => f.write_str(self.name()),
         }
     }
 }
@@ -44,7 +130,22 @@ impl FromStr for DataType {
             "int" => Ok(DataType::Int),
             "bool" => Ok(DataType::Bool),
             "string" => Ok(DataType::String),
-            _ => Err(()),
+            "image" => Ok(DataType::Image),
+            "generic" => Ok(DataType::Generic),
+            _ => {
+                let inner = s
+                    .strip_prefix("array<")
+                    .and_then(|s| s.strip_suffix('>'))
+                    .ok_or(())?;
+                // the length follows the last comma, nested array types may contain commas too
+                let len = inner
+                    .rsplit_once(',')
+                    .and_then(|(element_type, len)| Some((element_type, len.trim().parse::<u32>().ok()?)));
+                match len {
+                    Some((element_type, len)) => Ok(DataType::fixed_array_of(element_type.trim().parse()?, len)),
+                    None => Ok(DataType::array_of(inner.parse()?)),
+                }
+            }
         }
     }
 }
@@ -59,9 +160,76 @@ pub enum Value {
     Int(i64),
     Bool(bool),
     String(String),
+    Array(Vec<Value>),
+    // images only live in memory, graphs holding them as const values can't be saved
+    #[serde(skip)]
+    Image(ImageHandle),
+}
+
+// image owned by the invoker that produced it, e.g. an imaginarium Image;
+// the graph never looks inside, handles are equal only if they share the image
+#[derive(Clone)]
+pub struct ImageHandle(Arc<dyn Any + Send + Sync>);
+
+// value equality with a tolerance for floats; equal infinities are equal,
+// NaN is never equal to anything, including another NaN, so runs producing NaN always show up as diverging
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ValueCompare {
+    pub epsilon: f64,
+}
+
+impl Default for ValueCompare {
+    fn default() -> Self {
+        ValueCompare {
+            epsilon: 1e-9,
+        }
+    }
+}
+
+impl ValueCompare {
+    pub fn new(epsilon: f64) -> Self {
+        assert!(epsilon >= 0.0);
+        ValueCompare { epsilon }
+    }
+
+    pub fn values_equal(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            // the difference of equal infinities is NaN, compare them directly
+            (Value::Float(a), Value::Float(b)) => a == b || (a - b).abs() <= self.epsilon,
+            (Value::Array(a), Value::Array(b)) =>
+                a.len() == b.len()
+                    && a.iter().zip(b.iter()).all(|(a, b)| self.values_equal(a, b)),
+            _ => a == b,
+        }
+    }
+    pub fn args_equal(&self, a: &[Option<Value>], b: &[Option<Value>]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+            .zip(b.iter())
+            .all(|(a, b)| match (a, b) {
+                (Some(a), Some(b)) => self.values_equal(a, b),
+                (None, None) => true,
+                _ => false,
+            })
+    }
 }
 
 impl Value {
+    // zero, false, empty string, empty array or a fixed length array of element defaults;
+    // there is no default image or generic value, null is returned instead
+    pub fn default_for(data_type: &DataType) -> Value {
+        match data_type {
+            DataType::Null => Value::Null,
+            DataType::Float => Value::Float(0.0),
+            DataType::Int => Value::Int(0),
+            DataType::Bool => Value::Bool(false),
+            DataType::String => Value::String(String::new()),
+            DataType::Array(_, None) => Value::Array(Vec::new()),
+            DataType::Array(element_type, Some(len)) =>
+                Value::Array(vec![Value::default_for(element_type); *len as usize]),
+            DataType::Image | DataType::Generic => Value::Null,
+        }
+    }
     pub fn data_type(&self) -> DataType {
         match self {
             Value::Null => DataType::Null,
@@ -69,6 +237,20 @@ impl Value {
             Value::Int(_) => DataType::Int,
             Value::Bool(_) => DataType::Bool,
             Value::String(_) => DataType::String,
+            Value::Image(_) => DataType::Image,
+            // element type of an empty array is unknown, the length is left open
+            Value::Array(values) => DataType::array_of(
+                values.first().map_or(DataType::Null, Value::data_type)
+            ),
+        }
+    }
+    pub fn has_type(&self, data_type: &DataType) -> bool {
+        match (self, data_type) {
+            (_, DataType::Generic) => true,
+            (Value::Array(values), DataType::Array(element_type, len)) =>
+                len.is_none_or(|len| values.len() == len as usize)
+                    && values.iter().all(|value| value.has_type(element_type)),
+            _ => self.data_type() == *data_type,
         }
     }
 
@@ -96,6 +278,45 @@ impl Value {
             _ => { panic!("Value is not a string") }
         }
     }
+    pub fn as_array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => { values }
+            _ => { panic!("Value is not an array") }
+        }
+    }
+    pub fn as_image(&self) -> &ImageHandle {
+        match self {
+            Value::Image(image) => { image }
+            _ => { panic!("Value is not an image") }
+        }
+    }
+}
+
+impl ImageHandle {
+    pub fn new<T: Any + Send + Sync>(image: T) -> ImageHandle {
+        ImageHandle(Arc::new(image))
+    }
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref::<T>()
+    }
+}
+
+impl PartialEq for ImageHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for ImageHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ImageHandle({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+impl From<ImageHandle> for Value {
+    fn from(image: ImageHandle) -> Self {
+        Value::Image(image)
+    }
 }
 
 impl From<DataType> for Value {
@@ -105,6 +326,7 @@ impl From<DataType> for Value {
             DataType::Int => Value::Int(0),
             DataType::Bool => Value::Bool(false),
             DataType::String => Value::String("".to_string()),
+            DataType::Array(..) => Value::default_for(&data_type),
             _ => panic!("No value for {:?}", data_type),
         }
     }
@@ -152,6 +374,12 @@ impl From<bool> for Value {
     }
 }
 
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
 impl From<Value> for i64 {
     fn from(value: Value) -> Self {
         match value {
@@ -11,10 +11,13 @@ pub struct OutputInfo {
     pub data_type: DataType,
 }
 
-#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InputInfo {
     pub name: String,
     pub data_type: DataType,
+    // copied to the inputs of nodes created from the function
+    #[serde(default = "is_required_default")]
+    pub is_required: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub const_value: Option<Value>,
 }
@@ -25,6 +28,8 @@ id_type!(FunctionId);
 pub struct Function {
     self_id: FunctionId,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub behavior: FunctionBehavior,
     pub is_output: bool,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -82,6 +87,22 @@ impl Functions {
     }
 }
 
+impl Default for InputInfo {
+    fn default() -> Self {
+        InputInfo {
+            name: String::new(),
+            data_type: DataType::default(),
+            is_required: is_required_default(),
+            const_value: None,
+        }
+    }
+}
+
+// inputs are required unless declared optional
+pub(crate) fn is_required_default() -> bool {
+    true
+}
+
 impl Function {
     pub fn new(func_id: FunctionId) -> Function {
         Function {
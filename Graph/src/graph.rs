@@ -1,17 +1,36 @@
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use glam::Vec2;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use common::id_type;
 
-use crate::data::{DataType, Value};
-use crate::functions::{Function, FunctionId};
+use crate::common::StableHasher;
+use crate::data::{Coercion, DataType, Value};
+use crate::functions::{is_required_default, Function, FunctionId};
 use crate::subgraph::{SubGraph, SubGraphId};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
 pub enum FunctionBehavior {
     #[default]
     Active,
     Passive,
+    // like Passive, but becomes active only through the given input
+    OnInputChange(u32),
+}
+
+// where a node executes, Any nodes follow their inputs, see Graph::resolved_backends
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum NodeBackend {
+    #[default]
+    Any,
+    Cpu,
+    Gpu,
 }
 
 id_type!(NodeId);
@@ -23,9 +42,16 @@ pub struct Node {
     pub function_id: FunctionId,
 
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub behavior: FunctionBehavior,
     pub is_output: bool,
     pub should_cache_outputs: bool,
+    // orders independent nodes, lower runs first, see Graph::topological_order
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub execution_priority: i32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub backend: NodeBackend,
 
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub inputs: Vec<Input>,
@@ -34,18 +60,44 @@ pub struct Node {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub subgraph_id: Option<SubGraphId>,
+    // node executing the given subgraph in place of a function
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_of: Option<SubGraphId>,
+
+    // editor position, not part of the computation
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub position: Vec2,
+
+    // display position assigned by Graph::add_node, independent of storage order
+    #[serde(default)]
+    order: u32,
 }
 
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Output {
     pub name: String,
     pub data_type: DataType,
+    // set for functions whose output type is configured per node,
+    // takes precedence over the declared data_type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_type_override: Option<DataType>,
+}
+
+// Always: changes of the producing node propagate to the consumer;
+// Once: the consumer reuses the last produced value if there is one
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum BindingBehavior {
+    #[default]
+    Always,
+    Once,
 }
 
 #[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OutputBinding {
     pub output_node_id: NodeId,
     pub output_index: u32,
+    #[serde(default)]
+    pub behavior: BindingBehavior,
 }
 
 #[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -56,7 +108,7 @@ pub enum Binding {
     Output(OutputBinding),
 }
 
-#[derive(Clone, Default, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Input {
     pub name: String,
     pub data_type: DataType,
@@ -67,11 +119,61 @@ pub struct Input {
 }
 
 
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    // skipping validation is only safe for trusted graphs, e.g. previously saved by the app
+    pub validate: bool,
+}
+
+// a bound node input, identified by the node and input index
+pub struct EdgeRef<'a> {
+    pub dst_node_id: NodeId,
+    pub dst_input_index: u32,
+    pub binding: &'a Binding,
+}
+pub struct EdgeMut<'a> {
+    pub dst_node_id: NodeId,
+    pub dst_input_index: u32,
+    pub binding: &'a mut Binding,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ConnectionStatus {
+    Ok,
+    NeedsCoercion(Coercion),
+    Incompatible(String),
+}
+
+
 #[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(from = "GraphData")]
 pub struct Graph {
     nodes: Vec<Node>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     subgraphs: Vec<SubGraph>,
+
+    // display order of the next added node, above the order of every node
+    #[serde(skip)]
+    next_order: u32,
+
+    // host data attached to the graph, one value per type, never serialized;
+    // clones of the graph share the payloads
+    #[serde(skip)]
+    payloads: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+// serialized part of Graph, next_order is restored from the loaded nodes
+#[derive(Deserialize)]
+struct GraphData {
+    nodes: Vec<Node>,
+    #[serde(default)]
+    subgraphs: Vec<SubGraph>,
+}
+
+// read-only graph, shared for execution; thaw it to edit again
+#[derive(Clone)]
+pub struct FrozenGraph {
+    graph: Graph,
 }
 
 
@@ -83,12 +185,68 @@ impl Graph {
         self.nodes.as_mut_slice()
     }
 
-    pub fn add_node(&mut self, node: Node) {
+    // all inputs bound to a const value or another node's output
+    pub fn bindings(&self) -> impl Iterator<Item=EdgeRef<'_>> {
+        self.nodes
+            .iter()
+            .flat_map(|node| {
+                node.inputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, input)| input.binding.is_some())
+                    .map(|(index, input)| EdgeRef {
+                        dst_node_id: node.self_id,
+                        dst_input_index: index as u32,
+                        binding: &input.binding,
+                    })
+            })
+    }
+    pub fn bindings_mut(&mut self) -> impl Iterator<Item=EdgeMut<'_>> {
+        self.nodes
+            .iter_mut()
+            .flat_map(|node| {
+                let node_id = node.self_id;
+                node.inputs
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, input)| input.binding.is_some())
+                    .map(move |(index, input)| EdgeMut {
+                        dst_node_id: node_id,
+                        dst_input_index: index as u32,
+                        binding: &mut input.binding,
+                    })
+            })
+    }
+
+    // a replaced node keeps its display order, a new one is placed after all others
+    pub fn add_node(&mut self, mut node: Node) {
         match self.nodes.iter().position(|n| n.self_id == node.self_id) {
-            Some(index) => self.nodes[index] = node,
-            None => self.nodes.push(node),
+            Some(index) => {
+                node.order = self.nodes[index].order;
+                self.nodes[index] = node;
+            }
+            None => {
+                node.order = self.next_order;
+                self.next_order += 1;
+                self.nodes.push(node);
+            }
         }
     }
+    // nodes sorted by display order, ties keep storage order
+    pub fn nodes_in_order(&self) -> Vec<&Node> {
+        let mut nodes: Vec<&Node> = self.nodes.iter().collect();
+        nodes.sort_by_key(|node| node.order);
+        nodes
+    }
+    // renumbers display order without gaps left by removed nodes
+    pub fn compact(&mut self) {
+        let mut indices: Vec<usize> = (0..self.nodes.len()).collect();
+        indices.sort_by_key(|&index| self.nodes[index].order);
+        for (order, index) in indices.into_iter().enumerate() {
+            self.nodes[index].order = order as u32;
+        }
+        self.next_order = self.nodes.len() as u32;
+    }
     pub fn remove_node_by_id(&mut self, id: NodeId) {
         assert_ne!(id.0, Uuid::nil());
 
@@ -101,10 +259,427 @@ impl Graph {
                 Binding::Output(output_binding) if output_binding.output_node_id == id => Some(input),
                 _ => None,
             })
+            .for_each(|input| input.unbind());
+    }
+
+    // removes the output and shifts consumer bindings referencing outputs after it;
+    // consumers of the removed output itself are unbound
+    pub fn remove_node_output(&mut self, node_id: NodeId, output_index: u32) -> anyhow::Result<()> {
+        let node = self.node_by_id_mut(node_id)
+            .ok_or(anyhow::Error::msg("Node not found"))?;
+        node.remove_output(output_index)?;
+
+        self.nodes
+            .iter_mut()
+            .flat_map(|node| node.inputs.iter_mut())
             .for_each(|input| {
-                input.binding = input.const_value.as_ref()
-                    .map_or(Binding::None, |_| Binding::Const);
+                let output_binding = match input.binding.as_output_binding_mut() {
+                    Some(output_binding) if output_binding.output_node_id == node_id => output_binding,
+                    _ => return,
+                };
+
+                if output_binding.output_index == output_index {
+                    input.unbind();
+                } else if output_binding.output_index > output_index {
+                    output_binding.output_index -= 1;
+                }
             });
+
+        self.subgraphs
+            .iter_mut()
+            .for_each(|subgraph| {
+                subgraph.outputs.retain(|suboutput| {
+                    suboutput.subnode_id != node_id || suboutput.subnode_output_index != output_index
+                });
+                subgraph.outputs
+                    .iter_mut()
+                    .filter(|suboutput| suboutput.subnode_id == node_id && suboutput.subnode_output_index > output_index)
+                    .for_each(|suboutput| suboutput.subnode_output_index -= 1);
+            });
+
+        Ok(())
+    }
+    // removes the input and shifts subgraph input connections referencing inputs after it
+    pub fn remove_node_input(&mut self, node_id: NodeId, input_index: u32) -> anyhow::Result<()> {
+        let node = self.node_by_id_mut(node_id)
+            .ok_or(anyhow::Error::msg("Node not found"))?;
+        node.remove_input(input_index)?;
+
+        self.subgraphs
+            .iter_mut()
+            .flat_map(|subgraph| subgraph.inputs.iter_mut())
+            .for_each(|subinput| {
+                subinput.connections.retain(|connection| {
+                    connection.subnode_id != node_id || connection.subnode_input_index != input_index
+                });
+                subinput.connections
+                    .iter_mut()
+                    .filter(|connection| connection.subnode_id == node_id && connection.subnode_input_index > input_index)
+                    .for_each(|connection| connection.subnode_input_index -= 1);
+            });
+
+        Ok(())
+    }
+
+    pub fn reroute_consumers(
+        &mut self,
+        old_node_id: NodeId,
+        old_output_index: u32,
+        new_node_id: NodeId,
+        new_output_index: u32,
+    ) {
+        self.bindings_mut()
+            .filter_map(|edge| edge.binding.as_output_binding_mut())
+            .filter(|output_binding| {
+                output_binding.output_node_id == old_node_id
+                    && output_binding.output_index == old_output_index
+            })
+            .for_each(|output_binding| {
+                output_binding.output_node_id = new_node_id;
+                output_binding.output_index = new_output_index;
+            });
+    }
+
+    // binds the source output to the destination input, replacing the previous binding
+    pub fn connect(
+        &mut self,
+        dst_node_id: NodeId,
+        dst_input_index: u32,
+        src_node_id: NodeId,
+        src_output_index: u32,
+    ) -> anyhow::Result<()> {
+        self.connect_with_behavior(dst_node_id, dst_input_index, src_node_id, src_output_index, BindingBehavior::Always)
+    }
+    pub fn connect_with_behavior(
+        &mut self,
+        dst_node_id: NodeId,
+        dst_input_index: u32,
+        src_node_id: NodeId,
+        src_output_index: u32,
+        behavior: BindingBehavior,
+    ) -> anyhow::Result<()> {
+        match self.connection_check(dst_node_id, dst_input_index, src_node_id, src_output_index) {
+            ConnectionStatus::Ok => {}
+            ConnectionStatus::NeedsCoercion(coercion) =>
+                return Err(anyhow::anyhow!("Connection needs coercion {:?}", coercion)),
+            ConnectionStatus::Incompatible(reason) =>
+                return Err(anyhow::Error::msg(reason)),
+        }
+
+        let mut binding = Binding::from_output_binding(src_node_id, src_output_index);
+        binding.as_output_binding_mut().unwrap().behavior = behavior;
+
+        let input = &mut self.node_by_id_mut(dst_node_id).unwrap()
+            .inputs[dst_input_index as usize];
+        let previous_binding = std::mem::replace(&mut input.binding, binding);
+
+        if self.topological_order().is_err() {
+            self.node_by_id_mut(dst_node_id).unwrap()
+                .inputs[dst_input_index as usize].binding = previous_binding;
+            return Err(anyhow::Error::msg("Connection would create a cycle"));
+        }
+
+        Ok(())
+    }
+
+    // checks whether binding the source output to the destination input is valid,
+    // without modifying the graph
+    pub fn connection_check(
+        &self,
+        dst_node_id: NodeId,
+        dst_input_index: u32,
+        src_node_id: NodeId,
+        src_output_index: u32,
+    ) -> ConnectionStatus {
+        if dst_node_id == src_node_id {
+            return ConnectionStatus::Incompatible("Cannot connect a node to itself".to_string());
+        }
+
+        let dst_input = match self.node_by_id(dst_node_id)
+            .and_then(|node| node.inputs.get(dst_input_index as usize)) {
+            Some(input) => input,
+            None => return ConnectionStatus::Incompatible("Input not found".to_string()),
+        };
+        let src_output = match self.node_by_id(src_node_id)
+            .and_then(|node| node.outputs.get(src_output_index as usize)) {
+            Some(output) => output,
+            None => return ConnectionStatus::Incompatible("Output not found".to_string()),
+        };
+
+        let src_data_type = src_output.effective_data_type();
+        if DataType::can_assign(src_data_type, &dst_input.data_type) {
+            return ConnectionStatus::Ok;
+        }
+
+        match DataType::coercion(src_data_type, &dst_input.data_type) {
+            Some(coercion) => ConnectionStatus::NeedsCoercion(coercion),
+            None => ConnectionStatus::Incompatible(format!(
+                "Cannot assign {} to {}",
+                src_data_type,
+                dst_input.data_type
+            )),
+        }
+    }
+
+    // removes matching single input/single output nodes, binding their consumers and the subgraph
+    // outputs they feed directly to the passthrough's producer; returns the number of removed nodes
+    pub fn remove_passthroughs<F>(&mut self, is_passthrough: F) -> usize
+    where F: Fn(&Node) -> bool
+    {
+        let candidate_ids = self.nodes
+            .iter()
+            .filter(|node| {
+                node.inputs.len() == 1
+                    && node.outputs.len() == 1
+                    && !node.is_output
+                    && is_passthrough(node)
+            })
+            .map(|node| node.id())
+            .collect::<Vec<NodeId>>();
+
+        let mut removed_count = 0;
+        for node_id in candidate_ids {
+            let node = self.node_by_id(node_id).unwrap();
+            let source = match node.inputs[0].binding.as_output_binding() {
+                Some(output_binding) => output_binding.clone(),
+                None => continue,
+            };
+            let source_data_type = self
+                .node_by_id(source.output_node_id).unwrap()
+                .outputs[source.output_index as usize]
+                .effective_data_type()
+                .clone();
+
+            let is_compatible = self.nodes
+                .iter()
+                .flat_map(|node| node.inputs.iter())
+                .filter(|input| match input.binding.as_output_binding() {
+                    Some(output_binding) => output_binding.output_node_id == node_id,
+                    None => false,
+                })
+                .all(|input| DataType::can_assign(&source_data_type, &input.data_type));
+            // subgraph outputs can only be rerouted to a producer inside the same subgraph
+            let source_subgraph_id = self.node_by_id(source.output_node_id).unwrap().subgraph_id;
+            let is_compatible = is_compatible && self.subgraphs
+                .iter()
+                .flat_map(|subgraph| {
+                    subgraph.outputs
+                        .iter()
+                        .map(move |suboutput| (subgraph.id(), suboutput))
+                })
+                .filter(|(_, suboutput)| suboutput.subnode_id == node_id)
+                .all(|(subgraph_id, suboutput)| {
+                    source_subgraph_id == Some(subgraph_id)
+                        && DataType::can_assign(&suboutput.data_type, &source_data_type)
+                });
+            if !is_compatible {
+                continue;
+            }
+
+            // consumers keep their binding behavior, a Once edge into the passthrough makes it Once
+            self.bindings_mut()
+                .filter_map(|edge| edge.binding.as_output_binding_mut())
+                .filter(|output_binding| output_binding.output_node_id == node_id)
+                .for_each(|output_binding| {
+                    output_binding.output_node_id = source.output_node_id;
+                    output_binding.output_index = source.output_index;
+                    if source.behavior == BindingBehavior::Once {
+                        output_binding.behavior = BindingBehavior::Once;
+                    }
+                });
+            self.subgraphs
+                .iter_mut()
+                .flat_map(|subgraph| subgraph.outputs.iter_mut())
+                .filter(|suboutput| suboutput.subnode_id == node_id)
+                .for_each(|suboutput| {
+                    suboutput.subnode_id = source.output_node_id;
+                    suboutput.subnode_output_index = source.output_index;
+                });
+            self.remove_node_by_id(node_id);
+            removed_count += 1;
+        }
+
+        removed_count
+    }
+
+    // collapses nodes computing the same function over the same bindings into one,
+    // rerouting consumers to the survivor; returns the number of removed nodes
+    pub fn dedupe_nodes(&mut self) -> usize {
+        let mut removed_count = 0;
+
+        // removing a duplicate can make its consumers identical, repeat until stable
+        while let Some((survivor_id, duplicate_id)) = self.find_duplicate_nodes() {
+            let output_count = self.node_by_id(duplicate_id).unwrap().outputs.len() as u32;
+            for output_index in 0..output_count {
+                self.reroute_consumers(duplicate_id, output_index, survivor_id, output_index);
+            }
+
+            self.subgraphs
+                .iter_mut()
+                .flat_map(|subgraph| subgraph.outputs.iter_mut())
+                .filter(|suboutput| suboutput.subnode_id == duplicate_id)
+                .for_each(|suboutput| suboutput.subnode_id = survivor_id);
+
+            self.remove_node_by_id(duplicate_id);
+            removed_count += 1;
+        }
+
+        removed_count
+    }
+    fn find_duplicate_nodes(&self) -> Option<(NodeId, NodeId)> {
+        // nodes fed by subgraph inputs get values not described by their bindings
+        let is_candidate = |node: &Node| {
+            !node.is_output
+                && !self.subgraphs
+                .iter()
+                .flat_map(|subgraph| subgraph.inputs.iter())
+                .flat_map(|subinput| subinput.connections.iter())
+                .any(|connection| connection.subnode_id == node.id())
+        };
+
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| is_candidate(node))
+            .find_map(|(index, survivor)| {
+                self.nodes[index + 1..]
+                    .iter()
+                    .filter(|node| is_candidate(node))
+                    .find(|node| survivor.is_duplicate_of(node))
+                    .map(|duplicate| (survivor.id(), duplicate.id()))
+            })
+    }
+
+    // dependencies come before their consumers; among nodes that are ready at the same time
+    // the one with the lower execution priority goes first, then the one with the smaller name,
+    // equal names are ordered by node id, so the order doesn't depend on the order nodes were added in;
+    // compute executes nodes in this order
+    pub fn topological_order(&self) -> anyhow::Result<Vec<NodeId>> {
+        let mut dependency_counts: HashMap<NodeId, usize> = HashMap::new();
+        let mut consumers: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for node in self.nodes.iter() {
+            dependency_counts.insert(node.id(), 0);
+        }
+        for edge in self.bindings() {
+            if let Some(output_binding) = edge.binding.as_output_binding() {
+                *dependency_counts.get_mut(&edge.dst_node_id).unwrap() += 1;
+                consumers
+                    .entry(output_binding.output_node_id)
+                    .or_default()
+                    .push(edge.dst_node_id);
+            }
+        }
+
+        let mut ready: BTreeSet<(i32, &str, NodeId)> = self.nodes
+            .iter()
+            .filter(|node| dependency_counts[&node.id()] == 0)
+            .map(|node| (node.execution_priority, node.name.as_str(), node.id()))
+            .collect();
+
+        let mut order: Vec<NodeId> = Vec::with_capacity(self.nodes.len());
+        while let Some((_, _, node_id)) = ready.pop_first() {
+            order.push(node_id);
+
+            for &consumer_id in consumers.get(&node_id).into_iter().flatten() {
+                let count = dependency_counts.get_mut(&consumer_id).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    let consumer = self.node_by_id(consumer_id).unwrap();
+                    ready.insert((consumer.execution_priority, consumer.name.as_str(), consumer_id));
+                }
+            }
+        }
+
+        if order.len() != self.nodes.len() {
+            return Err(anyhow::Error::msg("Graph contains a cycle"));
+        }
+
+        Ok(order)
+    }
+
+    pub fn set_payload<T: Any + Send + Sync>(&mut self, value: T) {
+        self.payloads.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+    pub fn payload<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.payloads
+            .get(&TypeId::of::<T>())
+            .and_then(|payload| payload.downcast_ref::<T>())
+    }
+    pub fn remove_payload<T: Any + Send + Sync>(&mut self) {
+        self.payloads.remove(&TypeId::of::<T>());
+    }
+
+    // number of nodes using each function
+    pub fn function_usage(&self) -> HashMap<FunctionId, usize> {
+        let mut usage: HashMap<FunctionId, usize> = HashMap::new();
+        self.nodes
+            .iter()
+            .for_each(|node| *usage.entry(node.function_id).or_insert(0) += 1);
+
+        usage
+    }
+
+    // entry points of the graph: nodes not bound to other nodes' outputs,
+    // whose required inputs are all set by constants
+    pub fn source_nodes(&self) -> Vec<&Node> {
+        self.nodes
+            .iter()
+            .filter(|node| {
+                node.inputs
+                    .iter()
+                    .all(|input| match &input.binding {
+                        Binding::None => !input.is_required,
+                        Binding::Const => true,
+                        Binding::Output(_) => false,
+                    })
+            })
+            .collect()
+    }
+
+    // output nodes that can run given only the listed source nodes produce data;
+    // source nodes are nodes without output bindings, only listed ones are treated as available
+    pub fn computable_outputs(&self, available_sources: &[NodeId]) -> Vec<NodeId> {
+        let mut computable: HashMap<NodeId, bool> = HashMap::new();
+
+        self.nodes
+            .iter()
+            .filter(|node| node.is_output)
+            .filter(|node| self.is_computable(node, available_sources, &mut computable))
+            .map(|node| node.id())
+            .collect()
+    }
+    fn is_computable(
+        &self,
+        node: &Node,
+        available_sources: &[NodeId],
+        computable: &mut HashMap<NodeId, bool>,
+    ) -> bool {
+        if let Some(&result) = computable.get(&node.id()) {
+            return result;
+        }
+
+        let is_source = !node.inputs
+            .iter()
+            .any(|input| input.binding.is_output_binding());
+
+        let result = if is_source && !available_sources.contains(&node.id()) {
+            false
+        } else {
+            node.inputs
+                .iter()
+                .all(|input| match &input.binding {
+                    Binding::None => !input.is_required,
+                    Binding::Const => true,
+                    Binding::Output(output_binding) => {
+                        let output_node = self.node_by_id(output_binding.output_node_id).unwrap();
+                        self.is_computable(output_node, available_sources, computable)
+                            || !input.is_required
+                    }
+                })
+        };
+
+        computable.insert(node.id(), result);
+        result
     }
 
     pub fn node_by_name(&self, name: &str) -> Option<&Node> {
@@ -137,22 +712,489 @@ impl Graph {
             .collect()
     }
 
+    // copy with fresh node ids, bindings and subgraph connections are remapped
+    pub fn clone_with_new_ids(&self) -> Graph {
+        let mut graph = self.clone();
+        let id_map: HashMap<NodeId, NodeId> = graph.nodes
+            .iter_mut()
+            .map(|node| {
+                let old_id = node.self_id;
+                node.self_id = NodeId::unique();
+                (old_id, node.self_id)
+            })
+            .collect();
+
+        graph.nodes
+            .iter_mut()
+            .flat_map(|node| node.inputs.iter_mut())
+            .filter_map(|input| input.binding.as_output_binding_mut())
+            .for_each(|output_binding| output_binding.output_node_id = id_map[&output_binding.output_node_id]);
+        for subgraph in graph.subgraphs.iter_mut() {
+            subgraph.inputs
+                .iter_mut()
+                .flat_map(|subinput| subinput.connections.iter_mut())
+                .for_each(|connection| connection.subnode_id = id_map[&connection.subnode_id]);
+            subgraph.outputs
+                .iter_mut()
+                .for_each(|suboutput| suboutput.subnode_id = id_map[&suboutput.subnode_id]);
+        }
+
+        graph
+    }
+
+    // nodes the given one transitively reads from, in storage order, itself excluded
+    pub fn ancestors(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut ancestors: HashSet<NodeId> = HashSet::new();
+        let mut queue: Vec<NodeId> = vec![node_id];
+
+        while let Some(node_id) = queue.pop() {
+            self.node_by_id(node_id).unwrap()
+                .inputs
+                .iter()
+                .filter_map(|input| input.binding.as_output_binding())
+                .for_each(|output_binding| {
+                    if ancestors.insert(output_binding.output_node_id) {
+                        queue.push(output_binding.output_node_id);
+                    }
+                });
+        }
+
+        self.nodes
+            .iter()
+            .map(|node| node.self_id)
+            .filter(|node_id| ancestors.contains(node_id))
+            .collect()
+    }
+
+    // standalone graph with the node and its input cone for bug reports,
+    // the node is the only output so running it reproduces just that node
+    pub fn minimal_subgraph_for(&self, node_id: NodeId) -> anyhow::Result<Graph> {
+        if self.node_by_id(node_id).is_none() {
+            return Err(anyhow::anyhow!("Node {} not found", node_id));
+        }
+
+        let mut node_ids = self.ancestors(node_id);
+        node_ids.push(node_id);
+
+        let mut graph = self.selection(&node_ids)?;
+        graph.nodes
+            .iter_mut()
+            .for_each(|node| node.is_output = node.self_id == node_id);
+
+        Ok(graph)
+    }
+
+    // yaml fragment with the given nodes in display order; bindings to nodes outside
+    // the selection and subgraph membership are dropped
+    pub fn copy_selection(&self, node_ids: &[NodeId]) -> anyhow::Result<String> {
+        self.selection(node_ids)?.to_yaml()
+    }
+    fn selection(&self, node_ids: &[NodeId]) -> anyhow::Result<Graph> {
+        if let Some(node_id) = node_ids.iter().find(|&&node_id| self.node_by_id(node_id).is_none()) {
+            return Err(anyhow::anyhow!("Node {} not found", node_id));
+        }
+
+        let mut fragment = Graph::default();
+        self.nodes_in_order()
+            .into_iter()
+            .filter(|node| node_ids.contains(&node.self_id))
+            .for_each(|node| {
+                let mut node = node.clone();
+                node.subgraph_id = None;
+                node.inputs
+                    .iter_mut()
+                    .filter(|input| {
+                        input.binding
+                            .as_output_binding()
+                            .is_some_and(|output_binding| !node_ids.contains(&output_binding.output_node_id))
+                    })
+                    .for_each(|input| input.unbind());
+                fragment.add_node(node);
+            });
+
+        Ok(fragment)
+    }
+    // inserts a fragment from copy_selection with fresh node ids, after all existing nodes,
+    // moving their editor positions by offset; the graph is left unchanged if the result does not validate.
+    // the fragment is untrusted text, so bindings to nodes outside it are dropped as in selection
+    // and its subgraphs are ignored before ids are remapped
+    pub fn paste(&mut self, fragment: &str, offset: Vec2) -> anyhow::Result<Vec<NodeId>> {
+        let mut fragment = Graph::from_yaml_with_options(fragment, &LoadOptions { validate: false })?;
+        fragment.subgraphs.clear();
+        let fragment_ids: HashSet<NodeId> = fragment.nodes
+            .iter()
+            .map(|node| node.self_id)
+            .collect();
+        fragment.nodes
+            .iter_mut()
+            .flat_map(|node| node.inputs.iter_mut())
+            .filter(|input| {
+                input.binding
+                    .as_output_binding()
+                    .is_some_and(|output_binding| !fragment_ids.contains(&output_binding.output_node_id))
+            })
+            .for_each(|input| input.unbind());
+        let fragment = fragment.clone_with_new_ids();
+
+        let previous = self.clone();
+        let node_ids: Vec<NodeId> = fragment.nodes_in_order()
+            .into_iter()
+            .map(|node| {
+                let mut node = node.clone();
+                node.position += offset;
+                let node_id = node.self_id;
+                self.add_node(node);
+                node_id
+            })
+            .collect();
+
+        if let Err(error) = self.validate() {
+            *self = previous;
+            return Err(error);
+        }
+
+        Ok(node_ids)
+    }
+
+    // same nodes, bindings and subgraphs with node ids and storage order ignored;
+    // nodes are paired by canonical key, the search only backtracks among nodes with equal keys
+    pub fn structurally_eq(&self, other: &Graph) -> bool {
+        if self.nodes.len() != other.nodes.len() || self.subgraphs.len() != other.subgraphs.len() {
+            return false;
+        }
+
+        let keys = self.canonical_keys();
+        let other_keys = other.canonical_keys();
+        let mut sorted_keys: Vec<u64> = keys.values().copied().collect();
+        let mut other_sorted_keys: Vec<u64> = other_keys.values().copied().collect();
+        sorted_keys.sort_unstable();
+        other_sorted_keys.sort_unstable();
+        if sorted_keys != other_sorted_keys {
+            return false;
+        }
+
+        // dependencies first, so most bindings are checked as soon as their consumer is paired
+        let order: Vec<&Node> = match self.topological_order() {
+            Ok(order) => order
+                .iter()
+                .map(|&node_id| self.node_by_id(node_id).unwrap())
+                .collect(),
+            Err(_) => self.nodes.iter().collect(),
+        };
+        let mut id_map: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut paired = vec![false; other.nodes.len()];
+        let keys = (&keys, &other_keys);
+
+        self.pair_nodes(other, &order, keys, &mut id_map, &mut paired)
+    }
+
+    // key shared by nodes that can pair in structurally_eq: function, flags and ordered ports,
+    // refined with the keys of bound nodes in topological order, then with the keys of consumers
+    // in reverse order; graphs with a cycle keep the local part only, there is no order to refine in
+    fn canonical_keys(&self) -> HashMap<NodeId, u64> {
+        let (order, refine) = match self.topological_order() {
+            Ok(order) => (order, true),
+            Err(_) => (self.nodes.iter().map(|node| node.self_id).collect(), false),
+        };
+
+        let mut keys: HashMap<NodeId, u64> = HashMap::with_capacity(order.len());
+        for &node_id in order.iter() {
+            let node = self.node_by_id(node_id).unwrap();
+
+            let mut hasher = DefaultHasher::new();
+            node.function_id.hash(&mut hasher);
+            node.name.hash(&mut hasher);
+            node.behavior.hash(&mut hasher);
+            node.is_output.hash(&mut hasher);
+            node.should_cache_outputs.hash(&mut hasher);
+            node.execution_priority.hash(&mut hasher);
+            node.backend.hash(&mut hasher);
+            node.subgraph_id.hash(&mut hasher);
+            node.instance_of.hash(&mut hasher);
+            for output in node.outputs.iter() {
+                output.name.hash(&mut hasher);
+                output.data_type.hash(&mut hasher);
+                output.data_type_override.hash(&mut hasher);
+            }
+            node.outputs.len().hash(&mut hasher);
+            for input in node.inputs.iter() {
+                input.name.hash(&mut hasher);
+                input.data_type.hash(&mut hasher);
+                input.is_required.hash(&mut hasher);
+                match &input.binding {
+                    Binding::None => 0u8.hash(&mut hasher),
+                    Binding::Const => 1u8.hash(&mut hasher),
+                    Binding::Output(output_binding) => {
+                        2u8.hash(&mut hasher);
+                        output_binding.output_index.hash(&mut hasher);
+                        output_binding.behavior.hash(&mut hasher);
+                        if refine {
+                            // bindings to missing nodes have no key
+                            keys.get(&output_binding.output_node_id).hash(&mut hasher);
+                        }
+                    }
+                }
+            }
+            node.inputs.len().hash(&mut hasher);
+
+            keys.insert(node_id, hasher.finish());
+        }
+
+        if refine {
+            // otherwise equal producers feeding different inputs are told apart here
+            let mut consumer_edges: HashMap<NodeId, Vec<(NodeId, u32, u32)>> = HashMap::new();
+            for edge in self.bindings() {
+                if let Some(output_binding) = edge.binding.as_output_binding() {
+                    consumer_edges
+                        .entry(output_binding.output_node_id)
+                        .or_default()
+                        .push((edge.dst_node_id, edge.dst_input_index, output_binding.output_index));
+                }
+            }
+
+            for node_id in order.iter().rev() {
+                let mut consumers: Vec<(u64, u32, u32)> = consumer_edges
+                    .get(node_id)
+                    .into_iter()
+                    .flatten()
+                    .map(|(consumer_id, input_index, output_index)| (keys[consumer_id], *input_index, *output_index))
+                    .collect();
+                consumers.sort_unstable();
+
+                let mut hasher = DefaultHasher::new();
+                keys[node_id].hash(&mut hasher);
+                consumers.hash(&mut hasher);
+                keys.insert(*node_id, hasher.finish());
+            }
+        }
+
+        keys
+    }
+
+    fn pair_nodes(
+        &self,
+        other: &Graph,
+        order: &[&Node],
+        keys: (&HashMap<NodeId, u64>, &HashMap<NodeId, u64>),
+        id_map: &mut HashMap<NodeId, NodeId>,
+        paired: &mut [bool],
+    ) -> bool {
+        let (node, rest) = match order.split_first() {
+            Some(split) => split,
+            None => return self.subgraphs_eq(other, id_map),
+        };
+        let (keys, other_keys) = keys;
+        let key = keys[&node.self_id];
+
+        for (index, other_node) in other.nodes.iter().enumerate() {
+            if paired[index]
+                || other_keys[&other_node.self_id] != key
+                || !node.eq_ignoring_bindings(other_node) {
+                continue;
+            }
+            id_map.insert(node.self_id, other_node.self_id);
+            paired[index] = true;
+
+            if self.paired_bindings_eq(other, node, other_node, id_map)
+                && self.pair_nodes(other, rest, (keys, other_keys), id_map, paired) {
+                return true;
+            }
+
+            id_map.remove(&node.self_id);
+            paired[index] = false;
+        }
+
+        false
+    }
+
+    // bindings of the new pair and bindings of already paired nodes consuming it
+    fn paired_bindings_eq(
+        &self,
+        other: &Graph,
+        node: &Node,
+        other_node: &Node,
+        id_map: &HashMap<NodeId, NodeId>,
+    ) -> bool {
+        let inputs_eq = |node: &Node, other_node: &Node| {
+            node.inputs
+                .iter()
+                .zip(other_node.inputs.iter())
+                .all(|(input, other_input)| {
+                    self.binding_eq(&input.binding, &other_input.binding, id_map) != Some(false)
+                })
+        };
+
+        inputs_eq(node, other_node)
+            && self.nodes
+            .iter()
+            .filter(|consumer| consumer.self_id != node.self_id)
+            .filter(|consumer| consumer.inputs
+                .iter()
+                .filter_map(|input| input.binding.as_output_binding())
+                .any(|binding| binding.output_node_id == node.self_id))
+            .filter_map(|consumer| id_map
+                .get(&consumer.self_id)
+                .map(|&other_id| (consumer, other.node_by_id(other_id).unwrap())))
+            .all(|(consumer, other_consumer)| inputs_eq(consumer, other_consumer))
+    }
+
+    // None while the bound node is not paired yet
+    fn binding_eq(
+        &self,
+        binding: &Binding,
+        other_binding: &Binding,
+        id_map: &HashMap<NodeId, NodeId>,
+    ) -> Option<bool> {
+        match (binding, other_binding) {
+            (Binding::Output(binding), Binding::Output(other_binding)) => {
+                if binding.output_index != other_binding.output_index
+                    || binding.behavior != other_binding.behavior {
+                    return Some(false);
+                }
+                match id_map.get(&binding.output_node_id) {
+                    Some(&mapped_id) => Some(mapped_id == other_binding.output_node_id),
+                    // bindings to missing nodes only match the same missing id
+                    None if self.node_by_id(binding.output_node_id).is_none() =>
+                        Some(binding.output_node_id == other_binding.output_node_id),
+                    None => None,
+                }
+            }
+            (binding, other_binding) => Some(binding == other_binding),
+        }
+    }
+
+    // subgraphs are paired by id, their node references through the node pairing
+    fn subgraphs_eq(&self, other: &Graph, id_map: &HashMap<NodeId, NodeId>) -> bool {
+        let maps_to = |id: NodeId, other_id: NodeId| id_map.get(&id) == Some(&other_id);
+
+        self.subgraphs
+            .iter()
+            .all(|subgraph| {
+                let other_subgraph = match other.subgraphs
+                    .iter()
+                    .find(|other_subgraph| other_subgraph.id() == subgraph.id()) {
+                    Some(other_subgraph) => other_subgraph,
+                    None => return false,
+                };
+
+                subgraph.name == other_subgraph.name
+                    && subgraph.inputs.len() == other_subgraph.inputs.len()
+                    && subgraph.inputs
+                    .iter()
+                    .zip(other_subgraph.inputs.iter())
+                    .all(|(subinput, other_subinput)| {
+                        subinput.name == other_subinput.name
+                            && subinput.data_type == other_subinput.data_type
+                            && subinput.is_required == other_subinput.is_required
+                            && subinput.connections.len() == other_subinput.connections.len()
+                            && subinput.connections
+                            .iter()
+                            .zip(other_subinput.connections.iter())
+                            .all(|(connection, other_connection)| {
+                                maps_to(connection.subnode_id, other_connection.subnode_id)
+                                    && connection.subnode_input_index == other_connection.subnode_input_index
+                            })
+                    })
+                    && subgraph.outputs.len() == other_subgraph.outputs.len()
+                    && subgraph.outputs
+                    .iter()
+                    .zip(other_subgraph.outputs.iter())
+                    .all(|(suboutput, other_suboutput)| {
+                        suboutput.name == other_suboutput.name
+                            && suboutput.data_type == other_suboutput.data_type
+                            && maps_to(suboutput.subnode_id, other_suboutput.subnode_id)
+                            && suboutput.subnode_output_index == other_suboutput.subnode_output_index
+                    })
+            })
+    }
+
+    // hash of nodes, functions and connections; ignores names and const values;
+    // stable across builds, it is persisted in run snapshots and saved runtime state
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+
+        let write_option_id = |hasher: &mut StableHasher, id: Option<u128>| match id {
+            None => hasher.write_u8(0),
+            Some(id) => {
+                hasher.write_u8(1);
+                hasher.write_u128(id);
+            }
+        };
+
+        for node in self.nodes.iter() {
+            hasher.write_u128(node.self_id.as_u128());
+            hasher.write_u128(node.function_id.as_u128());
+            write_option_id(&mut hasher, node.subgraph_id.map(|id| id.as_u128()));
+            write_option_id(&mut hasher, node.instance_of.map(|id| id.as_u128()));
+            match node.behavior {
+                FunctionBehavior::Active => hasher.write_u8(0),
+                FunctionBehavior::Passive => hasher.write_u8(1),
+                FunctionBehavior::OnInputChange(input_index) => {
+                    hasher.write_u8(2);
+                    hasher.write_u32(input_index);
+                }
+            }
+            hasher.write_bool(node.is_output);
+            hasher.write_i32(node.execution_priority);
+            hasher.write_u8(match node.backend {
+                NodeBackend::Any => 0,
+                NodeBackend::Cpu => 1,
+                NodeBackend::Gpu => 2,
+            });
+
+            hasher.write_u32(node.inputs.len() as u32);
+            for input in node.inputs.iter() {
+                input.data_type.stable_hash(&mut hasher);
+                match &input.binding {
+                    Binding::None => hasher.write_u8(0),
+                    Binding::Const => hasher.write_u8(1),
+                    Binding::Output(output_binding) => {
+                        hasher.write_u8(2);
+                        hasher.write_u128(output_binding.output_node_id.as_u128());
+                        hasher.write_u32(output_binding.output_index);
+                        hasher.write_u8(match output_binding.behavior {
+                            BindingBehavior::Always => 0,
+                            BindingBehavior::Once => 1,
+                        });
+                    }
+                }
+            }
+            hasher.write_u32(node.outputs.len() as u32);
+            for output in node.outputs.iter() {
+                output.data_type.stable_hash(&mut hasher);
+                match &output.data_type_override {
+                    None => hasher.write_u8(0),
+                    Some(data_type) => {
+                        hasher.write_u8(1);
+                        data_type.stable_hash(&mut hasher);
+                    }
+                }
+            }
+        }
+
+        hasher.finish()
+    }
+
     pub fn to_yaml(&self) -> anyhow::Result<String> {
         let yaml = serde_yaml::to_string(&self)?;
         Ok(yaml)
     }
     pub fn from_yaml_file(path: &str) -> anyhow::Result<Graph> {
+        Self::from_yaml_file_with_options(path, &LoadOptions::default())
+    }
+    pub fn from_yaml_file_with_options(path: &str, options: &LoadOptions) -> anyhow::Result<Graph> {
         let yaml = std::fs::read_to_string(path)?;
-        let graph: Graph = serde_yaml::from_str(&yaml)?;
-
-        graph.validate()?;
-
-        Ok(graph)
+        Self::from_yaml_with_options(&yaml, options)
     }
     pub fn from_yaml(yaml: &str) -> anyhow::Result<Graph> {
+        Self::from_yaml_with_options(yaml, &LoadOptions::default())
+    }
+    pub fn from_yaml_with_options(yaml: &str, options: &LoadOptions) -> anyhow::Result<Graph> {
         let graph: Graph = serde_yaml::from_str(yaml)?;
 
-        graph.validate()?;
+        if options.validate {
+            graph.validate()?;
+        }
 
         Ok(graph)
     }
@@ -167,12 +1209,26 @@ impl Graph {
             if let Some(subgraph_id) = node.subgraph_id {
                 self.subgraph_by_id(subgraph_id).ok_or(anyhow::Error::msg("Node has invalid subgraph id"))?;
             }
+            if let FunctionBehavior::OnInputChange(input_index) = node.behavior {
+                if input_index as usize >= node.inputs.len() {
+                    return Err(anyhow::Error::msg("Node watches a non-existent input"));
+                }
+            }
+            if let Some(instance_of) = node.instance_of {
+                self.subgraph_by_id(instance_of).ok_or(anyhow::Error::msg("Node instantiates a non-existent subgraph"))?;
+            }
 
             // validate node has valid bindings
             for input in node.inputs.iter() {
                 if let Binding::Output(output_binding) = &input.binding {
-                    if self.node_by_id(output_binding.output_node_id).is_none() {
-                        return Err(anyhow::Error::msg("Node input connected to a non-existent node"));
+                    let output_node = self.node_by_id(output_binding.output_node_id)
+                        .ok_or(anyhow::Error::msg("Node input connected to a non-existent node"))?;
+
+                    let output = output_node.outputs.get(output_binding.output_index as usize);
+                    if let Some(data_type_override) = output.and_then(|output| output.data_type_override.as_ref()) {
+                        if !DataType::can_assign(data_type_override, &input.data_type) {
+                            return Err(anyhow::Error::msg("Node input connected to an overridden output with an incompatible data type"));
+                        }
                     }
                 }
             }
@@ -190,7 +1246,7 @@ impl Graph {
                     let input = node.inputs.get(connection.subnode_input_index as usize)
                         .ok_or(anyhow::Error::msg("Subgraph input connected to a non-existent input"))?;
 
-                    if !DataType::can_assign(subinput.data_type, input.data_type) {
+                    if !DataType::can_assign(&subinput.data_type, &input.data_type) {
                         return Err(anyhow::Error::msg("Subgraph input connected to a node input with an incompatible data type"));
                     }
                 }
@@ -205,12 +1261,14 @@ impl Graph {
 
                 let output = node.outputs.get(suboutput.subnode_output_index as usize)
                     .ok_or(anyhow::Error::msg("Subgraph output connected to a non-existent output"))?;
-                if !DataType::can_assign(suboutput.data_type, output.data_type) {
+                if !DataType::can_assign(&suboutput.data_type, output.effective_data_type()) {
                     return Err(anyhow::Error::msg("Subgraph output connected to a node output with an incompatible data type"));
                 }
             }
         }
 
+        self.check_subgraph_recursion()?;
+
         Ok(())
     }
 
@@ -221,21 +1279,111 @@ impl Graph {
     pub(crate) fn subgraphs_mut(&mut self) -> &mut Vec<SubGraph> {
         &mut self.subgraphs
     }
+
+    pub fn freeze(self) -> FrozenGraph {
+        FrozenGraph { graph: self }
+    }
+}
+
+impl From<GraphData> for Graph {
+    fn from(data: GraphData) -> Graph {
+        let next_order = data.nodes
+            .iter()
+            .map(|node| node.order + 1)
+            .max()
+            .unwrap_or(0);
+
+        Graph {
+            nodes: data.nodes,
+            subgraphs: data.subgraphs,
+            next_order,
+            payloads: HashMap::new(),
+        }
+    }
+}
+
+impl FrozenGraph {
+    pub fn nodes(&self) -> &[Node] {
+        self.graph.nodes()
+    }
+    pub fn nodes_in_order(&self) -> Vec<&Node> {
+        self.graph.nodes_in_order()
+    }
+    pub fn bindings(&self) -> impl Iterator<Item=EdgeRef<'_>> {
+        self.graph.bindings()
+    }
+    pub fn node_by_id(&self, id: NodeId) -> Option<&Node> {
+        self.graph.node_by_id(id)
+    }
+    pub fn node_by_name(&self, name: &str) -> Option<&Node> {
+        self.graph.node_by_name(name)
+    }
+    pub fn topological_order(&self) -> anyhow::Result<Vec<NodeId>> {
+        self.graph.topological_order()
+    }
+    pub fn payload<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.graph.payload::<T>()
+    }
+    pub fn structural_hash(&self) -> u64 {
+        self.graph.structural_hash()
+    }
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        self.graph.to_yaml()
+    }
+
+    // for preprocess and compute, which only read the graph
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+    pub fn thaw(self) -> Graph {
+        self.graph
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            name: String::new(),
+            data_type: DataType::default(),
+            is_required: is_required_default(),
+            binding: Binding::None,
+            const_value: None,
+        }
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            validate: true,
+        }
+    }
 }
 
 impl Node {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Node {
+        Node::with_id(NodeId::unique())
+    }
+    pub(crate) fn with_id(id: NodeId) -> Node {
+        assert!(!id.is_nil());
+
         Node {
-            self_id: NodeId::unique(),
+            self_id: id,
             function_id: FunctionId::nil(),
             name: "".to_string(),
+            description: None,
             behavior: FunctionBehavior::Active,
             is_output: false,
             should_cache_outputs: false,
+            execution_priority: 0,
+            backend: NodeBackend::Any,
             inputs: vec![],
             outputs: vec![],
             subgraph_id: None,
+            instance_of: None,
+            position: Vec2::ZERO,
+            order: 0,
         }
     }
 
@@ -243,8 +1391,8 @@ impl Node {
         let inputs: Vec<Input> = function.inputs.iter().map(|func_input| {
             Input {
                 name: func_input.name.clone(),
-                data_type: func_input.data_type,
-                is_required: true,
+                data_type: func_input.data_type.clone(),
+                is_required: func_input.is_required,
                 binding: func_input.const_value.as_ref().map_or(Binding::None, |_| Binding::Const),
                 const_value: func_input.const_value.clone(),
             }
@@ -253,7 +1401,8 @@ impl Node {
         let outputs: Vec<Output> = function.outputs.iter().map(|output| {
             Output {
                 name: output.name.clone(),
-                data_type: output.data_type,
+                data_type: output.data_type.clone(),
+                data_type_override: None,
             }
         }).collect();
 
@@ -261,18 +1410,150 @@ impl Node {
             self_id: NodeId::unique(),
             function_id: function.id(),
             name: function.name.clone(),
+            description: function.description.clone(),
             behavior: FunctionBehavior::Active,
             should_cache_outputs: false,
             is_output: false,
+            execution_priority: 0,
+            backend: NodeBackend::Any,
             inputs,
             outputs,
             subgraph_id: None,
+            instance_of: None,
+            position: Vec2::ZERO,
+            order: 0,
         }
     }
 
     pub fn id(&self) -> NodeId {
         self.self_id
     }
+    pub fn order(&self) -> u32 {
+        self.order
+    }
+
+    // whether a change of the value bound to the input makes the node recompute;
+    // Once bindings keep the last value, OnInputChange nodes only react to the watched input
+    pub fn propagates_change(&self, input_index: usize) -> bool {
+        let is_watched = match self.behavior {
+            FunctionBehavior::OnInputChange(watched_index) => watched_index as usize == input_index,
+            _ => true,
+        };
+
+        is_watched
+            && self.inputs[input_index].binding
+            .as_output_binding()
+            .is_some_and(|output_binding| output_binding.behavior == BindingBehavior::Always)
+    }
+
+    // everything but ids and the nodes bound to, those depend on the pairing
+    fn eq_ignoring_bindings(&self, other: &Node) -> bool {
+        self.function_id == other.function_id
+            && self.name == other.name
+            && self.behavior == other.behavior
+            && self.is_output == other.is_output
+            && self.should_cache_outputs == other.should_cache_outputs
+            && self.execution_priority == other.execution_priority
+            && self.backend == other.backend
+            && self.subgraph_id == other.subgraph_id
+            && self.instance_of == other.instance_of
+            && self.outputs.len() == other.outputs.len()
+            && self.outputs
+            .iter()
+            .zip(other.outputs.iter())
+            .all(|(output, other_output)| {
+                output.name == other_output.name
+                    && output.data_type == other_output.data_type
+                    && output.data_type_override == other_output.data_type_override
+            })
+            && self.inputs.len() == other.inputs.len()
+            && self.inputs
+            .iter()
+            .zip(other.inputs.iter())
+            .all(|(input, other_input)| {
+                input.binding.is_output_binding() == other_input.binding.is_output_binding()
+                    && input.name == other_input.name
+                    && input.data_type == other_input.data_type
+                    && input.is_required == other_input.is_required
+                    && input.const_value == other_input.const_value
+            })
+    }
+
+    // active nodes without output bound inputs read their own invoke context or time,
+    // so two of them compute different values even with equal consts
+    fn is_duplicate_of(&self, other: &Node) -> bool {
+        let is_stateful = |node: &Node| {
+            node.behavior == FunctionBehavior::Active
+                && !node.inputs.iter().any(|input| input.binding.is_output_binding())
+        };
+
+        !is_stateful(self)
+            && self.function_id == other.function_id
+            && self.behavior == other.behavior
+            && self.should_cache_outputs == other.should_cache_outputs
+            && self.backend == other.backend
+            && self.subgraph_id == other.subgraph_id
+            && self.instance_of == other.instance_of
+            && self.outputs.len() == other.outputs.len()
+            && self.outputs
+            .iter()
+            .zip(other.outputs.iter())
+            .all(|(output, other_output)| output.data_type_override == other_output.data_type_override)
+            && self.inputs.len() == other.inputs.len()
+            && self.inputs
+            .iter()
+            .zip(other.inputs.iter())
+            .all(|(input, other_input)| {
+                input.binding == other_input.binding
+                    && input.const_value == other_input.const_value
+            })
+    }
+
+    // only safe for nodes not yet bound to others,
+    // use Graph::remove_node_input and Graph::remove_node_output otherwise
+    pub fn add_input(&mut self, input: Input) -> u32 {
+        self.inputs.push(input);
+        self.inputs.len() as u32 - 1
+    }
+    pub fn remove_input(&mut self, index: u32) -> anyhow::Result<Input> {
+        if index as usize >= self.inputs.len() {
+            return Err(anyhow::Error::msg("Input index out of range"));
+        }
+
+        // the watched input follows the shift, a node watching the removed one turns passive
+        if let FunctionBehavior::OnInputChange(watched_index) = self.behavior {
+            if watched_index == index {
+                self.behavior = FunctionBehavior::Passive;
+            } else if watched_index > index {
+                self.behavior = FunctionBehavior::OnInputChange(watched_index - 1);
+            }
+        }
+
+        Ok(self.inputs.remove(index as usize))
+    }
+    pub fn add_output(&mut self, output: Output) -> u32 {
+        self.outputs.push(output);
+        self.outputs.len() as u32 - 1
+    }
+    pub fn remove_output(&mut self, index: u32) -> anyhow::Result<Output> {
+        if index as usize >= self.outputs.len() {
+            return Err(anyhow::Error::msg("Output index out of range"));
+        }
+        Ok(self.outputs.remove(index as usize))
+    }
+}
+
+impl Output {
+    pub fn effective_data_type(&self) -> &DataType {
+        self.data_type_override.as_ref().unwrap_or(&self.data_type)
+    }
+}
+
+impl Input {
+    pub fn unbind(&mut self) {
+        self.binding = self.const_value.as_ref()
+            .map_or(Binding::None, |_| Binding::Const);
+    }
 }
 
 impl Binding {
@@ -280,6 +1561,7 @@ impl Binding {
         Binding::Output(OutputBinding {
             output_node_id,
             output_index,
+            behavior: BindingBehavior::Always,
         })
     }
 
@@ -311,11 +1593,16 @@ impl Binding {
     }
 }
 
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
 impl FunctionBehavior {
     pub fn toggle(&mut self) {
         *self = match *self {
             FunctionBehavior::Active => FunctionBehavior::Passive,
             FunctionBehavior::Passive => FunctionBehavior::Active,
+            FunctionBehavior::OnInputChange(_) => FunctionBehavior::Active,
         };
     }
 }
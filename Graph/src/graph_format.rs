@@ -0,0 +1,56 @@
+use crate::graph::Graph;
+
+// pluggable file format for Graph::save_with and Graph::load_with;
+// the format has to be self-describing, as fields at their defaults are skipped
+// during serialization: yaml, json or MessagePack with field names work,
+// positional formats such as bincode or postcard can't read the graph back
+pub trait GraphFormat {
+    fn serialize(&self, graph: &Graph) -> anyhow::Result<Vec<u8>>;
+    fn deserialize(&self, bytes: &[u8]) -> anyhow::Result<Graph>;
+}
+
+#[derive(Default)]
+pub struct YamlFormat {}
+
+#[derive(Default)]
+pub struct JsonFormat {}
+
+
+impl GraphFormat for YamlFormat {
+    fn serialize(&self, graph: &Graph) -> anyhow::Result<Vec<u8>> {
+        let yaml = serde_yaml::to_string(graph)?;
+        Ok(yaml.into_bytes())
+    }
+    fn deserialize(&self, bytes: &[u8]) -> anyhow::Result<Graph> {
+        let graph: Graph = serde_yaml::from_slice(bytes)?;
+        Ok(graph)
+    }
+}
+
+impl GraphFormat for JsonFormat {
+    fn serialize(&self, graph: &Graph) -> anyhow::Result<Vec<u8>> {
+        let json = serde_json::to_vec_pretty(graph)?;
+        Ok(json)
+    }
+    fn deserialize(&self, bytes: &[u8]) -> anyhow::Result<Graph> {
+        let graph: Graph = serde_json::from_slice(bytes)?;
+        Ok(graph)
+    }
+}
+
+impl Graph {
+    pub fn save_with<F: GraphFormat>(&self, format: &F, path: &str) -> anyhow::Result<()> {
+        let bytes = format.serialize(self)?;
+        std::fs::write(path, bytes)?;
+
+        Ok(())
+    }
+    pub fn load_with<F: GraphFormat>(format: &F, path: &str) -> anyhow::Result<Graph> {
+        let bytes = std::fs::read(path)?;
+        let graph = format.deserialize(&bytes)?;
+
+        graph.validate()?;
+
+        Ok(graph)
+    }
+}
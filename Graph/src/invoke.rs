@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::backend::TransitionKind;
 use crate::data::Value;
 use crate::functions::FunctionId;
 use crate::runtime_graph::InvokeContext;
@@ -20,6 +22,38 @@ pub trait Invoker {
         inputs: &InvokeArgs,
         outputs: &mut InvokeArgs,
     ) -> anyhow::Result<()>;
+
+    // converts an output value for consumers on the other backend, called on the invoker
+    // of the Gpu side function once per run and output; values pass through unchanged by default
+    fn transition(
+        &self,
+        _function_id: FunctionId,
+        _kind: TransitionKind,
+        value: &Value,
+    ) -> anyhow::Result<Value> {
+        Ok(value.clone())
+    }
+}
+
+// lets several Computes, e.g. one per thread, share a ThreadSafeInvoker
+impl<T: Invoker + ?Sized> Invoker for Arc<T> {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        (**self).all_functions()
+    }
+
+    fn invoke(&self,
+              function_id: FunctionId,
+              ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        (**self).invoke(function_id, ctx, inputs, outputs)
+    }
+
+    fn transition(&self, function_id: FunctionId, kind: TransitionKind, value: &Value) -> anyhow::Result<Value> {
+        (**self).transition(function_id, kind, value)
+    }
 }
 
 
@@ -8,6 +8,7 @@ mod tests;
 pub mod common;
 pub mod preprocess;
 pub mod graph;
+pub mod graph_format;
 pub mod functions;
 pub mod compute;
 pub mod lua_invoker;
@@ -15,4 +16,13 @@ pub mod data;
 pub mod runtime_graph;
 pub mod subgraph;
 pub mod invoke;
+pub mod thread_safe_invoker;
+pub mod call_log;
+pub mod run_snapshot;
+pub mod run_debouncer;
+pub mod runner;
+pub mod lint;
+pub mod session;
+pub mod type_inference;
+pub mod backend;
 
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::graph::{Binding, Graph, NodeId};
+use crate::subgraph::SubGraphId;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintSeverity {
+    Warning,
+    Info,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintKind {
+    // not connected to any output node, never executed
+    UnreachableNode,
+    // output not bound to any input or subgraph output
+    DanglingOutput,
+    DuplicateName,
+    // subgraph without member nodes and instances
+    UnusedSubGraph,
+    // every input is optional and unbound
+    UnboundOptionalInputs,
+}
+
+// non-fatal problem found by Graph::lint, unlike errors from Graph::validate
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub node_ids: Vec<NodeId>,
+    pub subgraph_id: Option<SubGraphId>,
+}
+
+impl Graph {
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints: Vec<Lint> = Vec::new();
+
+        self.lint_unreachable_nodes(&mut lints);
+        self.lint_dangling_outputs(&mut lints);
+        self.lint_duplicate_names(&mut lints);
+        self.lint_unused_subgraphs(&mut lints);
+        self.lint_unbound_optional_inputs(&mut lints);
+
+        lints
+    }
+
+    fn lint_unreachable_nodes(&self, lints: &mut Vec<Lint>) {
+        let mut reachable: HashSet<NodeId> = HashSet::new();
+        let mut queue: Vec<NodeId> = self.nodes()
+            .iter()
+            .filter(|node| node.is_output)
+            .map(|node| node.id())
+            .collect();
+
+        while let Some(node_id) = queue.pop() {
+            if !reachable.insert(node_id) {
+                continue;
+            }
+            let node = self.node_by_id(node_id).unwrap();
+            node.inputs
+                .iter()
+                .filter_map(|input| input.binding.as_output_binding())
+                .for_each(|output_binding| queue.push(output_binding.output_node_id));
+        }
+
+        self.nodes()
+            .iter()
+            .filter(|node| !reachable.contains(&node.id()))
+            .for_each(|node| lints.push(Lint {
+                kind: LintKind::UnreachableNode,
+                severity: LintSeverity::Warning,
+                message: format!("Node {} is not connected to any output node", node.name),
+                node_ids: vec![node.id()],
+                subgraph_id: None,
+            }));
+    }
+
+    fn lint_dangling_outputs(&self, lints: &mut Vec<Lint>) {
+        let mut used_outputs: HashSet<(NodeId, u32)> = self.nodes()
+            .iter()
+            .flat_map(|node| node.inputs.iter())
+            .filter_map(|input| input.binding.as_output_binding())
+            .map(|output_binding| (output_binding.output_node_id, output_binding.output_index))
+            .collect();
+        self.subgraphs()
+            .iter()
+            .flat_map(|subgraph| subgraph.outputs.iter())
+            .for_each(|suboutput| {
+                used_outputs.insert((suboutput.subnode_id, suboutput.subnode_output_index));
+            });
+
+        for node in self.nodes().iter() {
+            for (index, output) in node.outputs.iter().enumerate() {
+                if used_outputs.contains(&(node.id(), index as u32)) {
+                    continue;
+                }
+                lints.push(Lint {
+                    kind: LintKind::DanglingOutput,
+                    severity: LintSeverity::Info,
+                    message: format!("Output {} of node {} is not used", output.name, node.name),
+                    node_ids: vec![node.id()],
+                    subgraph_id: None,
+                });
+            }
+        }
+    }
+
+    fn lint_duplicate_names(&self, lints: &mut Vec<Lint>) {
+        let mut names: Vec<&str> = Vec::new();
+        let mut nodes_by_name: HashMap<&str, Vec<NodeId>> = HashMap::new();
+        for node in self.nodes().iter() {
+            let node_ids = nodes_by_name.entry(node.name.as_str()).or_default();
+            if node_ids.is_empty() {
+                names.push(node.name.as_str());
+            }
+            node_ids.push(node.id());
+        }
+
+        for name in names {
+            let node_ids = nodes_by_name.remove(name).unwrap();
+            if node_ids.len() < 2 {
+                continue;
+            }
+            lints.push(Lint {
+                kind: LintKind::DuplicateName,
+                severity: LintSeverity::Warning,
+                message: format!("{} nodes are named {}", node_ids.len(), name),
+                node_ids,
+                subgraph_id: None,
+            });
+        }
+    }
+
+    fn lint_unused_subgraphs(&self, lints: &mut Vec<Lint>) {
+        for subgraph in self.subgraphs().iter() {
+            let is_used = self.nodes()
+                .iter()
+                .any(|node| {
+                    node.subgraph_id == Some(subgraph.id())
+                        || node.instance_of == Some(subgraph.id())
+                });
+            if is_used {
+                continue;
+            }
+            lints.push(Lint {
+                kind: LintKind::UnusedSubGraph,
+                severity: LintSeverity::Warning,
+                message: format!("Subgraph {} has no nodes and is not instantiated", subgraph.name),
+                node_ids: Vec::new(),
+                subgraph_id: Some(subgraph.id()),
+            });
+        }
+    }
+
+    fn lint_unbound_optional_inputs(&self, lints: &mut Vec<Lint>) {
+        self.nodes()
+            .iter()
+            .filter(|node| {
+                !node.inputs.is_empty()
+                    && node.inputs
+                    .iter()
+                    .all(|input| !input.is_required && input.binding == Binding::None)
+            })
+            .for_each(|node| lints.push(Lint {
+                kind: LintKind::UnboundOptionalInputs,
+                severity: LintSeverity::Info,
+                message: format!("None of the optional inputs of node {} are bound", node.name),
+                node_ids: vec![node.id()],
+                subgraph_id: None,
+            }));
+    }
+}
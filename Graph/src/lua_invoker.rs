@@ -1,9 +1,10 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 use std::str::FromStr;
 
 use mlua::{Error, Function, Lua, Table, Variadic};
+use uuid::Uuid;
 
 use crate::{data, functions};
 use crate::data::DataType;
@@ -12,9 +13,12 @@ use crate::graph::{Binding, Graph, Input, Node, NodeId, Output};
 use crate::invoke::{InvokeArgs, Invoker};
 use crate::runtime_graph::InvokeContext;
 
-#[derive(Default)]
+const DEFAULT_MAX_OUTPUT_LINES: usize = 1024;
+
 struct Cache {
-    output_stream: Vec<String>,
+    // last printed lines, older ones are dropped once max_output_lines is reached
+    output_stream: VecDeque<String>,
+    max_output_lines: usize,
 }
 
 struct LuaFuncInfo {
@@ -29,21 +33,39 @@ struct FuncConnections {
     outputs: Vec<u32>,
 }
 
-pub(crate) struct LuaInvoker {
+pub struct LuaInvoker {
     lua: &'static Lua,
     cache: Rc<RefCell<Cache>>,
     funcs: HashMap<FunctionId, LuaFuncInfo>,
+    // globals present before any script is loaded (standard library)
+    builtin_globals: HashSet<String>,
+    // derived from the loaded scripts, ids of mapped nodes are generated in it
+    node_id_namespace: Uuid,
 }
 
+// root of node id namespaces, changing it changes the ids of every mapped graph
+const NODE_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1d_42a8_93c5_4e0b_b7a1_5d2e_8c90_3f64);
+
 impl Default for LuaInvoker {
     fn default() -> Self {
         let lua = Box::new(Lua::new());
         let lua: &'static Lua = Box::leak(lua);
 
+        let builtin_globals = lua.globals()
+            .pairs::<String, mlua::Value>()
+            .filter_map(|pair| pair.ok())
+            .map(|(name, _)| name)
+            .collect::<HashSet<String>>();
+
         LuaInvoker {
             lua,
-            cache: Rc::new(RefCell::new(Cache::default())),
+            cache: Rc::new(RefCell::new(Cache {
+                output_stream: VecDeque::new(),
+                max_output_lines: DEFAULT_MAX_OUTPUT_LINES,
+            })),
             funcs: HashMap::new(),
+            builtin_globals,
+            node_id_namespace: NODE_ID_NAMESPACE,
         }
     }
 }
@@ -79,13 +101,17 @@ impl LuaInvoker {
                 }
 
                 let mut cache = cache.borrow_mut();
-                cache.output_stream.push(output);
+                if cache.output_stream.len() == cache.max_output_lines {
+                    cache.output_stream.pop_front();
+                }
+                cache.output_stream.push_back(output);
                 Ok(())
             }
         ).unwrap();
         self.lua.globals().set("print", print_function)?;
 
         self.lua.load(script).exec()?;
+        self.node_id_namespace = Uuid::new_v5(&self.node_id_namespace, script.as_bytes());
 
         self.read_function_info()?;
 
@@ -114,6 +140,7 @@ impl LuaInvoker {
 
         let mut function_info = functions::Function::new(FunctionId::from_str(&id_str)?);
         function_info.name = table.get("name")?;
+        function_info.description = table.get("desc")?;
         function_info.inputs = Vec::new();
         function_info.outputs = Vec::new();
 
@@ -122,14 +149,18 @@ impl LuaInvoker {
             let input: Table = inputs.get(i).unwrap();
             let name: String = input.get(1).unwrap();
             let data_type_name: String = input.get(2).unwrap();
-            let data_type = data_type_name.parse::<DataType>().unwrap();
+            let data_type = Self::parse_data_type(&data_type_name)?;
 
             let mut default_value: Option<data::Value> = None;
             if input.len()? > 2 {
                 default_value = None;
             }
 
-            function_info.inputs.push(functions::InputInfo { name, data_type, const_value: default_value });
+            // inputs are declared as { name, type, optional = true }
+            let is_optional: Option<bool> = input.get("optional")?;
+            let is_required = is_optional.map_or(functions::is_required_default(), |is_optional| !is_optional);
+
+            function_info.inputs.push(functions::InputInfo { name, data_type, is_required, const_value: default_value });
         }
 
         let outputs: Table = table.get("outputs")?;
@@ -137,31 +168,57 @@ impl LuaInvoker {
             let output: Table = outputs.get(i).unwrap();
             let name: String = output.get(1).unwrap();
             let data_type_name: String = output.get(2).unwrap();
-            let data_type = data_type_name.parse::<DataType>().unwrap();
+            let data_type = Self::parse_data_type(&data_type_name)?;
 
             function_info.outputs.push(functions::OutputInfo { name, data_type });
         }
 
         Ok(function_info)
     }
+    // name-based uuid, the same for the same scripts on every run and toolchain;
+    // graphs mapped from different scripts get different ids
+    fn node_id(&self, function_id: FunctionId, call_index: usize) -> NodeId {
+        let mut name = function_id.as_u128().to_le_bytes().to_vec();
+        name.extend_from_slice(&(call_index as u64).to_le_bytes());
 
-    pub fn map_graph(&self) -> anyhow::Result<Graph> {
-        let connections = self.substitute_functions();
+        NodeId::from_u128(Uuid::new_v5(&self.node_id_namespace, &name).as_u128())
+    }
+    fn parse_data_type(data_type_name: &str) -> anyhow::Result<DataType> {
+        data_type_name.parse::<DataType>()
+            .map_err(|_| {
+                let names = DataType::all()
+                    .iter()
+                    .map(|data_type| data_type.name())
+                    .collect::<Vec<&str>>();
+                anyhow::anyhow!("Unknown data type '{}', expected one of: {} or array<type>", data_type_name, names.join(", "))
+            })
+    }
 
-        self.restore_functions();
+    pub fn map_graph(&self) -> anyhow::Result<Graph> {
+        let graph_function = self.graph_function()?;
 
-        let graph_function: Function = self.lua.globals().get("graph").unwrap();
-        graph_function.call::<_, ()>(()).unwrap();
+        // functions are restored even if the graph function failed
+        let connections = self.substitute_functions(&graph_function);
+        self.restore_functions()?;
+        let connections = connections?;
 
-        let graph = self.create_graph(connections);
+        graph_function.call::<_, ()>(())
+            .map_err(|err| anyhow::anyhow!("Lua graph function failed: {}", err))?;
 
-        Ok(graph)
+        self.create_graph(connections)
+    }
+    fn graph_function(&self) -> anyhow::Result<Function<'static>> {
+        self.lua.globals()
+            .get::<_, Option<Function>>("graph")?
+            .ok_or(anyhow::anyhow!("Lua script has no graph function"))
     }
 
-    fn substitute_functions(&self) -> Vec<FuncConnections> {
+    fn substitute_functions(&self, graph_function: &Function) -> anyhow::Result<Vec<FuncConnections>> {
         let connections: Rc<RefCell<Vec<FuncConnections>>> = Rc::new(RefCell::new(Vec::new()));
 
-        let functions = self.get_all_functions();
+        // sorted, so output indexes don't depend on hash map order
+        let mut functions = self.get_all_functions();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
 
         let mut output_index: u32 = 0;
 
@@ -195,55 +252,67 @@ impl LuaInvoker {
 
                     Ok(result)
                 }
-            ).unwrap();
+            )?;
 
-            self.lua.globals().set(lua_func_info.name.clone(), new_function).unwrap();
+            self.lua.globals().set(lua_func_info.name.clone(), new_function)?;
 
             output_index += lua_func_info.outputs.len() as u32;
         }
 
-        let graph_function: Function = self.lua.globals().get("graph").unwrap();
-        graph_function.call::<_, ()>(()).unwrap();
+        graph_function.call::<_, ()>(())
+            .map_err(|err| anyhow::anyhow!("Lua graph function failed: {}", err))?;
 
-        connections.take()
+        Ok(connections.take())
     }
-    fn restore_functions(&self) {
+    fn restore_functions(&self) -> anyhow::Result<()> {
         let functions = self.funcs.values().collect::<Vec<&LuaFuncInfo>>();
 
         for lua_func_info in functions.iter() {
             self.lua.globals().set(
                 lua_func_info.info.name.clone(),
                 lua_func_info.lua_func.clone(),
-            ).unwrap();
+            )?;
         }
+
+        Ok(())
     }
-    fn create_graph(&self, mut connections: Vec<FuncConnections>) -> Graph {
+    fn create_graph(&self, mut connections: Vec<FuncConnections>) -> anyhow::Result<Graph> {
         let mut graph = Graph::default();
 
         struct OutputAddr {
             index: u32,
             node_id: NodeId,
+            function_name: String,
+            data_type: DataType,
         }
         let mut output_ids: HashMap<u32, OutputAddr> = HashMap::new();
         let mut nodes: Vec<Node> = Vec::new();
 
-        for connection in connections.iter() {
+        for (call_index, connection) in connections.iter().enumerate() {
             let function = &self.funcs
-                .iter()
-                .find(|(_, func)| func.info.name == connection.name)
-                .unwrap()
-                .1.info;
-            nodes.push(Node::new());
+                .values()
+                .find(|func| func.info.name == connection.name)
+                .ok_or_else(|| anyhow::anyhow!("Graph references unknown function {}", connection.name))?
+                .info;
+            if connection.inputs.len() > function.inputs.len() {
+                return Err(anyhow::anyhow!(
+                    "Function {} called with {} arguments, expects {}",
+                    function.name,
+                    connection.inputs.len(),
+                    function.inputs.len()
+                ));
+            }
+            nodes.push(Node::with_id(self.node_id(function.id(), call_index)));
             let node = nodes.last_mut().unwrap();
 
             node.name = function.name.clone();
+            node.description = function.description.clone();
 
-            for (i, _input_id) in connection.inputs.iter().enumerate() {
-                let input = function.inputs.get(i).unwrap();
+            for input in function.inputs.iter().take(connection.inputs.len()) {
                 node.inputs.push(Input {
                     name: input.name.clone(),
-                    data_type: input.data_type,
-                    is_required: true,
+                    data_type: input.data_type.clone(),
+                    is_required: input.is_required,
                     binding: Binding::None,
                     const_value: None,
                 });
@@ -252,13 +321,16 @@ impl LuaInvoker {
                 let output = function.outputs.get(i).unwrap();
                 node.outputs.push(Output {
                     name: output.name.clone(),
-                    data_type: output.data_type,
+                    data_type: output.data_type.clone(),
+                    data_type_override: None,
                 });
 
                 assert!(!node.id().is_nil());
                 output_ids.insert(output_id, OutputAddr {
                     index: i as u32,
                     node_id: node.id(),
+                    function_name: function.name.clone(),
+                    data_type: output.data_type.clone(),
                 });
             }
         }
@@ -268,7 +340,23 @@ impl LuaInvoker {
 
             for (input_index, output_id) in connection.inputs.iter().enumerate() {
                 let input = &mut node.inputs[input_index];
-                let output_addr = output_ids.get(output_id).unwrap();
+                let output_addr = output_ids.get(output_id)
+                    .ok_or_else(|| anyhow::anyhow!(
+                        "Input {} of function {} is bound to an unknown output",
+                        input.name,
+                        connection.name
+                    ))?;
+                if !DataType::can_assign(&output_addr.data_type, &input.data_type) {
+                    return Err(anyhow::anyhow!(
+                        "Output {} of function {} is {}, but input {} of function {} expects {}",
+                        output_addr.index,
+                        output_addr.function_name,
+                        output_addr.data_type,
+                        input.name,
+                        connection.name,
+                        input.data_type
+                    ));
+                }
 
                 input.binding = Binding::from_output_binding(output_addr.node_id, output_addr.index)
             }
@@ -276,22 +364,54 @@ impl LuaInvoker {
             graph.add_node(node);
         }
 
-        assert!(graph.validate().is_ok());
+        graph.validate()?;
 
-        graph
+        Ok(graph)
     }
 
 
     pub fn get_output(&self) -> String {
         let mut cache = self.cache.borrow_mut();
-        let result = cache.output_stream.join("\n");
-        cache.output_stream.clear();
+        let result = cache.output_stream
+            .drain(..)
+            .collect::<Vec<String>>()
+            .join("\n");
         result
     }
+    pub fn set_max_output_lines(&mut self, max_output_lines: usize) {
+        assert_ne!(max_output_lines, 0);
+
+        let mut cache = self.cache.borrow_mut();
+        cache.max_output_lines = max_output_lines;
+        while cache.output_stream.len() > max_output_lines {
+            cache.output_stream.pop_front();
+        }
+    }
 
     pub fn get_all_functions(&self) -> Vec<&functions::Function> {
         self.funcs.values().map(|f| &f.info).collect()
     }
+
+    // sorted names of global functions and tables defined by loaded scripts
+    pub fn list_globals(&self) -> Vec<String> {
+        let mut names = self.lua.globals()
+            .pairs::<String, mlua::Value>()
+            .filter_map(|pair| pair.ok())
+            .filter(|(_, value)| matches!(value, mlua::Value::Function(_) | mlua::Value::Table(_)))
+            .map(|(name, _)| name)
+            .filter(|name| !self.builtin_globals.contains(name))
+            .collect::<Vec<String>>();
+        names.sort();
+
+        names
+    }
+
+    pub fn describe_function(&self, name: &str) -> Option<&functions::Function> {
+        self.funcs
+            .values()
+            .map(|f| &f.info)
+            .find(|info| info.name == name)
+    }
 }
 
 
@@ -319,15 +439,32 @@ impl Invoker for LuaInvoker {
 
         let function_info = self.funcs
             .get(&function_id)
-            .unwrap();
+            .ok_or_else(|| anyhow::anyhow!("Function {} is not loaded", function_id))?;
 
         let mut input_args: Variadic<mlua::Value> = Variadic::new();
         for (index, input_info) in function_info.info.inputs.iter().enumerate() {
-            let input = inputs.get(index)
-                .unwrap()
-                .as_ref()
-                .expect("input is required");
-            assert_eq!(input_info.data_type, input.data_type());
+            // unbound optional inputs reach the function as nil
+            let input = match inputs.get(index).and_then(Option::as_ref) {
+                Some(input) => input,
+                None if !input_info.is_required => {
+                    input_args.push(mlua::Value::Nil);
+                    continue;
+                }
+                None => return Err(anyhow::anyhow!(
+                    "Required input {} of function {} has no value",
+                    input_info.name,
+                    function_info.info.name
+                )),
+            };
+            if !input.has_type(&input_info.data_type) {
+                return Err(anyhow::anyhow!(
+                    "Input {} of function {} expects {}, got {}",
+                    input_info.name,
+                    function_info.info.name,
+                    input_info.data_type,
+                    input.data_type()
+                ));
+            }
 
             let invoke_value = to_lua_value(self.lua, input)?;
             input_args.push(invoke_value);
@@ -338,10 +475,18 @@ impl Invoker for LuaInvoker {
         for (index, output_info) in function_info.info.outputs.iter().enumerate() {
             let output_arg: &mlua::Value = output_args
                 .get(index)
-                .unwrap();
-
-            let output = data::Value::from(output_arg);
-            assert_eq!(output_info.data_type, output.data_type());
+                .ok_or_else(|| anyhow::anyhow!("Function {} returned no value for output {}", function_info.info.name, output_info.name))?;
+
+            let output = data::Value::try_from(output_arg)?;
+            if !output.has_type(&output_info.data_type) {
+                return Err(anyhow::anyhow!(
+                    "Output {} of function {} expects {}, got {}",
+                    output_info.name,
+                    function_info.info.name,
+                    output_info.data_type,
+                    output.data_type()
+                ));
+            }
             outputs[index] = Some(output);
         }
 
@@ -361,18 +506,35 @@ fn to_lua_value<'lua>(lua: &'lua Lua, value: &data::Value) -> anyhow::Result<mlu
             let lua_string = lua.create_string(v)?;
             Ok(mlua::Value::String(lua_string))
         }
+        data::Value::Array(values) => {
+            let lua_values = values
+                .iter()
+                .map(|value| to_lua_value(lua, value))
+                .collect::<anyhow::Result<Vec<mlua::Value>>>()?;
+            Ok(mlua::Value::Table(lua.create_sequence_from(lua_values)?))
+        }
+        data::Value::Image(_) => Err(anyhow::anyhow!("Images can't be passed to Lua functions")),
     }
 }
 
-impl From<&mlua::Value<'_>> for data::Value {
-    fn from(value: &mlua::Value) -> Self {
+impl TryFrom<&mlua::Value<'_>> for data::Value {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &mlua::Value) -> anyhow::Result<Self> {
         match value {
-            mlua::Value::Nil => { data::Value::Null }
-            mlua::Value::Boolean(v) => { (*v).into() }
-            mlua::Value::Integer(v) => { (*v).into() }
-            mlua::Value::Number(v) => { (*v).into() }
-            mlua::Value::String(v) => { v.to_str().unwrap().into() }
-            _ => { panic!("not supported") }
+            mlua::Value::Nil => { Ok(data::Value::Null) }
+            mlua::Value::Boolean(v) => { Ok((*v).into()) }
+            mlua::Value::Integer(v) => { Ok((*v).into()) }
+            mlua::Value::Number(v) => { Ok((*v).into()) }
+            mlua::Value::String(v) => { Ok(v.to_str()?.into()) }
+            mlua::Value::Table(v) => {
+                let values = v.clone()
+                    .sequence_values::<mlua::Value>()
+                    .map(|value| data::Value::try_from(&value?))
+                    .collect::<anyhow::Result<Vec<data::Value>>>()?;
+                Ok(data::Value::Array(values))
+            }
+            _ => Err(anyhow::anyhow!("Lua {} values can't be converted", value.type_name())),
         }
     }
 }
@@ -2,28 +2,35 @@ use std::collections::HashSet;
 use std::mem::take;
 
 use crate::graph::*;
-use crate::runtime_graph::{RuntimeGraph, RuntimeNode};
+use crate::runtime_graph::{MissingInput, MissingInputReason, RuntimeGraph, RuntimeNode};
 
 #[derive(Default)]
 pub struct Preprocess {}
 
 impl Preprocess {
-    pub fn run(&self, graph: &Graph, previous_runtime: &mut RuntimeGraph) -> RuntimeGraph {
+    // fails if the graph contains a cycle, previous_runtime is left untouched in that case
+    pub fn run(&self, graph: &Graph, previous_runtime: &mut RuntimeGraph) -> anyhow::Result<RuntimeGraph> {
         debug_assert!(graph.validate().is_ok());
 
-        let mut r_nodes = self.gather_nodes(graph, previous_runtime);
+        let order = graph.topological_order()?;
+        let mut r_nodes = self.gather_nodes(graph, &order, previous_runtime);
         self.forward_pass(graph, &mut r_nodes);
-        self.backward_pass(graph, &mut r_nodes);
 
-        RuntimeGraph {
+        let mut runtime_graph = RuntimeGraph {
             nodes: r_nodes,
-        }
+            probes: take(&mut previous_runtime.probes),
+        };
+        self.invalidate_changed_params(graph, &mut runtime_graph);
+        self.backward_pass(graph, &mut runtime_graph.nodes);
+
+        Ok(runtime_graph)
     }
 
 
     fn gather_nodes(
         &self,
         graph: &Graph,
+        order: &[NodeId],
         previous_runtime: &mut RuntimeGraph,
     ) -> Vec<RuntimeNode>
     {
@@ -55,11 +62,12 @@ impl Preprocess {
                 });
         }
 
-        active_node_ids.reverse();
-        {
-            let mut set = HashSet::new();
-            active_node_ids.retain(|&x| set.insert(x));
-        }
+        let active_node_ids: HashSet<NodeId> = active_node_ids.into_iter().collect();
+        let active_node_ids = order
+            .iter()
+            .copied()
+            .filter(|node_id| active_node_ids.contains(node_id))
+            .collect::<Vec<NodeId>>();
 
         let r_nodes: Vec<RuntimeNode> = active_node_ids.iter()
             .map(|&node_id| {
@@ -69,28 +77,14 @@ impl Preprocess {
                     previous_runtime
                         .node_by_id_mut(node_id)
                         .map(take)
-                        .unwrap_or_else(|| {
-                            RuntimeNode {
-                                node_id,
-                                name: node.name.clone(),
-                                is_output: node.is_output,
-                                has_missing_inputs: false,
-                                behavior: node.behavior,
-                                should_execute: false,
-                                should_cache_outputs: node.should_cache_outputs,
-                                run_time: 0.0,
-                                invoke_context: Default::default(),
-                                output_values: None,
-                                output_binding_count: vec![0; node.outputs.len()],
-                                total_binding_count: 0,
-                            }
-                        });
+                        .unwrap_or_else(|| RuntimeNode::new(node));
                 assert_eq!(r_node.output_binding_count.len(), node.outputs.len());
                 assert_eq!(r_node.total_binding_count, 0);
                 debug_assert_eq!(r_node.name, node.name);
 
                 r_node.behavior = node.behavior;
                 r_node.has_missing_inputs = false;
+                r_node.missing_inputs.clear();
                 r_node.output_binding_count.fill(0);
                 r_node.should_execute = false;
 
@@ -110,36 +104,68 @@ impl Preprocess {
             let mut r_node = take(&mut r_nodes[index]);
             let node = graph.node_by_id(r_node.node_id).unwrap();
 
-            for input in node.inputs.iter() {
+            for (input_index, input) in node.inputs.iter().enumerate() {
                 match &input.binding {
                     Binding::None => {
-                        r_node.has_missing_inputs |= input.is_required;
+                        if input.is_required {
+                            r_node.missing_inputs.push(MissingInput {
+                                input_index: input_index as u32,
+                                reason: MissingInputReason::Unbound,
+                            });
+                        }
                     }
                     Binding::Const => {}
                     Binding::Output(output_binding) => {
                         let output_r_node = r_nodes[0..index].iter()
                             .find(|&p_node| p_node.node_id == output_binding.output_node_id)
                             .expect("Node not found among already processed ones");
-                        if output_r_node.behavior == FunctionBehavior::Active {
+                        if node.propagates_change(input_index)
+                            && output_r_node.behavior == FunctionBehavior::Active {
                             r_node.behavior = FunctionBehavior::Active;
                         }
-                        r_node.has_missing_inputs |= output_r_node.has_missing_inputs;
+                        if output_r_node.has_missing_inputs {
+                            r_node.missing_inputs.push(MissingInput {
+                                input_index: input_index as u32,
+                                reason: MissingInputReason::UpstreamIncomplete(output_binding.output_node_id),
+                            });
+                        }
                     }
                 }
             }
+            r_node.has_missing_inputs = !r_node.missing_inputs.is_empty();
 
             r_nodes[index] = r_node;
         }
     }
-    // in backward pass, mark active nodes without cached outputs for execution
+    // parameters outside of graph bindings changed since the last execution,
+    // drop outputs of the node and its consumers so the backward pass schedules them
+    fn invalidate_changed_params(&self, graph: &Graph, runtime_graph: &mut RuntimeGraph) {
+        let changed_node_ids: Vec<NodeId> = runtime_graph.nodes
+            .iter()
+            .filter(|r_node| r_node.invoke_context.is_param_hash_changed())
+            .map(|r_node| r_node.node_id)
+            .collect();
+
+        for node_id in changed_node_ids {
+            runtime_graph
+                .affected_by(graph, node_id)
+                .into_iter()
+                .for_each(|node_id| {
+                    runtime_graph.node_by_id_mut(node_id).unwrap().output_values = None;
+                });
+        }
+    }
+    // in backward pass, mark active nodes without cached outputs for execution;
+    // a node is pulled with Always only if every edge on the way from an output node is Always,
+    // a single Once edge makes the whole upstream chain reuse cached outputs where present
     fn backward_pass(&self,
                      graph: &Graph,
                      r_nodes: &mut Vec<RuntimeNode>,
     ) {
-        let mut active_node_ids: Vec<NodeId> = r_nodes.iter()
+        let mut active_node_ids: Vec<(NodeId, BindingBehavior)> = r_nodes.iter()
             .filter_map(|r_node| {
                 if r_node.is_output {
-                    Some(r_node.node_id)
+                    Some((r_node.node_id, BindingBehavior::Always))
                 } else {
                     None
                 }
@@ -151,20 +177,33 @@ impl Preprocess {
             index += 1;
             let index = index - 1;
 
-            let node_id = active_node_ids[index];
+            let (node_id, edge_behavior) = active_node_ids[index];
             let node = graph.node_by_id(node_id).unwrap();
             let r_node =
                 r_nodes
                     .iter_mut()
                     .find(|r_node| r_node.node_id == node_id).unwrap();
 
+            // already reached through another consumer, inputs are accounted for
+            if r_node.should_execute {
+                continue;
+            }
+
             if r_node.is_output {
-                r_node.should_execute = true;
+                // an output node that is also a source has nothing upstream to change,
+                // so it only re-executes when active
+                let is_source = !node.inputs
+                    .iter()
+                    .any(|input| input.binding.is_output_binding());
+                r_node.should_execute = !is_source
+                    || r_node.output_values.is_none()
+                    || r_node.behavior == FunctionBehavior::Active;
             } else if r_node.output_values.is_none() {
                 r_node.should_execute = true;
             } else if r_node.should_cache_outputs {
                 r_node.should_execute = false;
-            } else if r_node.behavior == FunctionBehavior::Active {
+            } else if r_node.behavior == FunctionBehavior::Active
+                && edge_behavior == BindingBehavior::Always {
                 r_node.should_execute = true;
             } else {
                 r_node.should_execute = false;
@@ -174,7 +213,14 @@ impl Preprocess {
                 node.inputs.iter()
                     .for_each(|input| {
                         if let Binding::Output(output_binding) = &input.binding {
-                            active_node_ids.push(output_binding.output_node_id);
+                            let output_edge_behavior =
+                                if edge_behavior == BindingBehavior::Always
+                                    && output_binding.behavior == BindingBehavior::Always {
+                                    BindingBehavior::Always
+                                } else {
+                                    BindingBehavior::Once
+                                };
+                            active_node_ids.push((output_binding.output_node_id, output_edge_behavior));
                             let output_r_node =
                                 r_nodes
                                     .iter_mut()
@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+// coalesces rapid run requests, e.g. while dragging a slider in the editor;
+// poll is expected to be called every frame and reports when a run is due
+pub struct RunDebouncer {
+    quiet_period: Duration,
+    last_request: Option<Instant>,
+}
+
+impl RunDebouncer {
+    pub fn new(quiet_period: Duration) -> RunDebouncer {
+        RunDebouncer {
+            quiet_period,
+            last_request: None,
+        }
+    }
+
+    pub fn request_run(&mut self) {
+        self.request_run_at(Instant::now());
+    }
+    pub fn request_run_at(&mut self, now: Instant) {
+        self.last_request = Some(now);
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.last_request.is_some()
+    }
+
+    // true once per burst of requests, after no new requests came in for the quiet period
+    pub fn poll(&mut self) -> bool {
+        self.poll_at(Instant::now())
+    }
+    pub fn poll_at(&mut self, now: Instant) -> bool {
+        match self.last_request {
+            Some(last_request) if now.duration_since(last_request) >= self.quiet_period => {
+                self.last_request = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // true if a run was pending, without waiting for the quiet period
+    pub fn flush(&mut self) -> bool {
+        self.last_request.take().is_some()
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Value, ValueCompare};
+use crate::graph::NodeId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub node_id: NodeId,
+    pub inputs: Vec<Option<Value>>,
+    pub outputs: Vec<Option<Value>>,
+}
+
+// inputs and outputs of every node executed during a single run, in execution order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunSnapshot {
+    pub structural_hash: u64,
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+
+impl RunSnapshot {
+    pub fn to_yaml(&self) -> anyhow::Result<String> {
+        let yaml = serde_yaml::to_string(&self)?;
+        Ok(yaml)
+    }
+    pub fn from_yaml(yaml: &str) -> anyhow::Result<RunSnapshot> {
+        let snapshot: RunSnapshot = serde_yaml::from_str(yaml)?;
+        Ok(snapshot)
+    }
+
+    // returns the first node whose execution differs between the two runs
+    pub fn diff(&self, other: &RunSnapshot) -> Option<NodeId> {
+        self.diff_with(other, &ValueCompare::default())
+    }
+    pub fn diff_with(&self, other: &RunSnapshot, compare: &ValueCompare) -> Option<NodeId> {
+        let divergent = self.nodes
+            .iter()
+            .zip(other.nodes.iter())
+            .find(|(node, other_node)| {
+                node.node_id != other_node.node_id
+                    || !compare.args_equal(&node.inputs, &other_node.inputs)
+                    || !compare.args_equal(&node.outputs, &other_node.outputs)
+            })
+            .map(|(node, _)| node.node_id);
+        if divergent.is_some() {
+            return divergent;
+        }
+
+        // one run executed more nodes than the other
+        let common_len = self.nodes.len().min(other.nodes.len());
+        self.nodes.get(common_len)
+            .or(other.nodes.get(common_len))
+            .map(|node| node.node_id)
+    }
+}
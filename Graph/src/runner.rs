@@ -0,0 +1,42 @@
+use crate::compute::Compute;
+use crate::data::Value;
+use crate::graph::Graph;
+use crate::lua_invoker::LuaInvoker;
+use crate::preprocess::Preprocess;
+use crate::run_snapshot::RunSnapshot;
+use crate::runtime_graph::RuntimeGraph;
+
+// loads the graph and the Lua functions, runs the graph once
+// and returns the value received by the first input of each executed output node
+pub fn run(graph_path: &str, lua_path: &str) -> anyhow::Result<Vec<(String, Value)>> {
+    let graph = Graph::from_yaml_file(graph_path)?;
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load_file(lua_path)?;
+    let compute = Compute::from(invoker);
+
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    let snapshot = compute.run_snapshot(&graph, &mut runtime_graph)?;
+
+    Ok(output_values(&graph, &snapshot))
+}
+
+// value received by the first input of each output node executed in the snapshot
+pub(crate) fn output_values(graph: &Graph, snapshot: &RunSnapshot) -> Vec<(String, Value)> {
+    snapshot.nodes
+        .iter()
+        .filter_map(|node_snapshot| {
+            let node = graph.node_by_id(node_snapshot.node_id).unwrap();
+            if !node.is_output {
+                return None;
+            }
+
+            let value = node_snapshot.inputs
+                .first()
+                .cloned()
+                .flatten()
+                .unwrap_or_default();
+            Some((node.name.clone(), value))
+        })
+        .collect()
+}
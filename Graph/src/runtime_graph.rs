@@ -3,11 +3,32 @@ use std::any::Any;
 use serde::{Deserialize, Serialize};
 
 use crate::data::Value;
-use crate::graph::{FunctionBehavior, NodeId};
+use crate::graph::{FunctionBehavior, Graph, Node, NodeId};
+use crate::preprocess::Preprocess;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct InvokeContext {
     boxed: Option<Box<dyn Any>>,
+    // 0..1 hint for functions with cheaper variants, lowered by Compute::run_within_budget
+    quality: f32,
+    // hash of parameters that are not graph bindings, e.g. push constants;
+    // the node and its consumers re-execute when it differs from the one of the last execution
+    param_hash: Option<u64>,
+    executed_param_hash: Option<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MissingInputReason {
+    // required input without a binding
+    Unbound,
+    // bound to a node which itself has missing inputs
+    UpstreamIncomplete(NodeId),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MissingInput {
+    pub input_index: u32,
+    pub reason: MissingInputReason,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -17,6 +38,8 @@ pub struct RuntimeNode {
     pub name: String,
     pub is_output: bool,
     pub has_missing_inputs: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) missing_inputs: Vec<MissingInput>,
     pub behavior: FunctionBehavior,
     pub should_execute: bool,
     pub should_cache_outputs: bool,
@@ -31,16 +54,65 @@ pub struct RuntimeNode {
 }
 
 
+// node output captured after every execution of the node
+#[derive(Clone, Debug)]
+pub(crate) struct Probe {
+    pub(crate) node_id: NodeId,
+    pub(crate) output_index: u32,
+    pub(crate) value: Option<Value>,
+}
+
+// what a node computed last time, enough for the next preprocess to skip it;
+// invoke contexts are not persisted
+#[derive(Serialize, Deserialize)]
+struct RuntimeNodeState {
+    node_id: NodeId,
+    run_time: f64,
+    output_values: Option<Vec<Option<Value>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RuntimeState {
+    structural_hash: u64,
+    nodes: Vec<RuntimeNodeState>,
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct RuntimeGraph {
     pub nodes: Vec<RuntimeNode>,
+
+    #[serde(skip)]
+    pub(crate) probes: Vec<Probe>,
 }
 
 
 impl RuntimeNode {
+    pub(crate) fn new(node: &Node) -> RuntimeNode {
+        RuntimeNode {
+            node_id: node.id(),
+            name: node.name.clone(),
+            is_output: node.is_output,
+            has_missing_inputs: false,
+            missing_inputs: Vec::new(),
+            behavior: node.behavior,
+            should_execute: false,
+            should_cache_outputs: node.should_cache_outputs,
+            run_time: 0.0,
+            invoke_context: Default::default(),
+            output_values: None,
+            output_binding_count: vec![0; node.outputs.len()],
+            total_binding_count: 0,
+        }
+    }
     pub fn node_id(&self) -> NodeId {
         self.node_id
     }
+    pub fn missing_inputs(&self) -> &[MissingInput] {
+        &self.missing_inputs
+    }
+    pub fn invoke_context_mut(&mut self) -> &mut InvokeContext {
+        &mut self.invoke_context
+    }
 
     pub(crate) fn increment_binding_count(&mut self, output_index: u32) {
         self.output_binding_count[output_index as usize] += 1;
@@ -68,15 +140,144 @@ impl RuntimeGraph {
         self.nodes.iter_mut()
             .find(|p_node| p_node.node_id == node_id)
     }
+
+    // nodes that recompute if the given node's inputs change, the node itself included;
+    // changes follow the same edges that make a node active in the forward pass
+    // and stop at nodes that keep cached outputs, returned in execution order
+    pub fn affected_by(&self, graph: &Graph, node_id: NodeId) -> Vec<NodeId> {
+        let mut affected: Vec<NodeId> = vec![node_id];
+
+        // runtime nodes are in execution order, producers come before their consumers
+        for r_node in self.nodes.iter() {
+            if r_node.node_id == node_id || r_node.should_cache_outputs {
+                continue;
+            }
+            // the runtime may be from before the node was removed
+            let node = match graph.node_by_id(r_node.node_id) {
+                Some(node) => node,
+                None => continue,
+            };
+            let is_affected = node.inputs
+                .iter()
+                .enumerate()
+                .any(|(input_index, input)| {
+                    input.binding
+                        .as_output_binding()
+                        .is_some_and(|output_binding| affected.contains(&output_binding.output_node_id))
+                        && node.propagates_change(input_index)
+                });
+            if is_affected {
+                affected.push(r_node.node_id);
+            }
+        }
+
+        self.nodes
+            .iter()
+            .filter(|r_node| !r_node.has_missing_inputs && affected.contains(&r_node.node_id))
+            .map(|r_node| r_node.node_id)
+            .collect()
+    }
+
+    // output values and timings by node id, for resuming incremental runs after a restart
+    pub fn save_state(&self, graph: &Graph, path: &str) -> anyhow::Result<()> {
+        let state = RuntimeState {
+            structural_hash: graph.structural_hash(),
+            nodes: self.nodes
+                .iter()
+                .map(|r_node| RuntimeNodeState {
+                    node_id: r_node.node_id,
+                    run_time: r_node.run_time,
+                    output_values: r_node.output_values.clone(),
+                })
+                .collect(),
+        };
+        std::fs::write(path, serde_yaml::to_string(&state)?)?;
+
+        Ok(())
+    }
+    // preprocessed runtime graph with the saved outputs, ready for Compute::run;
+    // fails if the graph structure changed since the state was saved
+    pub fn load_state(path: &str, graph: &Graph) -> anyhow::Result<RuntimeGraph> {
+        let state: RuntimeState = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+        if state.structural_hash != graph.structural_hash() {
+            return Err(anyhow::anyhow!("Runtime state {} was saved for a different graph", path));
+        }
+
+        let mut runtime_graph = RuntimeGraph {
+            nodes: state.nodes
+                .into_iter()
+                .filter_map(|node_state| {
+                    let node = graph.node_by_id(node_state.node_id)?;
+                    let mut r_node = RuntimeNode::new(node);
+                    r_node.run_time = node_state.run_time;
+                    r_node.output_values = node_state.output_values;
+                    Some(r_node)
+                })
+                .collect(),
+            probes: Vec::new(),
+        };
+
+        Preprocess::default().run(graph, &mut runtime_graph)
+    }
+
+    // probes don't affect scheduling, values are only captured when the node executes
+    pub fn add_probe(&mut self, node_id: NodeId, output_index: u32) {
+        if self.probe_index(node_id, output_index).is_none() {
+            self.probes.push(Probe {
+                node_id,
+                output_index,
+                value: None,
+            });
+        }
+    }
+    pub fn remove_probe(&mut self, node_id: NodeId, output_index: u32) {
+        self.probes
+            .retain(|probe| probe.node_id != node_id || probe.output_index != output_index);
+    }
+    // last captured value
+    pub fn probe_value(&self, node_id: NodeId, output_index: u32) -> Option<&Value> {
+        self.probe_index(node_id, output_index)
+            .and_then(|index| self.probes[index].value.as_ref())
+    }
+    fn probe_index(&self, node_id: NodeId, output_index: u32) -> Option<usize> {
+        self.probes
+            .iter()
+            .position(|probe| probe.node_id == node_id && probe.output_index == output_index)
+    }
 }
 
 
-impl InvokeContext {
-    pub(crate) fn default() -> InvokeContext {
+impl Default for InvokeContext {
+    fn default() -> InvokeContext {
         InvokeContext {
             boxed: None,
+            quality: 1.0,
+            param_hash: None,
+            executed_param_hash: None,
         }
     }
+}
+
+impl InvokeContext {
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+    pub(crate) fn set_quality(&mut self, quality: f32) {
+        self.quality = quality;
+    }
+
+    pub fn param_hash(&self) -> Option<u64> {
+        self.param_hash
+    }
+    pub fn set_param_hash(&mut self, param_hash: u64) {
+        self.param_hash = Some(param_hash);
+    }
+    pub(crate) fn is_param_hash_changed(&self) -> bool {
+        self.param_hash != self.executed_param_hash
+    }
+    pub(crate) fn mark_param_hash_executed(&mut self) {
+        self.executed_param_hash = self.param_hash;
+    }
 
     pub fn is_none(&self) -> bool {
         self.boxed.is_none()
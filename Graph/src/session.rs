@@ -0,0 +1,161 @@
+use std::mem::take;
+use std::time::{Duration, Instant};
+
+use crate::compute::Compute;
+use crate::data::Value;
+use crate::graph::{Binding, Graph, NodeId};
+use crate::preprocess::Preprocess;
+use crate::run_debouncer::RunDebouncer;
+use crate::runner;
+use crate::runtime_graph::RuntimeGraph;
+
+// per-frame value for a node input, replaces its binding with a const for that frame only
+#[derive(Clone, Debug)]
+pub struct FrameInput {
+    pub node_id: NodeId,
+    pub input_index: u32,
+    pub value: Value,
+}
+
+// runs the graph once per frame, keeping node outputs and invoke contexts between ticks,
+// so functions can refer to the previous frames; state is only dropped by reset
+pub struct Session {
+    graph: Graph,
+    runtime_graph: RuntimeGraph,
+    frame_count: u64,
+    // nodes that received frame inputs on the last tick
+    frame_input_nodes: Vec<NodeId>,
+    // coalesces request_run calls, the frame inputs of the latest request are used
+    debouncer: RunDebouncer,
+    requested_frame_inputs: Vec<FrameInput>,
+    last_outputs: Option<Vec<(String, Value)>>,
+}
+
+impl Session {
+    pub fn new(graph: Graph) -> Session {
+        Self::with_quiet_period(graph, Duration::ZERO)
+    }
+    // requested runs wait for the quiet period without new requests, see request_run
+    pub fn with_quiet_period(graph: Graph, quiet_period: Duration) -> Session {
+        Session {
+            graph,
+            runtime_graph: RuntimeGraph::default(),
+            frame_count: 0,
+            frame_input_nodes: Vec::new(),
+            debouncer: RunDebouncer::new(quiet_period),
+            requested_frame_inputs: Vec::new(),
+            last_outputs: None,
+        }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+    // outputs of the latest tick
+    pub fn last_outputs(&self) -> Option<&[(String, Value)]> {
+        self.last_outputs.as_deref()
+    }
+    pub fn reset(&mut self) {
+        self.runtime_graph = RuntimeGraph::default();
+        self.frame_count = 0;
+        self.frame_input_nodes.clear();
+        self.last_outputs = None;
+    }
+
+    // doesn't run anything, e.g. called on every slider move; the run happens in poll_run
+    // once no request came in for the quiet period, or in flush_run
+    pub fn request_run(&mut self, frame_inputs: Vec<FrameInput>) {
+        self.request_run_at(Instant::now(), frame_inputs);
+    }
+    pub fn request_run_at(&mut self, now: Instant, frame_inputs: Vec<FrameInput>) {
+        self.debouncer.request_run_at(now);
+        self.requested_frame_inputs = frame_inputs;
+    }
+    pub fn is_run_pending(&self) -> bool {
+        self.debouncer.is_pending()
+    }
+
+    // expected to be called every frame, ticks with the latest requested frame inputs
+    // when the quiet period has passed and returns the outputs of that tick
+    pub fn poll_run(&mut self, compute: &Compute) -> anyhow::Result<Option<Vec<(String, Value)>>> {
+        self.poll_run_at(compute, Instant::now())
+    }
+    pub fn poll_run_at(&mut self, compute: &Compute, now: Instant) -> anyhow::Result<Option<Vec<(String, Value)>>> {
+        if !self.debouncer.poll_at(now) {
+            return Ok(None);
+        }
+
+        self.run_requested(compute).map(Some)
+    }
+    // runs a pending request without waiting for the quiet period
+    pub fn flush_run(&mut self, compute: &Compute) -> anyhow::Result<Option<Vec<(String, Value)>>> {
+        if !self.debouncer.flush() {
+            return Ok(None);
+        }
+
+        self.run_requested(compute).map(Some)
+    }
+    fn run_requested(&mut self, compute: &Compute) -> anyhow::Result<Vec<(String, Value)>> {
+        let frame_inputs = take(&mut self.requested_frame_inputs);
+        self.tick(compute, &frame_inputs)
+    }
+
+    // nodes receiving frame inputs and everything downstream of them re-execute,
+    // returns the value received by the first input of each executed output node;
+    // frame inputs are applied to a copy of the graph, so a rejected one changes nothing
+    pub fn tick(&mut self, compute: &Compute, frame_inputs: &[FrameInput]) -> anyhow::Result<Vec<(String, Value)>> {
+        let mut frame_graph = self.graph.clone();
+        for frame_input in frame_inputs {
+            let node = frame_graph
+                .node_by_id_mut(frame_input.node_id)
+                .ok_or_else(|| anyhow::anyhow!("Frame input for unknown node {}", frame_input.node_id))?;
+            let input = node.inputs
+                .get_mut(frame_input.input_index as usize)
+                .ok_or_else(|| anyhow::anyhow!("Node {} has no input {}", node.name, frame_input.input_index))?;
+            if !frame_input.value.has_type(&input.data_type) {
+                return Err(anyhow::anyhow!(
+                    "Frame input {} of node {} expects {}, got {}",
+                    input.name,
+                    node.name,
+                    input.data_type,
+                    frame_input.value.data_type()
+                ));
+            }
+
+            input.binding = Binding::Const;
+            input.const_value = Some(frame_input.value.clone());
+        }
+
+        // nodes fed on the last tick but not on this one are back on their own bindings
+        let frame_input_nodes: Vec<NodeId> = frame_inputs
+            .iter()
+            .map(|frame_input| frame_input.node_id)
+            .collect();
+        for node_id in frame_input_nodes.iter().chain(self.frame_input_nodes.iter()) {
+            self.runtime_graph
+                .affected_by(&frame_graph, *node_id)
+                .into_iter()
+                .for_each(|node_id| {
+                    let r_node = self.runtime_graph.node_by_id_mut(node_id).unwrap();
+                    r_node.output_values = None;
+                });
+        }
+        self.frame_input_nodes = frame_input_nodes;
+
+        // preprocess leaves the previous runtime untouched when it fails
+        let mut runtime_graph = Preprocess::default().run(&frame_graph, &mut self.runtime_graph)?;
+        let snapshot = compute.run_snapshot(&frame_graph, &mut runtime_graph);
+        self.runtime_graph = runtime_graph;
+        let snapshot = snapshot?;
+
+        self.frame_count += 1;
+
+        let outputs = runner::output_values(&frame_graph, &snapshot);
+        self.last_outputs = Some(outputs.clone());
+
+        Ok(outputs)
+    }
+}
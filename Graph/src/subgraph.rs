@@ -3,10 +3,13 @@ use serde::{Deserialize, Serialize};
 use common::id_type;
 
 use crate::data::DataType;
-use crate::graph::{Graph, NodeId};
+use crate::graph::{FunctionBehavior, Graph, Node, NodeId};
 
 id_type!(SubGraphId);
 
+// guards expansion of nested subgraph instances
+pub const MAX_SUBGRAPH_DEPTH: usize = 64;
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct SubInputNodeConnection {
     pub subnode_id: NodeId,
@@ -56,6 +59,33 @@ impl SubGraph {
         self.self_id
     }
 }
+
+fn permute<T: Clone>(items: &[T], new_order: &[usize]) -> anyhow::Result<Vec<T>> {
+    if new_order.len() != items.len() {
+        return Err(anyhow::Error::msg("Permutation length does not match item count"));
+    }
+
+    let mut seen = vec![false; items.len()];
+    for &index in new_order.iter() {
+        if index >= items.len() || seen[index] {
+            return Err(anyhow::Error::msg("Invalid permutation"));
+        }
+        seen[index] = true;
+    }
+
+    Ok(new_order
+        .iter()
+        .map(|&index| items[index].clone())
+        .collect())
+}
+
+// position in a valid permutation of the item at old_index
+fn permuted_index(new_order: &[usize], old_index: u32) -> u32 {
+    new_order
+        .iter()
+        .position(|&index| index == old_index as usize)
+        .unwrap() as u32
+}
 impl Graph {
     pub fn add_subgraph(&mut self, subgraph: &SubGraph) {
         match self
@@ -84,6 +114,80 @@ impl Graph {
             });
     }
 
+    // new_order[i] is the current index of the input that should end up at position i;
+    // subgraph connections reference subnodes directly, so they stay valid, while the inputs
+    // of instance nodes are permuted the same way and keep their bindings
+    pub fn reorder_subgraph_inputs(&mut self, subgraph_id: SubGraphId, new_order: &[usize]) -> anyhow::Result<()> {
+        let subgraph = self.subgraph_by_id(subgraph_id)
+            .ok_or(anyhow::Error::msg("Subgraph not found"))?;
+        let inputs = permute(&subgraph.inputs, new_order)?;
+        let instance_ids = self.instance_ids(subgraph_id, subgraph.inputs.len(), |node| node.inputs.len())?;
+
+        self.subgraph_by_id_mut(subgraph_id).unwrap().inputs = inputs;
+        for node in self.nodes_mut().iter_mut().filter(|node| instance_ids.contains(&node.id())) {
+            node.inputs = permute(&node.inputs, new_order)?;
+            if let FunctionBehavior::OnInputChange(input_index) = node.behavior {
+                node.behavior = FunctionBehavior::OnInputChange(permuted_index(new_order, input_index));
+            }
+        }
+        // instances nested in other subgraphs are fed by index
+        self.subgraphs_mut()
+            .iter_mut()
+            .flat_map(|subgraph| subgraph.inputs.iter_mut())
+            .flat_map(|subinput| subinput.connections.iter_mut())
+            .filter(|connection| instance_ids.contains(&connection.subnode_id))
+            .for_each(|connection| {
+                connection.subnode_input_index = permuted_index(new_order, connection.subnode_input_index);
+            });
+
+        Ok(())
+    }
+    // same as reorder_subgraph_inputs, consumers of instance nodes are rebound to the moved outputs
+    pub fn reorder_subgraph_outputs(&mut self, subgraph_id: SubGraphId, new_order: &[usize]) -> anyhow::Result<()> {
+        let subgraph = self.subgraph_by_id(subgraph_id)
+            .ok_or(anyhow::Error::msg("Subgraph not found"))?;
+        let outputs = permute(&subgraph.outputs, new_order)?;
+        let instance_ids = self.instance_ids(subgraph_id, subgraph.outputs.len(), |node| node.outputs.len())?;
+
+        self.subgraph_by_id_mut(subgraph_id).unwrap().outputs = outputs;
+        for node in self.nodes_mut().iter_mut() {
+            if instance_ids.contains(&node.id()) {
+                node.outputs = permute(&node.outputs, new_order)?;
+            }
+            node.inputs
+                .iter_mut()
+                .filter_map(|input| input.binding.as_output_binding_mut())
+                .filter(|output_binding| instance_ids.contains(&output_binding.output_node_id))
+                .for_each(|output_binding| {
+                    output_binding.output_index = permuted_index(new_order, output_binding.output_index);
+                });
+        }
+        self.subgraphs_mut()
+            .iter_mut()
+            .flat_map(|subgraph| subgraph.outputs.iter_mut())
+            .filter(|suboutput| instance_ids.contains(&suboutput.subnode_id))
+            .for_each(|suboutput| {
+                suboutput.subnode_output_index = permuted_index(new_order, suboutput.subnode_output_index);
+            });
+
+        Ok(())
+    }
+    // nodes instantiating the subgraph, fails if any of them doesn't have one port per subgraph port
+    fn instance_ids<F>(&self, subgraph_id: SubGraphId, port_count: usize, node_port_count: F) -> anyhow::Result<Vec<NodeId>>
+    where F: Fn(&Node) -> usize
+    {
+        self.nodes()
+            .iter()
+            .filter(|node| node.instance_of == Some(subgraph_id))
+            .map(|node| {
+                if node_port_count(node) != port_count {
+                    return Err(anyhow::anyhow!("Instance node {} doesn't match the ports of its subgraph", node.name));
+                }
+                Ok(node.id())
+            })
+            .collect()
+    }
+
     pub fn subgraph_by_id_mut(&mut self, id: SubGraphId) -> Option<&mut SubGraph> {
         assert!(!id.is_nil());
         self.subgraphs_mut()
@@ -96,4 +200,127 @@ impl Graph {
             .iter()
             .find(|subgraph| subgraph.id() == id)
     }
+
+    // fails if a subgraph instantiates itself directly or through other subgraphs
+    pub fn check_subgraph_recursion(&self) -> anyhow::Result<()> {
+        for subgraph in self.subgraphs().iter() {
+            let mut chain = vec![subgraph.id()];
+            self.check_subgraph_recursion_from(&mut chain)?;
+        }
+
+        Ok(())
+    }
+    fn check_subgraph_recursion_from(&self, chain: &mut Vec<SubGraphId>) -> anyhow::Result<()> {
+        if chain.len() > MAX_SUBGRAPH_DEPTH {
+            return Err(anyhow::anyhow!("subgraph nesting is deeper than {}", MAX_SUBGRAPH_DEPTH));
+        }
+
+        let subgraph_id = *chain.last().unwrap();
+        let mut instantiated_ids = self.nodes_by_subgraph_id(subgraph_id)
+            .iter()
+            .filter_map(|node| node.instance_of)
+            .collect::<Vec<SubGraphId>>();
+        instantiated_ids.sort();
+        instantiated_ids.dedup();
+
+        for instantiated_id in instantiated_ids {
+            let is_recursive = chain.contains(&instantiated_id);
+            chain.push(instantiated_id);
+
+            if is_recursive {
+                let names = chain
+                    .iter()
+                    .map(|&id| self.subgraph_by_id(id).map_or(id.to_string(), |subgraph| subgraph.name.clone()))
+                    .collect::<Vec<String>>();
+                return Err(anyhow::anyhow!("subgraph recursion detected: {}", names.join(" -> ")));
+            }
+
+            self.check_subgraph_recursion_from(chain)?;
+            chain.pop();
+        }
+
+        Ok(())
+    }
+
+
+    // standalone graph with the subgraph and its member nodes;
+    // member inputs bound to external nodes are unbound and exposed as subgraph inputs
+    pub fn export_subgraph(&self, subgraph_id: SubGraphId) -> anyhow::Result<Graph> {
+        let mut subgraph = self.subgraph_by_id(subgraph_id)
+            .ok_or(anyhow::Error::msg("Subgraph not found"))?
+            .clone();
+        let mut nodes = self.nodes_by_subgraph_id(subgraph_id)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if nodes.iter().any(|node| node.instance_of.is_some()) {
+            return Err(anyhow::Error::msg("Exporting subgraphs with nested subgraph instances is not supported"));
+        }
+
+        let member_ids = nodes
+            .iter()
+            .map(|node| node.id())
+            .collect::<Vec<NodeId>>();
+
+        for node in nodes.iter_mut() {
+            let node_id = node.id();
+            for (input_index, input) in node.inputs.iter_mut().enumerate() {
+                let is_external = input.binding
+                    .as_output_binding()
+                    .is_some_and(|output_binding| !member_ids.contains(&output_binding.output_node_id));
+                if !is_external {
+                    continue;
+                }
+
+                input.unbind();
+
+                let is_exposed = subgraph.inputs
+                    .iter()
+                    .flat_map(|subinput| subinput.connections.iter())
+                    .any(|connection| connection.subnode_id == node_id
+                        && connection.subnode_input_index == input_index as u32);
+                if !is_exposed {
+                    subgraph.inputs.push(SubInput {
+                        name: input.name.clone(),
+                        data_type: input.data_type.clone(),
+                        is_required: input.is_required,
+                        connections: vec![SubInputNodeConnection {
+                            subnode_id: node_id,
+                            subnode_input_index: input_index as u32,
+                        }],
+                    });
+                }
+            }
+        }
+
+        let mut graph = Graph::default();
+        graph.add_subgraph(&subgraph);
+        nodes
+            .into_iter()
+            .for_each(|node| graph.add_node(node));
+        graph.validate()?;
+
+        Ok(graph)
+    }
+
+    // internal (subnode_id, subnode_output_index) producing the subgraph output
+    pub fn resolve_suboutput(&self, subgraph_id: SubGraphId, output_index: u32) -> Option<(NodeId, u32)> {
+        self.subgraph_by_id(subgraph_id)?
+            .outputs
+            .get(output_index as usize)
+            .map(|suboutput| (suboutput.subnode_id, suboutput.subnode_output_index))
+    }
+    // internal (subnode_id, subnode_input_index) pairs fed by the subgraph input
+    pub fn resolve_subinput(&self, subgraph_id: SubGraphId, input_index: u32) -> Option<Vec<(NodeId, u32)>> {
+        self.subgraph_by_id(subgraph_id)?
+            .inputs
+            .get(input_index as usize)
+            .map(|subinput| {
+                subinput.connections
+                    .iter()
+                    .map(|connection| (connection.subnode_id, connection.subnode_input_index))
+                    .collect()
+            })
+    }
 }
\ No newline at end of file
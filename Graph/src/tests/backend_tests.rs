@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::backend::{BackendTransition, TransitionKind};
+use crate::compute::Compute;
+use crate::data::{DataType, Value};
+use crate::functions::FunctionId;
+use crate::graph::*;
+use crate::invoke::{InvokeArgs, Invoker};
+use crate::preprocess::Preprocess;
+use crate::runtime_graph::{InvokeContext, RuntimeGraph};
+
+fn node(name: &str, backend: NodeBackend, input_node_ids: &[NodeId]) -> Node {
+    let mut node = Node::new();
+    node.name = name.to_string();
+    node.backend = backend;
+    node.inputs = input_node_ids
+        .iter()
+        .map(|&node_id| Input {
+            name: "image".to_string(),
+            data_type: DataType::Int,
+            is_required: true,
+            binding: Binding::from_output_binding(node_id, 0),
+            const_value: None,
+        })
+        .collect();
+    node.outputs.push(Output {
+        name: "image".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    node
+}
+
+#[test]
+fn backend_transitions_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    // load -> grayscale (cpu) -> passthrough (gpu) -> save (cpu)
+    //                                               -> preview (any, stays on gpu)
+    let load = node("load", NodeBackend::Any, &[]);
+    let load_id = load.id();
+    graph.add_node(load);
+    let grayscale = node("grayscale", NodeBackend::Cpu, &[load_id]);
+    let grayscale_id = grayscale.id();
+    graph.add_node(grayscale);
+    let passthrough = node("passthrough", NodeBackend::Gpu, &[grayscale_id]);
+    let passthrough_id = passthrough.id();
+    graph.add_node(passthrough);
+    let mut save = node("save", NodeBackend::Cpu, &[passthrough_id]);
+    save.is_output = true;
+    let save_id = save.id();
+    graph.add_node(save);
+    let mut preview = node("preview", NodeBackend::Any, &[passthrough_id]);
+    preview.is_output = true;
+    let preview_id = preview.id();
+    graph.add_node(preview);
+
+    let backends = graph.resolved_backends()?;
+    assert_eq!(backends[&load_id], NodeBackend::Cpu);
+    assert_eq!(backends[&preview_id], NodeBackend::Gpu);
+
+    assert_eq!(graph.backend_transitions()?, vec![
+        BackendTransition {
+            kind: TransitionKind::Upload,
+            node_id: grayscale_id,
+            output_index: 0,
+            consumers: vec![(passthrough_id, 0)],
+        },
+        BackendTransition {
+            kind: TransitionKind::Download,
+            node_id: passthrough_id,
+            output_index: 0,
+            consumers: vec![(save_id, 0)],
+        },
+    ]);
+
+    // a second cpu consumer shares the download
+    let mut log = node("log", NodeBackend::Cpu, &[passthrough_id]);
+    log.is_output = true;
+    let log_id = log.id();
+    graph.add_node(log);
+
+    // consumers in topological order, independent nodes by name
+    let transitions = graph.backend_transitions()?;
+    assert_eq!(transitions.len(), 2);
+    assert_eq!(transitions[1].consumers, vec![(log_id, 0), (save_id, 0)]);
+
+    Ok(())
+}
+
+// passes the first input through, 1 without inputs; uploads add 10, downloads add 100
+struct TransitionInvoker {
+    functions: Vec<FunctionId>,
+    transitions: Rc<RefCell<Vec<(FunctionId, TransitionKind)>>>,
+    results: Rc<RefCell<Vec<i64>>>,
+}
+
+impl Invoker for TransitionInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        self.functions.clone()
+    }
+
+    fn invoke(&self,
+              _function_id: FunctionId,
+              _ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        let value = match inputs.first() {
+            Some(input) => input.as_ref().unwrap().as_int(),
+            None => 1,
+        };
+        self.results.borrow_mut().push(value);
+        outputs[0] = Some(Value::from(value));
+
+        Ok(())
+    }
+
+    fn transition(&self, function_id: FunctionId, kind: TransitionKind, value: &Value) -> anyhow::Result<Value> {
+        self.transitions.borrow_mut().push((function_id, kind));
+        let offset = match kind {
+            TransitionKind::Upload => 10,
+            TransitionKind::Download => 100,
+        };
+
+        Ok(Value::from(value.as_int() + offset))
+    }
+}
+
+#[test]
+fn compute_runs_transitions() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    // load (cpu) -> blur (gpu) -> save (cpu)
+    //                          -> log (cpu)
+    let mut functions = Vec::new();
+    let mut add_node = |graph: &mut Graph, mut node: Node| -> NodeId {
+        node.function_id = FunctionId::unique();
+        functions.push(node.function_id);
+        let node_id = node.id();
+        graph.add_node(node);
+        node_id
+    };
+    let load_id = add_node(&mut graph, node("load", NodeBackend::Cpu, &[]));
+    let blur_id = add_node(&mut graph, node("blur", NodeBackend::Gpu, &[load_id]));
+    let mut save = node("save", NodeBackend::Cpu, &[blur_id]);
+    save.is_output = true;
+    add_node(&mut graph, save);
+    let mut log = node("log", NodeBackend::Cpu, &[blur_id]);
+    log.is_output = true;
+    add_node(&mut graph, log);
+
+    let blur_function_id = graph.node_by_id(blur_id).unwrap().function_id;
+    let transitions = Rc::new(RefCell::new(Vec::new()));
+    let results = Rc::new(RefCell::new(Vec::new()));
+    let compute = Compute::from(TransitionInvoker {
+        functions,
+        transitions: Rc::clone(&transitions),
+        results: Rc::clone(&results),
+    });
+
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+
+    // both transitions go through the gpu node's invoker, the download is shared by save and log
+    assert_eq!(*transitions.borrow(), vec![
+        (blur_function_id, TransitionKind::Upload),
+        (blur_function_id, TransitionKind::Download),
+    ]);
+    assert_eq!(*results.borrow(), vec![1, 11, 111, 111]);
+
+    Ok(())
+}
+
+#[test]
+fn default_backend_is_not_serialized() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+    graph.add_node(node("load", NodeBackend::Any, &[]));
+    let yaml = graph.to_yaml()?;
+    assert!(!yaml.contains("backend:"));
+    assert!(!yaml.contains("execution_priority:"));
+
+    let mut blur = node("blur", NodeBackend::Gpu, &[]);
+    blur.execution_priority = -1;
+    let blur_id = blur.id();
+    graph.add_node(blur);
+
+    let loaded = Graph::from_yaml(&graph.to_yaml()?)?;
+    let blur = loaded.node_by_id(blur_id).unwrap();
+    assert_eq!(blur.backend, NodeBackend::Gpu);
+    assert_eq!(blur.execution_priority, -1);
+
+    Ok(())
+}
@@ -1,35 +1,52 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use crate::compute::Compute;
-use crate::data::Value;
+use crate::call_log::{CallLog, RecordingInvoker, ReplayInvoker};
+use crate::compute::{Compute, MIN_QUALITY, MissingFunctionPolicy};
+use crate::data::{DataType, Value};
 use crate::functions::FunctionId;
-use crate::graph::{Binding, FunctionBehavior, Graph};
+use crate::graph::{Binding, ConnectionStatus, FunctionBehavior, Graph, Input, Node, NodeId, Output};
 use crate::invoke::LambdaInvoker;
 use crate::preprocess::Preprocess;
+use crate::run_debouncer::RunDebouncer;
+use crate::run_snapshot::RunSnapshot;
 use crate::runtime_graph::{InvokeContext, RuntimeGraph};
 
-static mut RESULT: i64 = 0;
-static mut A: i64 = 2;
-static mut B: i64 = 5;
+// function ids of test_graph.yml by node name
+const TEST_GRAPH_FUNCTIONS: [(&str, &str); 5] = [
+    ("print", "f22cd316-1cdf-4a80-b86c-1277acd1408a"),
+    ("val1", "d4d27137-5a14-437a-8bb5-b2f7be0941a2"),
+    ("val2", "a937baff-822d-48fd-9154-58751539b59b"),
+    ("sum", "2d3b389d-7b58-44d9-b3d1-a595765b21a5"),
+    ("mult", "432b9bf1-f478-476c-a9c9-9a6e190124fc"),
+];
 
-fn setup() {
-    unsafe {
-        RESULT = 0;
-        A = 2;
-        B = 5;
-    }
+fn function_id(name: &str) -> FunctionId {
+    let (_, id) = TEST_GRAPH_FUNCTIONS
+        .iter()
+        .find(|(function_name, _)| *function_name == name)
+        .unwrap();
+    FunctionId::from_str(id).unwrap()
 }
 
-fn create_compute<GetA, GetB, SetResult>(
+fn function_name(function_id: FunctionId) -> String {
+    TEST_GRAPH_FUNCTIONS
+        .iter()
+        .find(|(_, id)| FunctionId::from_str(id).unwrap() == function_id)
+        .map(|(name, _)| name.to_string())
+        .unwrap()
+}
+
+fn create_invoker<GetA, GetB, SetResult>(
     get_a: GetA, get_b: GetB, result: SetResult,
-) -> anyhow::Result<Compute>
+) -> anyhow::Result<LambdaInvoker>
 where
     SetResult: Fn(i64) + 'static,
     GetA: Fn() -> i64 + 'static,
     GetB: Fn() -> i64 + 'static,
 {
-    setup();
-
     let mut invoker = LambdaInvoker::default();
 
     // print func
@@ -69,18 +86,29 @@ where
             ctx.set(a * b);
         });
 
-    Ok(invoker.into())
+    Ok(invoker)
+}
+
+fn create_compute<GetA, GetB, SetResult>(
+    get_a: GetA, get_b: GetB, result: SetResult,
+) -> anyhow::Result<Compute>
+where
+    SetResult: Fn(i64) + 'static,
+    GetA: Fn() -> i64 + 'static,
+    GetB: Fn() -> i64 + 'static,
+{
+    Ok(create_invoker(get_a, get_b, result)?.into())
 }
 
 #[test]
 fn simple_compute_test_default_input_value() -> anyhow::Result<()> {
-    setup();
-
-    let compute = create_compute(
+    let recording_invoker = RecordingInvoker::new(Box::new(create_invoker(
         || panic!("Unexpected call to get_a"),
         || panic!("Unexpected call to get_b"),
-        |result| unsafe { RESULT = result; },
-    )?;
+        |_| {},
+    )?));
+    let log = recording_invoker.log();
+    let compute = Compute::from(recording_invoker);
 
     let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
 
@@ -103,10 +131,15 @@ fn simple_compute_test_default_input_value() -> anyhow::Result<()> {
     }
 
     let preprocess = Preprocess::default();
-    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default());
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
 
     compute.run(&graph, &mut runtime_graph)?;
-    assert_eq!(unsafe { RESULT }, 360);
+    let print_call = log.borrow().calls
+        .iter()
+        .rfind(|record| record.function_id == function_id("print"))
+        .cloned()
+        .unwrap();
+    assert_eq!(print_call.inputs[0], Some(Value::from(360)));
 
     drop(graph);
 
@@ -115,34 +148,96 @@ fn simple_compute_test_default_input_value() -> anyhow::Result<()> {
 
 #[test]
 fn simple_compute_test() -> anyhow::Result<()> {
-    setup();
+    let b = Rc::new(Cell::new(5));
+    let get_b = b.clone();
 
-    let compute = create_compute(
-        || unsafe { A },
-        || unsafe { B },
-        |result| unsafe { RESULT = result; },
-    )?;
+    let recording_invoker = RecordingInvoker::new(Box::new(create_invoker(
+        || 2,
+        move || get_b.get(),
+        |_| {},
+    )?));
+    let log = recording_invoker.log();
+    let compute = Compute::from(recording_invoker);
+
+    // names of the calls made since the last check, and the value printed last
+    let take_calls = || -> (Vec<String>, i64) {
+        let calls = std::mem::take(&mut log.borrow_mut().calls);
+        let result = calls
+            .iter()
+            .rfind(|record| record.function_id == function_id("print"))
+            .map(|record| record.inputs[0].as_ref().unwrap().as_int())
+            .unwrap();
+        let names = calls
+            .into_iter()
+            .map(|record| function_name(record.function_id))
+            .collect();
+        (names, result)
+    };
 
     let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
     let preprocess = Preprocess::default();
 
-    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default());
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
     compute.run(&graph, &mut runtime_graph)?;
-    assert_eq!(unsafe { RESULT }, 35);
+    assert_eq!(take_calls(), (to_names(&["val1", "val2", "sum", "mult", "print"]), 35));
 
-    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph);
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
     compute.run(&graph, &mut runtime_graph)?;
-    assert_eq!(unsafe { RESULT }, 35);
+    assert_eq!(take_calls(), (to_names(&["mult", "print"]), 35));
 
-    unsafe { B = 7; }
+    b.set(7);
     graph.node_by_name_mut("val2").unwrap().behavior = FunctionBehavior::Active;
-    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default());
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
     compute.run(&graph, &mut runtime_graph)?;
-    assert_eq!(unsafe { RESULT }, 63);
+    assert_eq!(take_calls(), (to_names(&["val1", "val2", "sum", "mult", "print"]), 63));
 
     Ok(())
 }
 
+#[test]
+fn runtime_state_test() -> anyhow::Result<()> {
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(function_id("print"), |_, _, _| {});
+    for name in ["val1", "val2", "sum", "mult"] {
+        invoker.add_lambda(function_id(name), |_, _, outputs| {
+            outputs[0] = Value::from(3).into();
+        });
+    }
+    let recording_invoker = RecordingInvoker::new(Box::new(invoker));
+    let log = recording_invoker.log();
+    let compute = Compute::from(recording_invoker);
+    let take_names = || -> Vec<String> {
+        std::mem::take(&mut log.borrow_mut().calls)
+            .into_iter()
+            .map(|record| function_name(record.function_id))
+            .collect()
+    };
+
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+    let path = "../test_output/runtime_state.yml";
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(take_names(), to_names(&["val1", "val2", "sum", "mult", "print"]));
+    runtime_graph.save_state(&graph, path)?;
+
+    // a fresh runtime resumes as if it had run before
+    let mut runtime_graph = RuntimeGraph::load_state(path, &graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(take_names(), to_names(&["mult", "print"]));
+
+    graph.node_by_name_mut("mult").unwrap().inputs[1].binding = Binding::Const;
+    let error = RuntimeGraph::load_state(path, &graph).err().unwrap().to_string();
+    assert!(error.contains("different graph"), "{}", error);
+
+    Ok(())
+}
+
+fn to_names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
 #[test]
 fn invoke_context_test() -> anyhow::Result<()> {
     fn box_test_(ctx: &mut InvokeContext) {
@@ -163,3 +258,761 @@ fn invoke_context_test() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn record_and_replay_test() -> anyhow::Result<()> {
+    let invoker = create_invoker(
+        || 2,
+        || 5,
+        |_| {},
+    )?;
+    let recording_invoker = RecordingInvoker::new(Box::new(invoker));
+    let log = recording_invoker.log();
+    let compute = Compute::from(recording_invoker);
+
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+
+    let mut recorded_runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut recorded_runtime_graph)?;
+    assert_eq!(log.borrow().calls.len(), 5);
+
+    let yaml = log.borrow().to_yaml()?;
+    let compute = Compute::from(ReplayInvoker::new(CallLog::from_yaml(&yaml)?));
+
+    let mut replayed_runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut replayed_runtime_graph)?;
+
+    for r_node in replayed_runtime_graph.nodes.iter() {
+        let recorded_r_node = recorded_runtime_graph.node_by_id(r_node.node_id()).unwrap();
+        assert_eq!(r_node.output_values, recorded_r_node.output_values);
+    }
+    let mult_node = replayed_runtime_graph.node_by_name("mult").unwrap();
+    assert_eq!(mult_node.output_values.as_ref().unwrap()[0], Some(Value::Int(35)));
+
+    Ok(())
+}
+
+#[test]
+fn replay_unrecorded_call_test() -> anyhow::Result<()> {
+    let recording_invoker = RecordingInvoker::new(Box::new(create_invoker(
+        || 2,
+        || 5,
+        |_| {},
+    )?));
+    let log = recording_invoker.log();
+    let compute = Compute::from(recording_invoker);
+
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+
+    {
+        let sum_inputs = &mut graph
+            .node_by_name_mut("sum").unwrap()
+            .inputs;
+        sum_inputs[0].const_value = Some(Value::from(29));
+        sum_inputs[0].binding = Binding::Const;
+    }
+
+    let compute = Compute::from(ReplayInvoker::new(log.borrow().clone()));
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    assert!(compute.run(&graph, &mut runtime_graph).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn run_snapshot_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+
+    let compute = create_compute(|| 2, || 5, |_| {})?;
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    let snapshot1 = compute.run_snapshot(&graph, &mut runtime_graph)?;
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    let snapshot2 = compute.run_snapshot(&graph, &mut runtime_graph)?;
+
+    assert_eq!(snapshot1.nodes.len(), 5);
+    assert_eq!(snapshot1.structural_hash, graph.structural_hash());
+    assert_eq!(snapshot1, snapshot2);
+    assert!(snapshot1.diff(&snapshot2).is_none());
+
+    let snapshot1 = RunSnapshot::from_yaml(&snapshot1.to_yaml()?)?;
+    assert_eq!(snapshot1, snapshot2);
+
+    let compute = create_compute(|| 2, || 7, |_| {})?;
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    let snapshot3 = compute.run_snapshot(&graph, &mut runtime_graph)?;
+
+    let val2_id = graph.node_by_name("val2").unwrap().id();
+    assert_eq!(snapshot1.diff(&snapshot3), Some(val2_id));
+
+    Ok(())
+}
+
+#[test]
+fn run_with_progress_test() -> anyhow::Result<()> {
+    let compute = create_compute(|| 2, || 5, |_| {})?;
+
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+
+    let scheduled_node_ids = runtime_graph.nodes
+        .iter()
+        .filter(|r_node| r_node.should_execute)
+        .map(|r_node| r_node.node_id())
+        .collect::<Vec<NodeId>>();
+
+    let mut completed: Vec<(NodeId, Vec<Option<Value>>)> = Vec::new();
+    compute.run_with_progress(&graph, &mut runtime_graph, |node_id, outputs| {
+        completed.push((node_id, outputs.to_vec()));
+    })?;
+
+    let completed_node_ids = completed
+        .iter()
+        .map(|(node_id, _)| *node_id)
+        .collect::<Vec<NodeId>>();
+    assert_eq!(completed_node_ids, scheduled_node_ids);
+
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    let (_, sum_outputs) = completed
+        .iter()
+        .find(|(node_id, _)| *node_id == sum_id)
+        .unwrap();
+    assert_eq!(sum_outputs, &vec![Some(Value::Int(7))]);
+
+    Ok(())
+}
+
+#[test]
+fn debounced_run_test() -> anyhow::Result<()> {
+    let run_count = Rc::new(Cell::new(0));
+    let compute = {
+        let run_count = Rc::clone(&run_count);
+        create_compute(|| 2, || 5, move |_| run_count.set(run_count.get() + 1))?
+    };
+
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+    let mut runtime_graph = RuntimeGraph::default();
+
+    let mut debouncer = RunDebouncer::new(Duration::from_millis(100));
+    let start = Instant::now();
+    let mut poll = |now: Instant, debouncer: &mut RunDebouncer| -> anyhow::Result<()> {
+        if debouncer.poll_at(now) {
+            runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+            compute.run(&graph, &mut runtime_graph)?;
+        }
+        Ok(())
+    };
+
+    // ten requests 5ms apart, polled every frame
+    for index in 0..10 {
+        let now = start + Duration::from_millis(index * 5);
+        debouncer.request_run_at(now);
+        poll(now, &mut debouncer)?;
+    }
+    assert!(debouncer.is_pending());
+    assert_eq!(run_count.get(), 0);
+
+    poll(start + Duration::from_millis(120), &mut debouncer)?;
+    assert_eq!(run_count.get(), 0);
+
+    poll(start + Duration::from_millis(145), &mut debouncer)?;
+    poll(start + Duration::from_millis(500), &mut debouncer)?;
+    assert_eq!(run_count.get(), 1);
+    assert!(!debouncer.is_pending());
+
+    debouncer.request_run_at(start + Duration::from_millis(510));
+    assert!(debouncer.flush());
+    assert!(!debouncer.flush());
+
+    Ok(())
+}
+
+#[test]
+fn runtime_stats_test() -> anyhow::Result<()> {
+    let compute = create_compute(
+        || 2,
+        || 5,
+        |_| {},
+    )?;
+
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let preprocess = Preprocess::default();
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+    let first = compute.stats();
+    assert_eq!(first.run_count, 1);
+    assert_eq!(first.executed_node_count, 5);
+    assert_eq!(first.function_run_time.len(), 5);
+
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    let second = compute.stats();
+    assert_eq!(second.run_count, 2);
+    assert!(second.skipped_node_count - first.skipped_node_count > first.skipped_node_count);
+    assert_eq!(
+        second.executed_node_count + second.skipped_node_count,
+        2 * (first.executed_node_count + first.skipped_node_count)
+    );
+
+    // stats are readable while the graph runs and count only nodes that ran
+    let mut executed_during_run = Vec::new();
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run_with_progress(&graph, &mut runtime_graph, |_, _| {
+        executed_during_run.push(compute.stats().executed_node_count);
+    })?;
+    let third = compute.stats();
+    assert!(!executed_during_run.is_empty());
+    assert_eq!(
+        executed_during_run,
+        (1..=executed_during_run.len() as u32)
+            .map(|count| second.executed_node_count + count)
+            .collect::<Vec<u32>>()
+    );
+    assert_eq!(third.executed_node_count, second.executed_node_count + executed_during_run.len() as u32);
+
+    compute.reset_stats();
+    assert_eq!(compute.stats().run_count, 0);
+    assert!(compute.stats().function_run_time.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn probe_test() -> anyhow::Result<()> {
+    let compute = create_compute(
+        || 2,
+        || 5,
+        |_| {},
+    )?;
+
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    let preprocess = Preprocess::default();
+
+    let mut runtime_graph = RuntimeGraph::default();
+    runtime_graph.add_probe(sum_id, 0);
+    assert!(runtime_graph.probe_value(sum_id, 0).is_none());
+
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    assert!(!runtime_graph.node_by_id(sum_id).unwrap().is_output);
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(runtime_graph.probe_value(sum_id, 0), Some(&Value::from(7)));
+
+    // probes survive preprocessing, keeping the last value while the node is cached
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(runtime_graph.probe_value(sum_id, 0), Some(&Value::from(7)));
+
+    runtime_graph.remove_probe(sum_id, 0);
+    assert!(runtime_graph.probe_value(sum_id, 0).is_none());
+
+    Ok(())
+}
+
+#[test]
+fn on_input_change_test() -> anyhow::Result<()> {
+    let compute = create_compute(
+        || 2,
+        || 5,
+        |_| {},
+    )?;
+
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    // sum is re-executed on every run because of active val1
+    graph.node_by_name_mut("sum").unwrap().should_cache_outputs = false;
+    // mult watches only input B, bound to passive val2
+    graph.node_by_name_mut("mult").unwrap().behavior = FunctionBehavior::OnInputChange(1);
+    graph.validate()?;
+
+    let preprocess = Preprocess::default();
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    assert_eq!(runtime_graph.node_by_name("sum").unwrap().behavior, FunctionBehavior::Active);
+    assert!(!runtime_graph.node_by_name("mult").unwrap().should_execute);
+    // nothing pulls sum while mult reuses its outputs
+    assert!(!runtime_graph.node_by_name("sum").unwrap().should_execute);
+    compute.run(&graph, &mut runtime_graph)?;
+
+    graph.node_by_name_mut("val2").unwrap().behavior = FunctionBehavior::Active;
+    let runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    assert!(runtime_graph.node_by_name("mult").unwrap().should_execute);
+
+    graph.node_by_name_mut("mult").unwrap().behavior = FunctionBehavior::OnInputChange(2);
+    assert!(graph.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn output_type_override_test() -> anyhow::Result<()> {
+    let convert_function_id = FunctionId::unique();
+    let consumer_function_id = FunctionId::unique();
+
+    // convert function declares an int output, the node configures it as float
+    let mut convert = Node::new();
+    convert.name = "convert".to_string();
+    convert.function_id = convert_function_id;
+    convert.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let convert_id = convert.id();
+
+    let mut consumer = Node::new();
+    consumer.name = "consumer".to_string();
+    consumer.function_id = consumer_function_id;
+    consumer.is_output = true;
+    consumer.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::from_output_binding(convert_id, 0),
+        const_value: None,
+    });
+    let consumer_id = consumer.id();
+
+    let mut graph = Graph::default();
+    graph.add_node(convert);
+    graph.add_node(consumer);
+
+    assert!(matches!(graph.connection_check(consumer_id, 0, convert_id, 0), ConnectionStatus::NeedsCoercion(_)));
+
+    graph.node_by_id_mut(convert_id).unwrap()
+        .outputs[0].data_type_override = Some(DataType::Float);
+    assert_eq!(graph.connection_check(consumer_id, 0, convert_id, 0), ConnectionStatus::Ok);
+    graph.validate()?;
+
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(convert_function_id, |_, _, outputs| {
+        outputs[0] = Value::from(3).into();
+    });
+    invoker.add_lambda(consumer_function_id, |_, _, _| {});
+    let compute = Compute::from(invoker);
+
+    let mut runtime_graph = RuntimeGraph::default();
+    runtime_graph.add_probe(convert_id, 0);
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut runtime_graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(runtime_graph.probe_value(convert_id, 0), Some(&Value::from(3.0)));
+
+    graph.node_by_id_mut(convert_id).unwrap()
+        .outputs[0].data_type_override = Some(DataType::String);
+    assert!(matches!(graph.connection_check(consumer_id, 0, convert_id, 0), ConnectionStatus::Incompatible(_)));
+    assert!(graph.validate().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn array_value_test() -> anyhow::Result<()> {
+    let producer_function_id = FunctionId::unique();
+    let consumer_function_id = FunctionId::unique();
+
+    let mut producer = Node::new();
+    producer.name = "producer".to_string();
+    producer.function_id = producer_function_id;
+    producer.outputs.push(Output {
+        name: "values".to_string(),
+        data_type: DataType::array_of(DataType::Float),
+        data_type_override: None,
+    });
+    let producer_id = producer.id();
+
+    let mut consumer = Node::new();
+    consumer.name = "consumer".to_string();
+    consumer.function_id = consumer_function_id;
+    consumer.is_output = true;
+    consumer.inputs.push(Input {
+        name: "values".to_string(),
+        data_type: DataType::array_of(DataType::Float),
+        is_required: true,
+        binding: Binding::from_output_binding(producer_id, 0),
+        const_value: None,
+    });
+    let consumer_id = consumer.id();
+
+    let mut graph = Graph::default();
+    graph.add_node(producer);
+    graph.add_node(consumer);
+    assert_eq!(graph.connection_check(consumer_id, 0, producer_id, 0), ConnectionStatus::Ok);
+    graph.validate()?;
+
+    let sum = Rc::new(Cell::new(0.0));
+    let consumer_sum = sum.clone();
+
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(producer_function_id, |_, _, outputs| {
+        outputs[0] = Value::from(vec![1.0, 2.0, 3.0]).into();
+    });
+    invoker.add_lambda(consumer_function_id, move |_, inputs, _| {
+        let values = inputs[0].as_ref().unwrap().as_array();
+        assert_eq!(values.len(), 3);
+        consumer_sum.set(values.iter().map(|value| value.as_float()).sum());
+    });
+    let compute = Compute::from(invoker);
+
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(sum.get(), 6.0);
+
+    Ok(())
+}
+
+#[test]
+fn run_within_budget_test() -> anyhow::Result<()> {
+    let names = ["load", "blur", "show"];
+    let function_ids = names.map(|_| FunctionId::unique());
+
+    let mut graph = Graph::default();
+    let mut previous_id: Option<NodeId> = None;
+    for (name, function_id) in names.iter().zip(function_ids) {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.function_id = function_id;
+        node.behavior = FunctionBehavior::Active;
+        node.is_output = *name == "show";
+        if let Some(previous_id) = previous_id {
+            node.inputs.push(Input {
+                name: "value".to_string(),
+                data_type: DataType::Float,
+                is_required: true,
+                binding: Binding::from_output_binding(previous_id, 0),
+                const_value: None,
+            });
+        }
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Float,
+            data_type_override: None,
+        });
+        previous_id = Some(node.id());
+        graph.add_node(node);
+    }
+
+    // quality each node was invoked with in the last run
+    let qualities: Rc<RefCell<Vec<(&str, f32)>>> = Rc::default();
+    let mut invoker = LambdaInvoker::default();
+    for (name, function_id) in names.into_iter().zip(function_ids) {
+        let qualities = qualities.clone();
+        invoker.add_lambda(function_id, move |ctx, _, outputs| {
+            if name == "blur" {
+                std::thread::sleep(Duration::from_millis(30));
+            }
+            qualities.borrow_mut().push((name, ctx.quality()));
+            outputs[0] = Value::from(ctx.quality() as f64).into();
+        });
+    }
+    let compute = Compute::from(invoker);
+    let preprocess = Preprocess::default();
+    let quality_of = |name: &str| qualities.borrow()
+        .iter()
+        .rfind(|(node_name, _)| *node_name == name)
+        .map(|(_, quality)| *quality)
+        .unwrap();
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run_within_budget(&graph, &mut runtime_graph, Duration::from_secs(3600))?;
+    assert!(names.iter().all(|name| quality_of(name) == 1.0));
+
+    // blur alone takes longer than the budget, show is degraded after it
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run_within_budget(&graph, &mut runtime_graph, Duration::from_millis(10))?;
+    assert_eq!(quality_of("load"), 1.0);
+    assert_eq!(quality_of("blur"), 1.0);
+    assert_eq!(quality_of("show"), MIN_QUALITY);
+
+    // run times of the previous run predict the overrun before blur starts
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run_within_budget(&graph, &mut runtime_graph, Duration::from_millis(10))?;
+    assert!(quality_of("load") < 1.0);
+    assert_eq!(quality_of("show"), MIN_QUALITY);
+
+    // plain runs always use full quality
+    compute.run(&graph, &mut preprocess.run(&graph, &mut runtime_graph)?)?;
+    assert!(names.iter().all(|name| quality_of(name) == 1.0));
+
+    Ok(())
+}
+
+#[test]
+fn param_hash_forces_execution() -> anyhow::Result<()> {
+    let names = ["load", "brightness", "scale", "show"];
+    let function_ids = names.map(|_| FunctionId::unique());
+
+    let mut graph = Graph::default();
+    let mut node_ids: Vec<NodeId> = Vec::new();
+    for (name, function_id) in names.iter().zip(function_ids) {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.function_id = function_id;
+        node.behavior = FunctionBehavior::Passive;
+        node.is_output = *name == "show";
+        if let Some(&previous_id) = node_ids.last() {
+            node.inputs.push(Input {
+                name: "value".to_string(),
+                data_type: DataType::Int,
+                is_required: true,
+                binding: Binding::from_output_binding(previous_id, 0),
+                const_value: None,
+            });
+        }
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Int,
+            data_type_override: None,
+        });
+        node_ids.push(node.id());
+        graph.add_node(node);
+    }
+
+    let executed: Rc<RefCell<Vec<&str>>> = Rc::default();
+    let mut invoker = LambdaInvoker::default();
+    for (name, function_id) in names.into_iter().zip(function_ids) {
+        let executed = executed.clone();
+        invoker.add_lambda(function_id, move |ctx, inputs, outputs| {
+            executed.borrow_mut().push(name);
+            let value = inputs.first()
+                .and_then(|input| input.as_ref())
+                .map_or(1, |input| input.as_int());
+            outputs[0] = Value::from(value + ctx.param_hash().unwrap_or(0) as i64).into();
+        });
+    }
+    let compute = Compute::from(invoker);
+    let preprocess = Preprocess::default();
+    let mut runtime_graph = RuntimeGraph::default();
+    let run = |runtime_graph: &mut RuntimeGraph| -> anyhow::Result<Vec<&str>> {
+        *runtime_graph = preprocess.run(&graph, runtime_graph)?;
+        compute.run(&graph, runtime_graph)?;
+        Ok(executed.take())
+    };
+
+    assert_eq!(run(&mut runtime_graph)?, names);
+    assert_eq!(run(&mut runtime_graph)?, ["show"]);
+
+    // bindings are unchanged, the new hash reruns brightness and everything downstream
+    runtime_graph.node_by_id_mut(node_ids[1]).unwrap()
+        .invoke_context_mut()
+        .set_param_hash(5);
+    assert_eq!(run(&mut runtime_graph)?, ["brightness", "scale", "show"]);
+    assert_eq!(run(&mut runtime_graph)?, ["show"]);
+
+    // setting the same hash again is not a change
+    runtime_graph.node_by_id_mut(node_ids[1]).unwrap()
+        .invoke_context_mut()
+        .set_param_hash(5);
+    assert_eq!(run(&mut runtime_graph)?, ["show"]);
+
+    Ok(())
+}
+
+#[test]
+fn missing_function_policy() -> anyhow::Result<()> {
+    let source_id = FunctionId::unique();
+    let sink_id = FunctionId::unique();
+
+    let mut graph = Graph::default();
+    let mut source = Node::new();
+    source.name = "source".to_string();
+    source.function_id = source_id;
+    source.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let mut unknown = Node::new();
+    unknown.name = "unknown".to_string();
+    unknown.function_id = FunctionId::unique();
+    unknown.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(source.id(), 0),
+        const_value: None,
+    });
+    for (name, data_type) in [("int", DataType::Int), ("text", DataType::String)] {
+        unknown.outputs.push(Output {
+            name: name.to_string(),
+            data_type,
+            data_type_override: None,
+        });
+    }
+    let mut sink = Node::new();
+    sink.name = "sink".to_string();
+    sink.function_id = sink_id;
+    sink.is_output = true;
+    for (index, (name, data_type)) in [("int", DataType::Int), ("text", DataType::String)].into_iter().enumerate() {
+        sink.inputs.push(Input {
+            name: name.to_string(),
+            data_type,
+            is_required: false,
+            binding: Binding::from_output_binding(unknown.id(), index as u32),
+            const_value: None,
+        });
+    }
+    graph.add_node(source);
+    graph.add_node(unknown);
+    graph.add_node(sink);
+
+    let received: Rc<RefCell<Vec<Option<Value>>>> = Rc::default();
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(source_id, |_, _, outputs| {
+        outputs[0] = Value::from(4).into();
+    });
+    let sink_received = received.clone();
+    invoker.add_lambda(sink_id, move |_, inputs, _| {
+        *sink_received.borrow_mut() = inputs.to_vec();
+    });
+    let mut compute = Compute::from(invoker);
+    let preprocess = Preprocess::default();
+
+    let mut run = |policy: MissingFunctionPolicy| -> anyhow::Result<Vec<Option<Value>>> {
+        compute.set_missing_function_policy(policy);
+        let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+        compute.run(&graph, &mut runtime_graph)?;
+        Ok(received.take())
+    };
+
+    let error = run(MissingFunctionPolicy::Error).err().unwrap().to_string();
+    assert!(error.contains("node unknown"), "{}", error);
+
+    // the int input fits the first output, nothing fits the second one
+    assert_eq!(run(MissingFunctionPolicy::PassthroughInputs)?, [Some(Value::from(4)), None]);
+
+    assert_eq!(
+        run(MissingFunctionPolicy::Zero)?,
+        [Some(Value::from(0)), Some(Value::from(String::new()))]
+    );
+
+    // a run failing partway leaves the runtime graph ready for the next preprocess
+    compute.set_missing_function_policy(MissingFunctionPolicy::Error);
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    assert!(compute.run(&graph, &mut runtime_graph).is_err());
+    compute.set_missing_function_policy(MissingFunctionPolicy::Zero);
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(received.take(), [Some(Value::from(0)), Some(Value::from(String::new()))]);
+
+    Ok(())
+}
+
+#[test]
+fn missing_function_passthrough_overridden_output() -> anyhow::Result<()> {
+    let source_id = FunctionId::unique();
+    let sink_id = FunctionId::unique();
+
+    let mut graph = Graph::default();
+    let mut source = Node::new();
+    source.name = "source".to_string();
+    source.function_id = source_id;
+    source.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    // declared as float, overridden to the int its input carries
+    let mut unknown = Node::new();
+    unknown.name = "unknown".to_string();
+    unknown.function_id = FunctionId::unique();
+    unknown.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(source.id(), 0),
+        const_value: None,
+    });
+    unknown.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        data_type_override: Some(DataType::Int),
+    });
+    let mut sink = Node::new();
+    sink.name = "sink".to_string();
+    sink.function_id = sink_id;
+    sink.is_output = true;
+    sink.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        is_required: false,
+        binding: Binding::from_output_binding(unknown.id(), 0),
+        const_value: None,
+    });
+    graph.add_node(source);
+    graph.add_node(unknown);
+    graph.add_node(sink);
+    graph.validate()?;
+
+    let received: Rc<RefCell<Vec<Option<Value>>>> = Rc::default();
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(source_id, |_, _, outputs| {
+        outputs[0] = Value::from(4).into();
+    });
+    let sink_received = received.clone();
+    invoker.add_lambda(sink_id, move |_, inputs, _| {
+        *sink_received.borrow_mut() = inputs.to_vec();
+    });
+    let mut compute = Compute::from(invoker);
+    compute.set_missing_function_policy(MissingFunctionPolicy::PassthroughInputs);
+
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(received.take(), [Some(Value::from(4))]);
+
+    Ok(())
+}
+
+#[test]
+fn execution_priority_orders_active_nodes() -> anyhow::Result<()> {
+    let write_id = FunctionId::unique();
+
+    let mut graph = Graph::default();
+    let mut node_ids: Vec<NodeId> = Vec::new();
+    // names alone would run "a_write" first
+    for (name, priority) in [("a_write", 1), ("b_write", -1)] {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.function_id = write_id;
+        node.behavior = FunctionBehavior::Active;
+        node.is_output = true;
+        node.execution_priority = priority;
+        node.inputs.push(Input {
+            name: "value".to_string(),
+            data_type: DataType::String,
+            is_required: true,
+            binding: Binding::Const,
+            const_value: Some(Value::from(name)),
+        });
+        node_ids.push(node.id());
+        graph.add_node(node);
+    }
+
+    let calls: Rc<RefCell<Vec<String>>> = Rc::default();
+    let mut invoker = LambdaInvoker::default();
+    let write_calls = calls.clone();
+    invoker.add_lambda(write_id, move |_, inputs, _| {
+        write_calls.borrow_mut().push(inputs[0].as_ref().unwrap().as_string().to_string());
+    });
+    let compute = Compute::from(invoker);
+    let preprocess = Preprocess::default();
+
+    let mut runtime_graph = preprocess.run(&graph, &mut RuntimeGraph::default())?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(calls.take(), to_names(&["b_write", "a_write"]));
+
+    graph.node_by_id_mut(node_ids[0]).unwrap().execution_priority = -2;
+    let mut runtime_graph = preprocess.run(&graph, &mut runtime_graph)?;
+    compute.run(&graph, &mut runtime_graph)?;
+    assert_eq!(calls.take(), to_names(&["a_write", "b_write"]));
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+use crate::data::{DataType, ImageHandle, Value, ValueCompare};
+use crate::graph::NodeId;
+use crate::run_snapshot::{NodeSnapshot, RunSnapshot};
+
+#[test]
+fn value_compare_test() -> anyhow::Result<()> {
+    let compare = ValueCompare::default();
+    let sum = Value::from(0.1 + 0.2);
+    let expected = Value::from(0.3);
+
+    assert_ne!(sum, expected);
+    assert!(compare.values_equal(&sum, &expected));
+    assert!(!ValueCompare::new(0.0).values_equal(&sum, &expected));
+    assert!(!compare.values_equal(&Value::from(0.3), &Value::from(0.4)));
+
+    let nan = Value::from(f64::NAN);
+    assert!(!compare.values_equal(&nan, &nan));
+    assert!(!ValueCompare::new(f64::INFINITY).values_equal(&nan, &expected));
+
+    let infinity = Value::from(f64::INFINITY);
+    assert!(compare.values_equal(&infinity, &infinity));
+    assert!(ValueCompare::new(0.0).values_equal(&infinity, &infinity));
+    assert!(!compare.values_equal(&infinity, &Value::from(f64::NEG_INFINITY)));
+    assert!(!compare.values_equal(&infinity, &Value::from(f64::MAX)));
+
+    assert!(compare.values_equal(&Value::from(3), &Value::from(3)));
+    assert!(!compare.values_equal(&Value::from(3), &Value::from(3.0)));
+
+    assert!(compare.args_equal(&[Some(sum.clone()), None], &[Some(expected.clone()), None]));
+    assert!(!compare.args_equal(&[Some(sum.clone())], &[None]));
+    assert!(!compare.args_equal(&[Some(sum)], &[]));
+
+    Ok(())
+}
+
+#[test]
+fn snapshot_diff_tolerance_test() -> anyhow::Result<()> {
+    let node_id = NodeId::unique();
+    let snapshot = |value: f64| RunSnapshot {
+        structural_hash: 0,
+        nodes: vec![NodeSnapshot {
+            node_id,
+            inputs: vec![],
+            outputs: vec![Some(Value::from(value))],
+        }],
+    };
+
+    assert_eq!(snapshot(0.1 + 0.2).diff(&snapshot(0.3)), None);
+    assert_eq!(snapshot(0.1 + 0.2).diff_with(&snapshot(0.3), &ValueCompare::new(0.0)), Some(node_id));
+    assert_eq!(snapshot(f64::NAN).diff(&snapshot(f64::NAN)), Some(node_id));
+    assert_eq!(snapshot(f64::INFINITY).diff(&snapshot(f64::INFINITY)), None);
+
+    Ok(())
+}
+
+#[test]
+fn data_type_names_test() -> anyhow::Result<()> {
+    assert!(!DataType::all().contains(&DataType::Null));
+    assert!(!DataType::all().contains(&DataType::Generic));
+    assert_eq!(DataType::from_str("generic"), Ok(DataType::Generic));
+    assert!(DataType::can_assign(&DataType::Float, &DataType::Generic));
+    assert!(!DataType::can_assign(&DataType::Float, &DataType::Null));
+    assert!(DataType::all().contains(&DataType::Image));
+
+    for data_type in DataType::all() {
+        assert_eq!(DataType::from_str(data_type.name()), Ok(data_type.clone()));
+        assert_eq!(data_type.to_string(), data_type.name());
+    }
+    assert!(DataType::from_str("integer").is_err());
+
+    let float_array = DataType::array_of(DataType::Float);
+    assert_eq!(float_array.to_string(), "array<float>");
+    assert_eq!(DataType::from_str("array<float>"), Ok(float_array.clone()));
+    assert!(DataType::from_str("array<integer>").is_err());
+    assert!(DataType::can_assign(&float_array, &float_array));
+    assert!(!DataType::can_assign(&float_array, &DataType::array_of(DataType::Int)));
+
+    let float_vec3 = DataType::fixed_array_of(DataType::Float, 3);
+    assert_eq!(float_vec3.to_string(), "array<float, 3>");
+    assert_eq!(DataType::from_str("array<float, 3>"), Ok(float_vec3.clone()));
+    let nested = DataType::fixed_array_of(float_vec3.clone(), 2);
+    assert_eq!(DataType::from_str(&nested.to_string()), Ok(nested.clone()));
+    assert_eq!(
+        DataType::from_str("array<array<float, 3>>"),
+        Ok(DataType::array_of(float_vec3.clone()))
+    );
+    assert!(DataType::from_str("array<float, -1>").is_err());
+
+    // any length is accepted where none is required, a fixed length has to match exactly
+    assert!(DataType::can_assign(&float_vec3, &float_array));
+    assert!(!DataType::can_assign(&float_array, &float_vec3));
+    assert!(!DataType::can_assign(&DataType::fixed_array_of(DataType::Float, 4), &float_vec3));
+    assert!(DataType::can_assign(&float_vec3, &float_vec3));
+
+    let default_vec3 = Value::default_for(&float_vec3);
+    assert_eq!(default_vec3, Value::from(vec![0.0f64; 3]));
+    assert!(default_vec3.has_type(&float_vec3));
+    assert!(default_vec3.has_type(&float_array));
+    assert!(!Value::from(vec![0.0f64; 2]).has_type(&float_vec3));
+
+    Ok(())
+}
+
+#[test]
+fn image_values() -> anyhow::Result<()> {
+    assert_eq!(DataType::from_str("image"), Ok(DataType::Image));
+    assert_eq!(DataType::Image.to_string(), "image");
+    assert_eq!(Value::default_for(&DataType::Image), Value::Null);
+
+    let image = Value::from(ImageHandle::new(vec![1u8, 2, 3]));
+    assert!(image.has_type(&DataType::Image));
+    assert_eq!(image.as_image().downcast_ref::<Vec<u8>>(), Some(&vec![1u8, 2, 3]));
+    assert!(image.as_image().downcast_ref::<String>().is_none());
+
+    // handles are compared by identity, not by content
+    assert_eq!(image, image.clone());
+    assert_ne!(image, Value::from(ImageHandle::new(vec![1u8, 2, 3])));
+
+    assert!(serde_yaml::to_string(&image).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::graph::Graph;
+use crate::graph_format::{GraphFormat, JsonFormat, YamlFormat};
+
+// binary format defined outside the crate's built-ins,
+// self-describing as GraphFormat requires
+struct MessagePackFormat {}
+
+impl GraphFormat for MessagePackFormat {
+    fn serialize(&self, graph: &Graph) -> anyhow::Result<Vec<u8>> {
+        let bytes = rmp_serde::to_vec_named(graph)?;
+        Ok(bytes)
+    }
+    fn deserialize(&self, bytes: &[u8]) -> anyhow::Result<Graph> {
+        let graph: Graph = rmp_serde::from_slice(bytes)?;
+        Ok(graph)
+    }
+}
+
+fn round_trip<F: GraphFormat>(format: &F, path: &str) -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    graph.save_with(format, path)?;
+    let loaded = Graph::load_with(format, path)?;
+
+    assert_eq!(graph.to_yaml()?, loaded.to_yaml()?);
+
+    Ok(())
+}
+
+#[test]
+fn yaml_format_round_trip() -> anyhow::Result<()> {
+    round_trip(&YamlFormat::default(), "../test_output/test_graph.yml")
+}
+
+#[test]
+fn json_format_round_trip() -> anyhow::Result<()> {
+    round_trip(&JsonFormat::default(), "../test_output/test_graph.json")
+}
+
+#[test]
+fn custom_format_round_trip() -> anyhow::Result<()> {
+    round_trip(&MessagePackFormat {}, "../test_output/test_graph.msgpack")
+}
@@ -1,7 +1,13 @@
 use std::hint::black_box;
 
-use crate::data::{DataType, Value};
+use glam::Vec2;
+
+use crate::data::{Coercion, DataType, Value};
+use crate::functions::FunctionId;
 use crate::graph::*;
+use crate::preprocess::Preprocess;
+use crate::runtime_graph::RuntimeGraph;
+use crate::subgraph::SubGraphId;
 
 #[test]
 fn graph_to_yaml() -> anyhow::Result<()> {
@@ -10,6 +16,7 @@ fn graph_to_yaml() -> anyhow::Result<()> {
     node1.outputs.push(Output {
         name: "output1".to_string(),
         data_type: DataType::Int,
+        data_type_override: None,
     });
     node1.inputs.push(Input {
         name: "input1".to_string(),
@@ -26,6 +33,7 @@ fn graph_to_yaml() -> anyhow::Result<()> {
         binding: Binding::Output(OutputBinding {
             output_node_id: node1.id(),
             output_index: 0,
+            behavior: BindingBehavior::Always,
         }),
         const_value: None,
     });
@@ -66,3 +74,978 @@ fn node_remove_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn remove_passthroughs_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let val1_id = graph.node_by_name("val1").unwrap().id();
+
+    let mut passthrough = Node::new();
+    passthrough.name = "passthrough".to_string();
+    passthrough.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(val1_id, 0),
+        const_value: None,
+    });
+    passthrough.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let passthrough_id = passthrough.id();
+    graph.add_node(passthrough);
+    graph.node_by_name_mut("sum").unwrap()
+        .inputs[0].binding = Binding::from_output_binding(passthrough_id, 0);
+    graph.validate()?;
+
+    let removed_count = graph.remove_passthroughs(|node| node.name == "passthrough");
+    assert_eq!(removed_count, 1);
+    assert!(graph.node_by_id(passthrough_id).is_none());
+
+    let binding = graph.node_by_name("sum").unwrap()
+        .inputs[0].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, val1_id);
+    assert_eq!(binding.output_index, 0);
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn remove_passthrough_keeps_once_binding() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let val1_id = graph.node_by_name("val1").unwrap().id();
+    let val2_id = graph.node_by_name("val2").unwrap().id();
+
+    let passthrough = |name: &str, source_id: NodeId, behavior: BindingBehavior| {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.inputs.push(Input {
+            name: "value".to_string(),
+            data_type: DataType::Int,
+            is_required: true,
+            binding: Binding::Output(OutputBinding {
+                output_node_id: source_id,
+                output_index: 0,
+                behavior,
+            }),
+            const_value: None,
+        });
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Int,
+            data_type_override: None,
+        });
+        node
+    };
+    // sum reads val1 through a Once edge out of the passthrough, and val2 through a Once edge into one
+    let once_out = passthrough("once out", val1_id, BindingBehavior::Always);
+    let once_out_id = once_out.id();
+    graph.add_node(once_out);
+    let once_in = passthrough("once in", val2_id, BindingBehavior::Once);
+    let once_in_id = once_in.id();
+    graph.add_node(once_in);
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    graph.connect_with_behavior(sum_id, 0, once_out_id, 0, BindingBehavior::Once)?;
+    graph.connect(sum_id, 1, once_in_id, 0)?;
+
+    let removed_count = graph.remove_passthroughs(|node| node.name.starts_with("once"));
+    assert_eq!(removed_count, 2);
+
+    let sum = graph.node_by_id(sum_id).unwrap();
+    let bindings: Vec<(NodeId, BindingBehavior)> = sum.inputs
+        .iter()
+        .map(|input| {
+            let output_binding = input.binding.as_output_binding().unwrap();
+            (output_binding.output_node_id, output_binding.behavior)
+        })
+        .collect();
+    assert_eq!(bindings, vec![(val1_id, BindingBehavior::Once), (val2_id, BindingBehavior::Once)]);
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn remove_passthrough_feeding_subgraph_output() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_subgraph.yml")?;
+    let subgraph_id = graph.subgraphs()[0].id();
+    let circumference_id = graph.node_by_name("circumference").unwrap().id();
+    let value_id = graph.node_by_name("value").unwrap().id();
+
+    let passthrough = |name: &str, source_id: NodeId| {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.subgraph_id = Some(subgraph_id);
+        node.inputs.push(Input {
+            name: "value".to_string(),
+            data_type: DataType::Float,
+            is_required: true,
+            binding: Binding::from_output_binding(source_id, 0),
+            const_value: None,
+        });
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Float,
+            data_type_override: None,
+        });
+        node
+    };
+    // the subgraph outputs read through passthroughs, one fed from inside the subgraph, one from outside
+    let internal = passthrough("internal", circumference_id);
+    let internal_id = internal.id();
+    graph.add_node(internal);
+    let external = passthrough("external", value_id);
+    let external_id = external.id();
+    graph.add_node(external);
+    let subgraph = graph.subgraph_by_id_mut(subgraph_id).unwrap();
+    subgraph.outputs[0].subnode_id = internal_id;
+    subgraph.outputs[1].subnode_id = external_id;
+    graph.validate()?;
+
+    let removed_count = graph.remove_passthroughs(|node| node.name == "internal" || node.name == "external");
+    assert_eq!(removed_count, 1);
+    assert!(graph.node_by_id(internal_id).is_none());
+    assert!(graph.node_by_id(external_id).is_some());
+
+    assert_eq!(graph.resolve_suboutput(subgraph_id, 0), Some((circumference_id, 0)));
+    assert_eq!(graph.resolve_suboutput(subgraph_id, 1), Some((external_id, 0)));
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn remove_node_output_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let mut producer = Node::new();
+    producer.add_output(Output {
+        name: "first".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    producer.add_output(Output {
+        name: "second".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let producer_id = producer.id();
+
+    let mut consumer = Node::new();
+    consumer.add_input(Input {
+        name: "input".to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(producer_id, 1),
+        const_value: None,
+    });
+    let consumer_id = consumer.id();
+
+    graph.add_node(producer);
+    graph.add_node(consumer);
+
+    assert!(graph.remove_node_output(producer_id, 2).is_err());
+    graph.remove_node_output(producer_id, 0)?;
+
+    let producer = graph.node_by_id(producer_id).unwrap();
+    assert_eq!(producer.outputs.len(), 1);
+    assert_eq!(producer.outputs[0].name, "second");
+
+    let binding = graph.node_by_id(consumer_id).unwrap()
+        .inputs[0].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, producer_id);
+    assert_eq!(binding.output_index, 0);
+
+    graph.remove_node_output(producer_id, 0)?;
+    assert!(!graph.node_by_id(consumer_id).unwrap().inputs[0].binding.is_some());
+
+    graph.remove_node_input(consumer_id, 0)?;
+    assert!(graph.node_by_id(consumer_id).unwrap().inputs.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn remove_watched_input_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let mut node = Node::new();
+    for name in ["first", "second", "third"] {
+        node.add_input(Input {
+            name: name.to_string(),
+            data_type: DataType::Int,
+            is_required: false,
+            binding: Binding::None,
+            const_value: None,
+        });
+    }
+    node.behavior = FunctionBehavior::OnInputChange(2);
+    let node_id = node.id();
+    graph.add_node(node);
+
+    // removing an earlier input keeps watching the same one
+    graph.remove_node_input(node_id, 0)?;
+    let node = graph.node_by_id(node_id).unwrap();
+    assert_eq!(node.behavior, FunctionBehavior::OnInputChange(1));
+    assert_eq!(node.inputs[1].name, "third");
+    graph.validate()?;
+
+    // a later input doesn't affect the index
+    graph.node_by_id_mut(node_id).unwrap().behavior = FunctionBehavior::OnInputChange(0);
+    graph.remove_node_input(node_id, 1)?;
+    assert_eq!(graph.node_by_id(node_id).unwrap().behavior, FunctionBehavior::OnInputChange(0));
+
+    // removing the watched input leaves the node passive
+    graph.remove_node_input(node_id, 0)?;
+    assert_eq!(graph.node_by_id(node_id).unwrap().behavior, FunctionBehavior::Passive);
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn remove_node_output_rewiring_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let mut producer = Node::new();
+    for name in ["first", "second", "third"] {
+        producer.add_output(Output {
+            name: name.to_string(),
+            data_type: DataType::Int,
+            data_type_override: None,
+        });
+    }
+    let producer_id = producer.id();
+
+    let consumer_input = |output_index: u32| Input {
+        name: format!("input{}", output_index),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(producer_id, output_index),
+        const_value: None,
+    };
+    let mut consumer1 = Node::new();
+    consumer1.add_input(consumer_input(0));
+    consumer1.add_input(consumer_input(2));
+    let consumer1_id = consumer1.id();
+    let mut consumer2 = Node::new();
+    consumer2.add_input(consumer_input(1));
+    let consumer2_id = consumer2.id();
+
+    graph.add_node(producer);
+    graph.add_node(consumer1);
+    graph.add_node(consumer2);
+
+    graph.remove_node_output(producer_id, 0)?;
+    graph.validate()?;
+
+    let producer = graph.node_by_id(producer_id).unwrap();
+    assert_eq!(
+        producer.outputs.iter().map(|output| output.name.as_str()).collect::<Vec<&str>>(),
+        ["second", "third"]
+    );
+
+    let output_index = |node_id: NodeId, input_index: usize| {
+        graph.node_by_id(node_id).unwrap()
+            .inputs[input_index].binding
+            .as_output_binding()
+            .map(|binding| binding.output_index)
+    };
+    // the consumer of the removed output is unbound, the others follow their outputs
+    assert_eq!(output_index(consumer1_id, 0), None);
+    assert_eq!(output_index(consumer1_id, 1), Some(1));
+    assert_eq!(output_index(consumer2_id, 0), Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn dedupe_nodes_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    let sum = graph.node_by_name("sum").unwrap().clone();
+    let sum_id = sum.id();
+    let mut sum_copy = Node::new();
+    sum_copy.function_id = sum.function_id;
+    sum_copy.name = "sum copy".to_string();
+    sum_copy.behavior = sum.behavior;
+    sum_copy.should_cache_outputs = sum.should_cache_outputs;
+    sum_copy.inputs = sum.inputs.clone();
+    sum_copy.outputs = sum.outputs.clone();
+    let sum_copy_id = sum_copy.id();
+    graph.add_node(sum_copy);
+
+    let mult = graph.node_by_name_mut("mult").unwrap();
+    mult.inputs[0].binding = Binding::from_output_binding(sum_copy_id, 0);
+
+    assert_eq!(graph.dedupe_nodes(), 1);
+    assert!(graph.node_by_id(sum_copy_id).is_none());
+
+    let binding = graph.node_by_name("mult").unwrap()
+        .inputs[0].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, sum_id);
+    assert_eq!(binding.output_index, 0);
+    graph.validate()?;
+
+    assert_eq!(graph.dedupe_nodes(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn dedupe_keeps_distinct_nodes_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    // same function and bindings as sum, differing in one attribute
+    let with_copy = |modify: &dyn Fn(&mut Node)| {
+        let mut graph = graph.clone();
+        let sum = graph.node_by_name("sum").unwrap().clone();
+        let mut sum_copy = Node::new();
+        sum_copy.function_id = sum.function_id;
+        sum_copy.name = "sum copy".to_string();
+        sum_copy.behavior = sum.behavior;
+        sum_copy.should_cache_outputs = sum.should_cache_outputs;
+        sum_copy.inputs = sum.inputs.clone();
+        sum_copy.outputs = sum.outputs.clone();
+        modify(&mut sum_copy);
+        graph.add_node(sum_copy);
+        graph
+    };
+
+    assert_eq!(with_copy(&|_| {}).dedupe_nodes(), 1);
+    assert_eq!(with_copy(&|node| node.behavior = FunctionBehavior::Active).dedupe_nodes(), 0);
+    assert_eq!(with_copy(&|node| node.behavior = FunctionBehavior::OnInputChange(1)).dedupe_nodes(), 0);
+    assert_eq!(with_copy(&|node| node.should_cache_outputs = !node.should_cache_outputs).dedupe_nodes(), 0);
+    assert_eq!(with_copy(&|node| node.backend = NodeBackend::Cpu).dedupe_nodes(), 0);
+    assert_eq!(with_copy(&|node| node.outputs[0].data_type_override = Some(DataType::Float)).dedupe_nodes(), 0);
+
+    // two active nodes without bound inputs keep their own state
+    let mut graph = Graph::default();
+    let function_id = FunctionId::unique();
+    for name in ["first", "second"] {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.function_id = function_id;
+        node.behavior = FunctionBehavior::Active;
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Int,
+            data_type_override: None,
+        });
+        graph.add_node(node);
+    }
+    assert_eq!(graph.dedupe_nodes(), 0);
+    assert_eq!(graph.nodes().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn connection_check_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let mut producer = Node::new();
+    producer.add_output(Output {
+        name: "int".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let producer_id = producer.id();
+
+    let mut consumer = Node::new();
+    for data_type in [DataType::Int, DataType::Float, DataType::String] {
+        consumer.add_input(Input {
+            name: data_type.to_string(),
+            data_type,
+            is_required: true,
+            binding: Binding::None,
+            const_value: None,
+        });
+    }
+    let consumer_id = consumer.id();
+
+    graph.add_node(producer);
+    graph.add_node(consumer);
+
+    assert_eq!(
+        graph.connection_check(consumer_id, 0, producer_id, 0),
+        ConnectionStatus::Ok
+    );
+    assert_eq!(
+        graph.connection_check(consumer_id, 1, producer_id, 0),
+        ConnectionStatus::NeedsCoercion(Coercion::IntToFloat)
+    );
+    assert!(matches!(
+        graph.connection_check(consumer_id, 2, producer_id, 0),
+        ConnectionStatus::Incompatible(_)
+    ));
+    assert!(matches!(
+        graph.connection_check(consumer_id, 3, producer_id, 0),
+        ConnectionStatus::Incompatible(_)
+    ));
+    assert!(matches!(
+        graph.connection_check(producer_id, 0, producer_id, 0),
+        ConnectionStatus::Incompatible(_)
+    ));
+
+    // checking must not bind anything
+    assert!(graph.node_by_id(consumer_id).unwrap()
+        .inputs
+        .iter()
+        .all(|input| !input.binding.is_some()));
+
+    Ok(())
+}
+
+#[test]
+fn bindings_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    let bound_input_count = graph.nodes()
+        .iter()
+        .flat_map(|node| node.inputs.iter())
+        .filter(|input| input.binding.is_some())
+        .count();
+    assert_eq!(bound_input_count, 5);
+    assert_eq!(graph.bindings().count(), bound_input_count);
+
+    let mult_id = graph.node_by_name("mult").unwrap().id();
+    let edge = graph.bindings()
+        .find(|edge| edge.dst_node_id == mult_id && edge.dst_input_index == 1)
+        .unwrap();
+    assert!(edge.binding.is_output_binding());
+
+    graph.bindings_mut()
+        .filter(|edge| edge.dst_node_id == mult_id)
+        .for_each(|edge| *edge.binding = Binding::None);
+    assert_eq!(graph.bindings().count(), bound_input_count - 2);
+
+    Ok(())
+}
+
+#[test]
+fn load_without_validation() -> anyhow::Result<()> {
+    let yaml = std::fs::read_to_string("../test_resources/test_graph.yml")?;
+    // bind mult to a node missing from the file, only validation can catch it
+    let yaml = yaml.replace(
+        "output_node_id: 999c4d37-e0eb-4856-be3f-ad2090c84d8c",
+        "output_node_id: 00000000-0000-0000-0000-000000000001",
+    );
+
+    assert!(Graph::from_yaml(&yaml).is_err());
+
+    let options = LoadOptions {
+        validate: false,
+    };
+    let graph = Graph::from_yaml_with_options(&yaml, &options)?;
+    assert_eq!(graph.nodes().len(), 5);
+    assert!(graph.validate().is_err());
+
+    let graph = Graph::from_yaml_file_with_options("../test_resources/test_graph.yml", &options)?;
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn function_usage_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    let sum_function_id = graph.node_by_name("sum").unwrap().function_id;
+    let mult_function_id = graph.node_by_name("mult").unwrap().function_id;
+    for _ in 0..2 {
+        let mut node = Node::new();
+        node.function_id = sum_function_id;
+        graph.add_node(node);
+    }
+
+    let usage = graph.function_usage();
+    assert_eq!(usage.len(), 5);
+    assert_eq!(usage[&sum_function_id], 3);
+    assert_eq!(usage[&mult_function_id], 1);
+    assert_eq!(usage.values().sum::<usize>(), graph.nodes().len());
+
+    Ok(())
+}
+
+#[test]
+fn computable_outputs_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let val1_id = graph.node_by_name("val1").unwrap().id();
+    let val2_id = graph.node_by_name("val2").unwrap().id();
+    let print_id = graph.node_by_name("print").unwrap().id();
+
+    // second output depending on val2 only
+    let mut print2 = Node::new();
+    print2.name = "print2".to_string();
+    print2.is_output = true;
+    print2.inputs = graph.node_by_id(print_id).unwrap().inputs.clone();
+    print2.inputs[0].binding = Binding::from_output_binding(val2_id, 0);
+    let print2_id = print2.id();
+    graph.add_node(print2);
+    graph.validate()?;
+
+    assert_eq!(graph.computable_outputs(&[]), vec![]);
+    assert_eq!(graph.computable_outputs(&[val1_id]), vec![]);
+    assert_eq!(graph.computable_outputs(&[val2_id]), vec![print2_id]);
+    assert_eq!(graph.computable_outputs(&[val1_id, val2_id]), vec![print_id, print2_id]);
+
+    // optional inputs don't block
+    graph.node_by_name_mut("sum").unwrap().inputs[0].is_required = false;
+    assert_eq!(graph.computable_outputs(&[val2_id]), vec![print_id, print2_id]);
+
+    Ok(())
+}
+
+#[test]
+fn topological_order_test() -> anyhow::Result<()> {
+    let source = |name: &str| {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: DataType::Int,
+            data_type_override: None,
+        });
+        node
+    };
+    let input = |name: &str, node: &Node| Input {
+        name: name.to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(node.id(), 0),
+        const_value: None,
+    };
+
+    // independent sources added out of name order
+    let b = source("b");
+    let a = source("a");
+    let mut c = Node::new();
+    c.name = "c".to_string();
+    c.is_output = true;
+    c.inputs.push(input("first", &a));
+    c.inputs.push(input("second", &b));
+    let (a_id, b_id, c_id) = (a.id(), b.id(), c.id());
+
+    let mut graph = Graph::default();
+    graph.add_node(c);
+    graph.add_node(b);
+    graph.add_node(a);
+    graph.validate()?;
+
+    // ready nodes are ordered by name
+    assert_eq!(graph.topological_order()?, vec![a_id, b_id, c_id]);
+
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    let schedule = runtime_graph.nodes
+        .iter()
+        .map(|r_node| r_node.node_id())
+        .collect::<Vec<NodeId>>();
+    assert_eq!(schedule, vec![a_id, b_id, c_id]);
+
+    // a cycle has no topological order
+    graph.node_by_id_mut(a_id).unwrap().inputs.push(Input {
+        name: "loop".to_string(),
+        data_type: DataType::Int,
+        is_required: true,
+        binding: Binding::from_output_binding(c_id, 0),
+        const_value: None,
+    });
+    assert!(graph.topological_order().is_err());
+    // preprocess reports the cycle instead of panicking
+    let mut previous_runtime = runtime_graph;
+    assert!(Preprocess::default().run(&graph, &mut previous_runtime).is_err());
+    assert_eq!(previous_runtime.nodes.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn graph_payload_test() -> anyhow::Result<()> {
+    #[derive(Debug, PartialEq)]
+    struct HostData {
+        world_id: u32,
+    }
+
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    assert!(graph.payload::<HostData>().is_none());
+
+    graph.set_payload(HostData { world_id: 3 });
+    graph.set_payload(String::from("render context"));
+    assert_eq!(graph.payload::<HostData>(), Some(&HostData { world_id: 3 }));
+    assert_eq!(graph.payload::<String>().map(String::as_str), Some("render context"));
+
+    graph.set_payload(HostData { world_id: 4 });
+    assert_eq!(graph.payload::<HostData>().unwrap().world_id, 4);
+
+    // payloads are not serialized
+    let loaded = Graph::from_yaml(&graph.to_yaml()?)?;
+    assert!(loaded.payload::<HostData>().is_none());
+
+    graph.remove_payload::<HostData>();
+    assert!(graph.payload::<HostData>().is_none());
+    assert!(graph.payload::<String>().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn frozen_graph_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let hash = graph.structural_hash();
+
+    // FrozenGraph has no add_node, nodes_mut or node_by_id_mut,
+    // so it can only be read and executed
+    let frozen = graph.freeze();
+    assert_eq!(frozen.nodes().len(), 5);
+    assert_eq!(frozen.structural_hash(), hash);
+    let sum_id = frozen.node_by_name("sum").unwrap().id();
+    assert_eq!(frozen.node_by_id(sum_id).unwrap().name, "sum");
+    assert_eq!(frozen.topological_order()?.len(), 5);
+
+    let runtime_graph = Preprocess::default().run(frozen.graph(), &mut RuntimeGraph::default())?;
+    assert_eq!(runtime_graph.nodes.len(), 5);
+
+    let mut graph = frozen.thaw();
+    graph.add_node(Node::new());
+    assert_eq!(graph.nodes().len(), 6);
+
+    Ok(())
+}
+
+#[test]
+fn source_nodes_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    let mut source_names = graph.source_nodes()
+        .iter()
+        .map(|node| node.name.as_str())
+        .collect::<Vec<&str>>();
+    source_names.sort();
+    assert_eq!(source_names, ["val1", "val2"]);
+
+    // sum becomes a source once its inputs are set by constants
+    graph.node_by_name_mut("sum").unwrap()
+        .inputs
+        .iter_mut()
+        .for_each(|input| input.binding = Binding::Const);
+    assert!(graph.source_nodes().iter().any(|node| node.name == "sum"));
+
+    // a required unbound input keeps it from being an entry point
+    graph.node_by_name_mut("sum").unwrap()
+        .inputs[0].binding = Binding::None;
+    assert!(!graph.source_nodes().iter().any(|node| node.name == "sum"));
+    assert!(!graph.source_nodes().iter().any(|node| node.name == "mult" || node.name == "print"));
+
+    Ok(())
+}
+
+#[test]
+fn connect_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let val1_id = graph.node_by_name("val1").unwrap().id();
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    let mult_id = graph.node_by_name("mult").unwrap().id();
+
+    graph.connect_with_behavior(mult_id, 1, val1_id, 0, BindingBehavior::Once)?;
+    let binding = graph.node_by_id(mult_id).unwrap()
+        .inputs[1].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, val1_id);
+    assert_eq!(binding.behavior, BindingBehavior::Once);
+
+    graph.connect(mult_id, 1, sum_id, 0)?;
+    let binding = graph.node_by_id(mult_id).unwrap()
+        .inputs[1].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, sum_id);
+    assert_eq!(binding.behavior, BindingBehavior::Always);
+
+    // cycles and invalid ports are rejected, leaving the binding unchanged
+    assert!(graph.connect(sum_id, 0, mult_id, 0).is_err());
+    assert!(graph.connect(sum_id, 2, val1_id, 0).is_err());
+    assert!(graph.connect(sum_id, 0, sum_id, 0).is_err());
+    let binding = graph.node_by_id(sum_id).unwrap()
+        .inputs[0].binding
+        .as_output_binding().unwrap();
+    assert_eq!(binding.output_node_id, val1_id);
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn node_display_order_test() -> anyhow::Result<()> {
+    let names = ["first", "second", "third", "fourth"];
+    let mut graph = Graph::default();
+    for name in names {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        graph.add_node(node);
+    }
+    let second_id = graph.node_by_name("second").unwrap().id();
+
+    // replacing a node keeps its place
+    let mut second = graph.node_by_id(second_id).unwrap().clone();
+    second.name = "second replaced".to_string();
+    graph.add_node(second);
+
+    // storage order changes, display order does not
+    graph.nodes_mut().reverse();
+    let third_id = graph.node_by_name("third").unwrap().id();
+    graph.remove_node_by_id(third_id);
+
+    let display_names = |graph: &Graph| -> Vec<String> {
+        graph.nodes_in_order()
+            .iter()
+            .map(|node| node.name.clone())
+            .collect()
+    };
+    let expected = vec!["first", "second replaced", "fourth"];
+    assert_eq!(display_names(&graph), expected);
+
+    let mut graph = Graph::from_yaml(&graph.to_yaml()?)?;
+    assert_eq!(display_names(&graph), expected);
+    assert_eq!(
+        graph.nodes_in_order().iter().map(|node| node.order()).collect::<Vec<u32>>(),
+        vec![0, 1, 3]
+    );
+
+    let mut fifth = Node::new();
+    fifth.name = "fifth".to_string();
+    graph.add_node(fifth);
+    assert_eq!(graph.node_by_name("fifth").unwrap().order(), 4);
+
+    graph.compact();
+    assert_eq!(display_names(&graph), vec!["first", "second replaced", "fourth", "fifth"]);
+    assert_eq!(
+        graph.nodes_in_order().iter().map(|node| node.order()).collect::<Vec<u32>>(),
+        vec![0, 1, 2, 3]
+    );
+
+    let mut sixth = Node::new();
+    sixth.name = "sixth".to_string();
+    graph.add_node(sixth);
+    assert_eq!(graph.node_by_name("sixth").unwrap().order(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn structurally_eq_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let clone = graph.clone_with_new_ids();
+
+    assert!(graph.nodes().iter().all(|node| clone.node_by_id(node.id()).is_none()));
+    assert_ne!(graph.to_yaml()?, clone.to_yaml()?);
+    assert!(graph.structurally_eq(&clone));
+    assert!(clone.structurally_eq(&Graph::from_yaml(&clone.to_yaml()?)?));
+    clone.validate()?;
+
+    // rewiring an input breaks the equivalence
+    let mut rewired = clone.clone();
+    let val1_id = rewired.node_by_name("val1").unwrap().id();
+    rewired.node_by_name_mut("mult").unwrap().inputs[1].binding = Binding::from_output_binding(val1_id, 0);
+    assert!(!graph.structurally_eq(&rewired));
+
+    let mut renamed = clone.clone();
+    renamed.node_by_name_mut("sum").unwrap().name = "add".to_string();
+    assert!(!graph.structurally_eq(&renamed));
+
+    // nodes are paired regardless of where they are stored
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(&clone.to_yaml()?)?;
+    yaml["nodes"]
+        .as_sequence_mut()
+        .unwrap()
+        .reverse();
+    let reversed = Graph::from_yaml(&serde_yaml::to_string(&yaml)?)?;
+    assert_ne!(reversed.nodes()[0].name, graph.nodes()[0].name);
+    assert!(graph.structurally_eq(&reversed));
+    assert!(reversed.structurally_eq(&graph));
+    assert!(!reversed.structurally_eq(&rewired));
+
+    Ok(())
+}
+
+#[test]
+fn structurally_eq_symmetric_nodes_test() -> anyhow::Result<()> {
+    let int_output = || Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    };
+    let int_input = |index: usize, binding: Binding| Input {
+        name: format!("value {}", index),
+        data_type: DataType::Int,
+        is_required: true,
+        binding,
+        const_value: None,
+    };
+
+    // identical sources, each through an identical node into its own input of one sum;
+    // pairing the sources by search alone tries every permutation of them
+    let source_count = 12;
+    let build = |reversed: bool, skip_last_pass: bool| -> Graph {
+        let mut graph = Graph::default();
+        let mut sum = Node::new();
+        sum.name = "sum".to_string();
+        sum.is_output = true;
+        let mut nodes = Vec::new();
+        for index in 0..source_count {
+            let mut source = Node::new();
+            source.name = "source".to_string();
+            source.outputs.push(int_output());
+
+            let mut pass = Node::new();
+            pass.name = "pass".to_string();
+            pass.inputs.push(int_input(0, Binding::from_output_binding(source.id(), 0)));
+            pass.outputs.push(int_output());
+
+            let bound_id = if skip_last_pass && index == source_count - 1 { source.id() } else { pass.id() };
+            sum.inputs.push(int_input(index, Binding::from_output_binding(bound_id, 0)));
+            nodes.push(source);
+            nodes.push(pass);
+        }
+        nodes.push(sum);
+        if reversed {
+            nodes.reverse();
+        }
+        nodes.into_iter().for_each(|node| graph.add_node(node));
+
+        graph
+    };
+
+    let graph = build(false, false);
+    assert!(graph.structurally_eq(&build(true, false)));
+    assert!(!graph.structurally_eq(&build(true, true)));
+
+    Ok(())
+}
+
+#[test]
+fn copy_paste_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    graph.node_by_name_mut("sum").unwrap().position = Vec2::new(10.0, 20.0);
+    let sum = graph.node_by_name("sum").unwrap();
+    let mult = graph.node_by_name("mult").unwrap();
+    let fragment = graph.copy_selection(&[sum.id(), mult.id()])?;
+
+    // the other graph keeps its own nodes, the pasted ones get fresh ids
+    let mut other = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let pasted_ids = other.paste(&fragment, Vec2::new(5.0, -5.0))?;
+    assert_eq!(pasted_ids.len(), 2);
+    assert_eq!(other.nodes().len(), graph.nodes().len() + 2);
+    assert!(pasted_ids.iter().all(|&id| id != sum.id() && id != mult.id()));
+
+    let pasted = |name: &str| {
+        pasted_ids.iter()
+            .map(|&id| other.node_by_id(id).unwrap())
+            .find(|node| node.name == name)
+            .unwrap()
+    };
+    let pasted_sum = pasted("sum");
+    let pasted_mult = pasted("mult");
+    assert_eq!(pasted_sum.function_id, sum.function_id);
+    assert_eq!(pasted_sum.position, Vec2::new(15.0, 15.0));
+    assert_eq!(pasted_mult.position, Vec2::new(5.0, -5.0));
+
+    // the internal binding follows the new ids, external ones fall back to const values
+    let internal = pasted_mult.inputs[0].binding.as_output_binding().unwrap();
+    assert_eq!(internal.output_node_id, pasted_sum.id());
+    assert!(pasted_mult.inputs[1].binding.as_output_binding().is_none());
+    assert!(pasted_sum.inputs.iter().all(|input| input.binding == Binding::Const));
+
+    // pasted nodes go after the existing ones
+    let order: Vec<NodeId> = other.nodes_in_order().iter().map(|node| node.id()).collect();
+    assert_eq!(order[graph.nodes().len()..], pasted_ids[..]);
+
+    assert!(graph.copy_selection(&[NodeId::unique()]).is_err());
+
+    // a fragment that doesn't validate leaves nodes and subgraphs as they were
+    let mut invalid = Graph::from_yaml_with_options(&fragment, &LoadOptions { validate: false })?;
+    invalid.nodes_mut()[0].instance_of = Some(SubGraphId::unique());
+    let before = other.clone();
+    assert!(other.paste(&invalid.to_yaml()?, Vec2::ZERO).is_err());
+    assert!(other.structurally_eq(&before));
+
+    Ok(())
+}
+
+#[test]
+fn paste_dangling_binding_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let sum = graph.node_by_name("sum").unwrap();
+    let mult = graph.node_by_name("mult").unwrap();
+    let fragment = graph.copy_selection(&[sum.id(), mult.id()])?;
+
+    // a hand edited blob binding to a node that isn't part of it
+    let mut dangling = Graph::from_yaml_with_options(&fragment, &LoadOptions { validate: false })?;
+    dangling.node_by_name_mut("mult").unwrap()
+        .inputs[0].binding = Binding::from_output_binding(NodeId::unique(), 0);
+
+    let mut other = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let pasted_ids = other.paste(&dangling.to_yaml()?, Vec2::ZERO)?;
+    other.validate()?;
+
+    let pasted_mult = pasted_ids.iter()
+        .map(|&id| other.node_by_id(id).unwrap())
+        .find(|node| node.name == "mult")
+        .unwrap();
+    assert!(pasted_mult.inputs[0].binding.as_output_binding().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn minimal_subgraph_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let names = |graph: &Graph| {
+        let mut names: Vec<String> = graph.nodes().iter().map(|node| node.name.clone()).collect();
+        names.sort();
+        names
+    };
+
+    let print = graph.node_by_name("print").unwrap().id();
+    let subgraph = graph.minimal_subgraph_for(print)?;
+    assert_eq!(names(&subgraph), names(&graph));
+    assert!(subgraph.structurally_eq(&graph));
+
+    let sum = graph.node_by_name("sum").unwrap().id();
+    let subgraph = graph.minimal_subgraph_for(sum)?;
+    assert_eq!(names(&subgraph), ["sum", "val1", "val2"]);
+    subgraph.validate()?;
+
+    // the failing node becomes the only output, ids and bindings are kept
+    assert!(subgraph.node_by_id(sum).unwrap().is_output);
+    assert_eq!(subgraph.nodes().iter().filter(|node| node.is_output).count(), 1);
+    let sum_node = subgraph.node_by_id(sum).unwrap();
+    assert!(sum_node.inputs.iter().all(|input| input.binding.is_output_binding()));
+
+    let runtime_graph = Preprocess::default().run(&subgraph, &mut RuntimeGraph::default())?;
+    assert_eq!(runtime_graph.nodes.len(), 3);
+    assert!(runtime_graph.nodes.iter().all(|r_node| r_node.should_execute));
+
+    Ok(())
+}
+
+#[test]
+fn structural_hash_is_stable() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    // persisted in snapshots and runtime state, must not change between builds
+    let expected = 2709146775720397976;
+    assert_eq!(graph.structural_hash(), expected);
+
+    graph.node_by_name_mut("sum").unwrap().name = "total".to_string();
+    assert_eq!(graph.structural_hash(), expected);
+
+    graph.node_by_name_mut("total").unwrap().execution_priority = 1;
+    assert_ne!(graph.structural_hash(), expected);
+
+    Ok(())
+}
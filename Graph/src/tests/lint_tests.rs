@@ -0,0 +1,64 @@
+use crate::data::DataType;
+use crate::graph::*;
+use crate::lint::{LintKind, LintSeverity};
+use crate::subgraph::SubGraph;
+
+#[test]
+fn lint_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    assert!(graph.lint().is_empty());
+
+    // not connected to print, its output is unused
+    let mut orphan = Node::new();
+    orphan.name = "orphan".to_string();
+    orphan.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        data_type_override: None,
+    });
+    let orphan_id = orphan.id();
+    graph.add_node(orphan);
+
+    // an output node sharing the name of another node, with no bound inputs
+    let mut optional = Node::new();
+    optional.name = "sum".to_string();
+    optional.is_output = true;
+    optional.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Int,
+        is_required: false,
+        binding: Binding::None,
+        const_value: None,
+    });
+    let optional_id = optional.id();
+    graph.add_node(optional);
+
+    let mut unused = SubGraph::new();
+    unused.name = "unused".to_string();
+    graph.add_subgraph(&unused);
+
+    graph.validate()?;
+
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    let lints = graph.lint();
+    let mut summary = lints
+        .iter()
+        .map(|lint| (lint.kind, lint.node_ids.clone()))
+        .collect::<Vec<(LintKind, Vec<NodeId>)>>();
+    summary.sort_by_key(|(kind, _)| *kind as u32);
+
+    assert_eq!(summary, vec![
+        (LintKind::UnreachableNode, vec![orphan_id]),
+        (LintKind::DanglingOutput, vec![orphan_id]),
+        (LintKind::DuplicateName, vec![sum_id, optional_id]),
+        (LintKind::UnusedSubGraph, vec![]),
+        (LintKind::UnboundOptionalInputs, vec![optional_id]),
+    ]);
+
+    let unused_lint = lints.iter().find(|lint| lint.kind == LintKind::UnusedSubGraph).unwrap();
+    assert_eq!(unused_lint.subgraph_id, Some(unused.id()));
+    assert_eq!(unused_lint.severity, LintSeverity::Warning);
+    assert!(unused_lint.message.contains("unused"));
+
+    Ok(())
+}
@@ -1,11 +1,17 @@
 use std::str::FromStr;
+use std::sync::Arc;
 
 use mlua::{Function, Lua, Value, Variadic};
 
-use crate::compute::ArgSet;
+use crate::compute::{ArgSet, Compute};
+use crate::data;
 use crate::functions::{FunctionId, Functions};
+use crate::graph::{Binding, Graph, Node};
 use crate::invoke::Invoker;
 use crate::lua_invoker::LuaInvoker;
+use crate::runner;
+use crate::session::Session;
+use crate::thread_safe_invoker::ThreadSafeInvoker;
 use crate::runtime_graph::InvokeContext;
 
 #[test]
@@ -111,3 +117,409 @@ fn load_functions_from_lua_file() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn lua_function_description() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.load_file("../test_resources/test_lua.lua")?;
+
+    let mult_id = FunctionId::from_str("432b9bf1-f478-476c-a9c9-9a6e190124fc")?;
+    let funcs = invoker.get_all_functions();
+    let mult_func = funcs.iter().find(|func| func.id() == mult_id).unwrap();
+    assert_eq!(mult_func.description.as_deref(), Some("Multiplies two integers"));
+
+    let sum_func = funcs.iter().find(|func| func.name == "sum").unwrap();
+    assert!(sum_func.description.is_none());
+
+    let functions = Functions::new(&funcs);
+    let functions: Functions = serde_yaml::from_str(&functions.to_yaml()?)?;
+    let mult_func = functions.function_by_id(mult_id).unwrap();
+    assert_eq!(mult_func.description.as_deref(), Some("Multiplies two integers"));
+
+    let graph = invoker.map_graph()?;
+    let mult_node = graph.node_by_name("mult").unwrap();
+    assert_eq!(mult_node.description.as_deref(), Some("Multiplies two integers"));
+
+    Ok(())
+}
+
+#[test]
+fn thread_safe_lua_invoker() -> anyhow::Result<()> {
+    let invoker = ThreadSafeInvoker::new(|| {
+        let mut invoker = LuaInvoker::default();
+        invoker.load_file("../test_resources/test_lua.lua")?;
+        Ok(Box::new(invoker))
+    })?;
+    assert_eq!(invoker.all_functions().len(), 5);
+
+    let mult_id = FunctionId::from_str("432b9bf1-f478-476c-a9c9-9a6e190124fc")?;
+
+    std::thread::scope(|scope| {
+        let handles = (0..4i64)
+            .map(|index| {
+                let invoker = &invoker;
+                scope.spawn(move || -> anyhow::Result<i64> {
+                    let inputs: ArgSet = ArgSet::from_vec(vec![Some(index), Some(10)]);
+                    let mut outputs: ArgSet = ArgSet::from_vec(vec![Some(0)]);
+                    let mut ctx = InvokeContext::default();
+
+                    invoker.invoke(mult_id, &mut ctx, inputs.as_slice(), outputs.as_mut_slice())?;
+
+                    Ok(outputs[0].as_ref().unwrap().as_int())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(index, handle)| -> anyhow::Result<()> {
+                let result = handle.join().unwrap()?;
+                assert_eq!(result, index as i64 * 10);
+                Ok(())
+            })
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn thread_safe_lua_compute() -> anyhow::Result<()> {
+    let invoker = Arc::new(ThreadSafeInvoker::new(|| {
+        let mut invoker = LuaInvoker::default();
+        invoker.load_file("../test_resources/test_lua.lua")?;
+        Ok(Box::new(invoker))
+    })?);
+    let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+
+    // every thread runs the whole graph with its own Compute over the shared invoker
+    std::thread::scope(|scope| {
+        let handles = (0..4)
+            .map(|_| {
+                let invoker = Arc::clone(&invoker);
+                let graph = graph.clone();
+                scope.spawn(move || -> anyhow::Result<Vec<(String, data::Value)>> {
+                    let compute = Compute::from(invoker);
+                    Session::new(graph).tick(&compute, &[])
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| -> anyhow::Result<()> {
+                // (val1 + val2) * val2 = (4 + 9) * 9
+                assert_eq!(handle.join().unwrap()?, vec![("print".to_string(), data::Value::from(117))]);
+                Ok(())
+            })
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn run_graph_with_lua() -> anyhow::Result<()> {
+    let outputs = runner::run("../test_resources/test_graph.yml", "../test_resources/test_lua.lua")?;
+
+    // (val1 + val2) * val2 = (4 + 9) * 9
+    assert_eq!(outputs, vec![("print".to_string(), data::Value::from(117))]);
+
+    assert!(runner::run("../test_resources/missing.yml", "../test_resources/test_lua.lua").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn lua_output_line_limit() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.set_max_output_lines(3);
+    invoker.load(r#"
+        for i = 1, 10 do
+            print(i)
+        end
+        functions = {}
+        "#)?;
+
+    assert_eq!(invoker.get_output(), "8\n9\n10");
+    assert_eq!(invoker.get_output(), "");
+
+    Ok(())
+}
+
+#[test]
+fn lua_array_values() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.load(r#"
+        function scale(values, factor)
+            local result = {}
+            for i, value in ipairs(values) do
+                result[i] = value * factor
+            end
+            return result
+        end
+        functions = {
+            {
+                id = "5f2b1e4c-3a47-4d8e-9b61-0c7d2e8f4a13",
+                name = "scale",
+                inputs = { { "values", "array<float>" }, { "factor", "float" } },
+                outputs = { { "result", "array<float>" } },
+            },
+        }
+        "#)?;
+
+    let inputs: ArgSet = ArgSet::from_vec(vec![
+        Some(data::Value::from(vec![1.0, 2.0, 3.0])),
+        Some(data::Value::from(2.0)),
+    ]);
+    let mut outputs: ArgSet = ArgSet::from_vec(vec![None::<data::Value>]);
+    invoker.invoke(
+        FunctionId::from_str("5f2b1e4c-3a47-4d8e-9b61-0c7d2e8f4a13")?,
+        &mut InvokeContext::default(),
+        inputs.as_slice(),
+        outputs.as_mut_slice(),
+    )?;
+    assert_eq!(outputs[0], Some(data::Value::from(vec![2.0, 4.0, 6.0])));
+
+    Ok(())
+}
+
+#[test]
+fn lua_unsupported_table_values() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.load(r#"
+        function mixed()
+            return { 1.0, "two" }
+        end
+        function with_function()
+            return { 1.0, print }
+        end
+        functions = {
+            {
+                id = "8c1d4f2a-6b3e-4a9d-b7e5-2f0a9c3d1e64",
+                name = "mixed",
+                inputs = {},
+                outputs = { { "result", "array<float>" } },
+            },
+            {
+                id = "3e7a9b1c-5d2f-4c8e-a6b4-9d0e1f2a3b75",
+                name = "with_function",
+                inputs = {},
+                outputs = { { "result", "array<float>" } },
+            },
+        }
+        "#)?;
+
+    // both fail the invoke instead of panicking
+    for id in ["8c1d4f2a-6b3e-4a9d-b7e5-2f0a9c3d1e64", "3e7a9b1c-5d2f-4c8e-a6b4-9d0e1f2a3b75"] {
+        let mut outputs: ArgSet = ArgSet::from_vec(vec![None::<data::Value>]);
+        let result = invoker.invoke(
+            FunctionId::from_str(id)?,
+            &mut InvokeContext::default(),
+            &[],
+            outputs.as_mut_slice(),
+        );
+        assert!(result.is_err());
+        assert_eq!(outputs[0], None);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn lua_optional_input() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.load(r#"
+        function value()
+            return 2
+        end
+        function scale(value, factor)
+            return value * (factor or 1)
+        end
+        function show(value)
+            print(value)
+        end
+        functions = {
+            {
+                id = "0c1f8a52-7d3e-4b9a-a6c4-2e5f9d8b7a61",
+                name = "value",
+                inputs = { },
+                outputs = { { "result", "int" } },
+            },
+            {
+                id = "9e4b2c7d-1a5f-4e8b-b3d6-7f0a2c9e5b14",
+                name = "scale",
+                inputs = { { "value", "int" }, { "factor", "int", optional = true } },
+                outputs = { { "result", "int" } },
+            },
+            {
+                id = "6a3d9f1e-8c2b-4d7a-9e5f-1b4c7a2d8e36",
+                name = "show",
+                inputs = { { "value", "int" } },
+                outputs = { },
+            },
+        }
+        function graph()
+            local a = value()
+            local b = scale(a, a)
+            show(b)
+        end
+        "#)?;
+
+    let scale = invoker.describe_function("scale").unwrap();
+    assert!(scale.inputs[0].is_required);
+    assert!(!scale.inputs[1].is_required);
+
+    let node = Node::from_function(scale);
+    assert!(node.inputs[0].is_required);
+    assert!(!node.inputs[1].is_required);
+
+    let graph = invoker.map_graph()?;
+    let node = graph.node_by_name("scale").unwrap();
+    assert!(node.inputs[0].is_required);
+    assert!(!node.inputs[1].is_required);
+    assert!(graph.node_by_name("show").unwrap().inputs[0].is_required);
+
+    // the unbound factor reaches scale as nil
+    let value = Node::from_function(invoker.describe_function("value").unwrap());
+    let mut scale = Node::from_function(invoker.describe_function("scale").unwrap());
+    scale.inputs[0].binding = Binding::from_output_binding(value.id(), 0);
+    assert!(scale.inputs[1].binding == Binding::None);
+    let mut show = Node::from_function(invoker.describe_function("show").unwrap());
+    show.inputs[0].binding = Binding::from_output_binding(scale.id(), 0);
+    show.is_output = true;
+    let mut graph = Graph::default();
+    graph.add_node(value);
+    graph.add_node(scale);
+    graph.add_node(show);
+    graph.validate()?;
+
+    let compute = Compute::from(invoker);
+    let outputs = Session::new(graph).tick(&compute, &[])?;
+    assert_eq!(outputs, vec![("show".to_string(), data::Value::from(2))]);
+
+    Ok(())
+}
+
+#[test]
+fn lua_map_graph_errors() -> anyhow::Result<()> {
+    let script = r#"
+        function value()
+            return 1
+        end
+        functions = {
+            {
+                id = "8be72298-dece-4a7a-b5a5-9ac8c1aa7d02",
+                name = "value",
+                inputs = { },
+                outputs = { { "result", "int" } },
+            },
+        }
+        "#;
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load(script)?;
+    let error = invoker.map_graph().err().unwrap().to_string();
+    assert!(error.contains("no graph function"), "{}", error);
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load(&format!(r#"
+        {}
+        function graph()
+            local a = value()
+            undefined_func(a)
+        end
+        "#, script))?;
+    let error = invoker.map_graph().err().unwrap().to_string();
+    assert!(error.contains("undefined_func"), "{}", error);
+
+    // original functions are restored after the failure
+    let mut outputs: ArgSet = ArgSet::from_vec(vec![Some(0)]);
+    invoker.invoke(
+        FunctionId::from_str("8be72298-dece-4a7a-b5a5-9ac8c1aa7d02")?,
+        &mut InvokeContext::default(),
+        &[],
+        outputs.as_mut_slice(),
+    )?;
+    assert_eq!(outputs[0].as_ref().unwrap().as_int(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn lua_map_graph_type_mismatch() -> anyhow::Result<()> {
+    let script = |graph: &str| format!(r#"
+        function label()
+            return "a"
+        end
+        function count()
+            return 1
+        end
+        function show(value)
+            print(value)
+        end
+        functions = {{
+            {{
+                id = "3f7a1c2e-9b4d-4e6a-8c1f-5d2b7e9a4c03",
+                name = "label",
+                inputs = {{ }},
+                outputs = {{ {{ "text", "string" }} }},
+            }},
+            {{
+                id = "b52e8d4a-6c1f-4a3b-9e7d-0f4c2a8b6d15",
+                name = "count",
+                inputs = {{ }},
+                outputs = {{ {{ "value", "int" }} }},
+            }},
+            {{
+                id = "e8c4a2f6-3d5b-4f1e-a7c9-2b6d0e4f8a27",
+                name = "show",
+                inputs = {{ {{ "value", "int" }} }},
+                outputs = {{ }},
+            }},
+        }}
+        function graph()
+            {}
+        end
+        "#, graph);
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load(&script("show(count())"))?;
+    invoker.map_graph()?;
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load(&script("show(label())"))?;
+    let error = invoker.map_graph().err().unwrap().to_string();
+    assert!(error.contains("function label is string"), "{}", error);
+    assert!(error.contains("input value of function show expects int"), "{}", error);
+
+    Ok(())
+}
+
+#[test]
+fn lua_map_graph_is_stable() -> anyhow::Result<()> {
+    let map_graph = || -> anyhow::Result<String> {
+        let mut invoker = LuaInvoker::default();
+        invoker.load_file("../test_resources/test_lua.lua")?;
+        invoker.map_graph()?.to_yaml()
+    };
+
+    let yaml = map_graph()?;
+    assert_eq!(yaml, map_graph()?);
+
+    let mut invoker = LuaInvoker::default();
+    invoker.load_file("../test_resources/test_lua.lua")?;
+    let graph = invoker.map_graph()?;
+    assert_eq!(graph.to_yaml()?, invoker.map_graph()?.to_yaml()?);
+
+    // the same functions called by another script are different nodes
+    let script = std::fs::read_to_string("../test_resources/test_lua.lua")?;
+    let mut other_invoker = LuaInvoker::default();
+    other_invoker.load(&format!("{}\n-- another script", script))?;
+    let other_graph = other_invoker.map_graph()?;
+    assert_eq!(other_graph.nodes().len(), graph.nodes().len());
+    assert!(other_graph.nodes()
+        .iter()
+        .all(|node| graph.node_by_id(node.id()).is_none()));
+
+    Ok(())
+}
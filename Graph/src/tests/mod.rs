@@ -6,3 +6,17 @@ mod graph_tests;
 mod lua_compute_tests;
 #[cfg(test)]
 mod compute_tests;
+#[cfg(test)]
+mod subgraph_tests;
+#[cfg(test)]
+mod graph_format_tests;
+#[cfg(test)]
+mod data_tests;
+#[cfg(test)]
+mod lint_tests;
+#[cfg(test)]
+mod session_tests;
+#[cfg(test)]
+mod type_inference_tests;
+#[cfg(test)]
+mod backend_tests;
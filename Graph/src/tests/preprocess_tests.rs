@@ -2,7 +2,7 @@ use crate::functions::FunctionId;
 use crate::graph::*;
 use crate::invoke::{InvokeArgs, Invoker};
 use crate::preprocess::Preprocess;
-use crate::runtime_graph::{InvokeContext, RuntimeGraph};
+use crate::runtime_graph::{InvokeContext, MissingInput, MissingInputReason, RuntimeGraph};
 
 struct EmptyInvoker {}
 
@@ -27,7 +27,7 @@ fn simple_run() -> anyhow::Result<()> {
     let graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
     let runtime = Preprocess::default();
 
-    let runtime_graph = runtime.run(&graph, &mut RuntimeGraph::default());
+    let runtime_graph = runtime.run(&graph, &mut RuntimeGraph::default())?;
     assert_eq!(runtime_graph.nodes.len(), 5);
     assert_eq!(runtime_graph.node_by_name("val2").unwrap().total_binding_count, 2);
     assert!(runtime_graph.nodes.iter().all(|r_node| r_node.should_execute));
@@ -45,7 +45,7 @@ fn missing_input() -> anyhow::Result<()> {
         .inputs[0].binding = Binding::None;
 
     let runtime = Preprocess::default();
-    let runtime_graph = runtime.run(&graph, &mut RuntimeGraph::default());
+    let runtime_graph = runtime.run(&graph, &mut RuntimeGraph::default())?;
     assert_eq!(runtime_graph.nodes.len(), 4);
     assert_eq!(runtime_graph.node_by_name("val2").unwrap().total_binding_count, 2);
     assert!(runtime_graph.nodes.iter().all(|r_node| r_node.should_execute));
@@ -54,7 +54,177 @@ fn missing_input() -> anyhow::Result<()> {
     assert!(runtime_graph.node_by_name("mult").unwrap().has_missing_inputs);
     assert!(runtime_graph.node_by_name("print").unwrap().has_missing_inputs);
 
+    assert!(runtime_graph.node_by_name("val2").unwrap().missing_inputs().is_empty());
+    assert_eq!(
+        runtime_graph.node_by_name("sum").unwrap().missing_inputs(),
+        &[MissingInput {
+            input_index: 0,
+            reason: MissingInputReason::Unbound,
+        }]
+    );
+    let sum_id = graph.node_by_name("sum").unwrap().id();
+    assert_eq!(
+        runtime_graph.node_by_name("mult").unwrap().missing_inputs(),
+        &[MissingInput {
+            input_index: 0,
+            reason: MissingInputReason::UpstreamIncomplete(sum_id),
+        }]
+    );
+
     let _yaml = serde_yaml::to_string(&runtime_graph)?;
 
     Ok(())
 }
+
+// a -> b -> c -> d, every node has one input and one output, d is the output node
+fn chain_graph(middle_edge: BindingBehavior, c_behavior: FunctionBehavior) -> Graph {
+    let mut graph = Graph::default();
+    let mut node_ids: Vec<NodeId> = Vec::new();
+    for name in ["a", "b", "c", "d"] {
+        let mut node = Node::new();
+        node.name = name.to_string();
+        node.behavior = FunctionBehavior::Passive;
+        node.outputs.push(Output {
+            name: "value".to_string(),
+            data_type: crate::data::DataType::Int,
+            data_type_override: None,
+        });
+        if !node_ids.is_empty() {
+            node.inputs.push(Input {
+                name: "value".to_string(),
+                data_type: crate::data::DataType::Int,
+                is_required: true,
+                binding: Binding::None,
+                const_value: None,
+            });
+        }
+        node_ids.push(node.id());
+        graph.add_node(node);
+    }
+
+    graph.connect(node_ids[1], 0, node_ids[0], 0).unwrap();
+    graph.connect_with_behavior(node_ids[2], 0, node_ids[1], 0, middle_edge).unwrap();
+    graph.connect(node_ids[3], 0, node_ids[2], 0).unwrap();
+
+    graph.node_by_name_mut("a").unwrap().behavior = FunctionBehavior::Active;
+    graph.node_by_name_mut("c").unwrap().behavior = c_behavior;
+    graph.node_by_name_mut("d").unwrap().is_output = true;
+
+    graph
+}
+
+// second preprocess run, as if every node had already produced outputs
+fn rerun_with_cached_outputs(graph: &Graph) -> anyhow::Result<RuntimeGraph> {
+    let runtime = Preprocess::default();
+    let mut runtime_graph = runtime.run(graph, &mut RuntimeGraph::default())?;
+    for r_node in runtime_graph.nodes.iter_mut() {
+        r_node.output_values = Some(vec![None]);
+        r_node.output_binding_count.fill(0);
+        r_node.total_binding_count = 0;
+    }
+
+    runtime.run(graph, &mut runtime_graph)
+}
+
+#[test]
+fn once_edge_severs_propagation() -> anyhow::Result<()> {
+    let should_execute = |runtime_graph: &RuntimeGraph, name: &str|
+        runtime_graph.node_by_name(name).unwrap().should_execute;
+
+    // activity of a reaches d through Always edges only
+    let graph = chain_graph(BindingBehavior::Always, FunctionBehavior::Passive);
+    let runtime_graph = rerun_with_cached_outputs(&graph)?;
+    assert_eq!(runtime_graph.node_by_name("c").unwrap().behavior, FunctionBehavior::Active);
+    assert!(["a", "b", "c", "d"].iter().all(|name| should_execute(&runtime_graph, name)));
+
+    // Once edge b -> c stops activity from propagating forward
+    let graph = chain_graph(BindingBehavior::Once, FunctionBehavior::Passive);
+    let runtime_graph = rerun_with_cached_outputs(&graph)?;
+    assert_eq!(runtime_graph.node_by_name("b").unwrap().behavior, FunctionBehavior::Active);
+    assert_eq!(runtime_graph.node_by_name("c").unwrap().behavior, FunctionBehavior::Passive);
+    assert!(should_execute(&runtime_graph, "d"));
+    assert!(!should_execute(&runtime_graph, "c"));
+    assert!(!should_execute(&runtime_graph, "b"));
+    assert!(!should_execute(&runtime_graph, "a"));
+
+    // c executes on its own, but pulls b and a only through the Once edge,
+    // so their cached outputs are reused
+    let graph = chain_graph(BindingBehavior::Once, FunctionBehavior::Active);
+    let runtime_graph = rerun_with_cached_outputs(&graph)?;
+    assert!(should_execute(&runtime_graph, "d"));
+    assert!(should_execute(&runtime_graph, "c"));
+    assert!(!should_execute(&runtime_graph, "b"));
+    assert!(!should_execute(&runtime_graph, "a"));
+    assert_eq!(runtime_graph.node_by_name("b").unwrap().total_binding_count, 1);
+
+    // without cached outputs a Once edge still pulls the producer
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    assert!(runtime_graph.nodes.iter().all(|r_node| r_node.should_execute));
+
+    Ok(())
+}
+
+#[test]
+fn source_output_node() -> anyhow::Result<()> {
+    let mut node = Node::new();
+    node.name = "noise".to_string();
+    node.behavior = FunctionBehavior::Passive;
+    node.is_output = true;
+    node.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: crate::data::DataType::Int,
+        data_type_override: None,
+    });
+    let mut graph = Graph::default();
+    graph.add_node(node);
+
+    let runtime = Preprocess::default();
+    let runtime_graph = runtime.run(&graph, &mut RuntimeGraph::default())?;
+    let r_node = runtime_graph.node_by_name("noise").unwrap();
+    assert!(r_node.should_execute);
+    assert!(!r_node.has_missing_inputs);
+
+    // passive, outputs are reused on the next run
+    let runtime_graph = rerun_with_cached_outputs(&graph)?;
+    assert!(!runtime_graph.node_by_name("noise").unwrap().should_execute);
+
+    graph.node_by_name_mut("noise").unwrap().behavior = FunctionBehavior::Active;
+    let runtime_graph = rerun_with_cached_outputs(&graph)?;
+    assert!(runtime_graph.node_by_name("noise").unwrap().should_execute);
+
+    Ok(())
+}
+
+#[test]
+fn affected_by_input_change() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_graph.yml")?;
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+
+    let node_id = |graph: &Graph, name: &str| graph.node_by_name(name).unwrap().id();
+    let val1_id = node_id(&graph, "val1");
+    let sum_id = node_id(&graph, "sum");
+    let mult_id = node_id(&graph, "mult");
+    let print_id = node_id(&graph, "print");
+
+    // sum keeps its cached outputs
+    assert_eq!(runtime_graph.affected_by(&graph, val1_id), vec![val1_id]);
+    assert_eq!(runtime_graph.affected_by(&graph, mult_id), vec![mult_id, print_id]);
+
+    graph.node_by_id_mut(sum_id).unwrap().should_cache_outputs = false;
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    assert_eq!(runtime_graph.affected_by(&graph, val1_id), vec![val1_id, sum_id, mult_id, print_id]);
+
+    // an OnInputChange node only reacts to its watched input, mult watches val2
+    graph.node_by_id_mut(mult_id).unwrap().behavior = FunctionBehavior::OnInputChange(1);
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    assert_eq!(runtime_graph.affected_by(&graph, val1_id), vec![val1_id, sum_id]);
+
+    // a Once binding keeps the consumer on its last value
+    let mult = graph.node_by_id_mut(mult_id).unwrap();
+    mult.behavior = FunctionBehavior::Passive;
+    mult.inputs[0].binding.as_output_binding_mut().unwrap().behavior = BindingBehavior::Once;
+    let runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default())?;
+    assert_eq!(runtime_graph.affected_by(&graph, val1_id), vec![val1_id, sum_id]);
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::compute::Compute;
+use crate::data::{DataType, Value};
+use crate::functions::FunctionId;
+use crate::graph::{Binding, FunctionBehavior, Graph, Input, Node, Output};
+use crate::invoke::{InvokeArgs, Invoker, LambdaInvoker};
+use crate::runtime_graph::InvokeContext;
+use crate::session::{FrameInput, Session};
+
+// fails the first call of one function, delegates everything else
+struct FailOnceInvoker {
+    invoker: LambdaInvoker,
+    failing_function_id: FunctionId,
+    has_failed: Cell<bool>,
+}
+
+impl Invoker for FailOnceInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        self.invoker.all_functions()
+    }
+
+    fn invoke(&self,
+              function_id: FunctionId,
+              ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        if function_id == self.failing_function_id && !self.has_failed.replace(true) {
+            return Err(anyhow::anyhow!("Invoke failed"));
+        }
+
+        self.invoker.invoke(function_id, ctx, inputs, outputs)
+    }
+}
+
+#[test]
+fn temporal_average() -> anyhow::Result<()> {
+    let average_function_id = FunctionId::unique();
+    let show_function_id = FunctionId::unique();
+
+    let mut average = Node::new();
+    average.name = "average".to_string();
+    average.function_id = average_function_id;
+    average.behavior = FunctionBehavior::Passive;
+    average.inputs.push(Input {
+        name: "frame".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::Const,
+        const_value: Some(Value::from(0.0)),
+    });
+    average.outputs.push(Output {
+        name: "average".to_string(),
+        data_type: DataType::Float,
+        data_type_override: None,
+    });
+    let average_id = average.id();
+
+    let mut show = Node::new();
+    show.name = "show".to_string();
+    show.function_id = show_function_id;
+    show.behavior = FunctionBehavior::Passive;
+    show.is_output = true;
+    show.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::from_output_binding(average_id, 0),
+        const_value: None,
+    });
+
+    let mut graph = Graph::default();
+    graph.add_node(average);
+    graph.add_node(show);
+    graph.validate()?;
+
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(average_function_id, |ctx, inputs, outputs| {
+        // sum and count of the frames seen so far
+        let (sum, count) = ctx.get_or_default::<(f64, u32)>();
+        *sum += inputs[0].as_ref().unwrap().as_float();
+        *count += 1;
+        outputs[0] = Value::from(*sum / *count as f64).into();
+    });
+    invoker.add_lambda(show_function_id, |_, _, _| {});
+    let compute = Compute::from(invoker);
+
+    let mut session = Session::new(graph);
+    let frame = |value: f64| [FrameInput {
+        node_id: average_id,
+        input_index: 0,
+        value: Value::from(value),
+    }];
+
+    assert_eq!(session.tick(&compute, &frame(3.0))?, vec![("show".to_string(), Value::from(3.0))]);
+    assert_eq!(session.tick(&compute, &frame(6.0))?, vec![("show".to_string(), Value::from(4.5))]);
+    assert_eq!(session.tick(&compute, &frame(9.0))?, vec![("show".to_string(), Value::from(6.0))]);
+    assert_eq!(session.frame_count(), 3);
+
+    // the average starts over without the previous frames
+    session.reset();
+    assert_eq!(session.tick(&compute, &frame(1.0))?, vec![("show".to_string(), Value::from(1.0))]);
+
+    // the valid frame input is not applied when a later one is rejected
+    assert!(session.tick(&compute, &[
+        FrameInput {
+            node_id: average_id,
+            input_index: 0,
+            value: Value::from(2.0),
+        },
+        FrameInput {
+            node_id: average_id,
+            input_index: 0,
+            value: Value::from(1),
+        },
+    ]).is_err());
+    assert_eq!(session.frame_count(), 1);
+    let frame_input = &session.graph().node_by_id(average_id).unwrap().inputs[0];
+    assert!(frame_input.binding == Binding::Const);
+    assert_eq!(frame_input.const_value, Some(Value::from(0.0)));
+
+    // without a frame input the node reads its own const again
+    assert_eq!(session.tick(&compute, &[])?, vec![("show".to_string(), Value::from(0.5))]);
+
+    Ok(())
+}
+
+#[test]
+fn debounced_run() -> anyhow::Result<()> {
+    let function_id = FunctionId::unique();
+
+    let mut show = Node::new();
+    show.name = "show".to_string();
+    show.function_id = function_id;
+    show.is_output = true;
+    show.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::Const,
+        const_value: Some(Value::from(0.0)),
+    });
+    let show_id = show.id();
+
+    let mut graph = Graph::default();
+    graph.add_node(show);
+
+    let run_count = Rc::new(Cell::new(0));
+    let mut invoker = LambdaInvoker::default();
+    {
+        let run_count = Rc::clone(&run_count);
+        invoker.add_lambda(function_id, move |_, _, _| run_count.set(run_count.get() + 1));
+    }
+    let compute = Compute::from(invoker);
+
+    let mut session = Session::with_quiet_period(graph, Duration::from_millis(100));
+    let frame = |value: f64| vec![FrameInput {
+        node_id: show_id,
+        input_index: 0,
+        value: Value::from(value),
+    }];
+
+    // ten slider moves 5ms apart, polled every frame
+    let start = Instant::now();
+    for index in 0..10 {
+        let now = start + Duration::from_millis(index * 5);
+        session.request_run_at(now, frame(index as f64));
+        assert!(session.poll_run_at(&compute, now)?.is_none());
+    }
+    assert!(session.is_run_pending());
+    assert!(session.poll_run_at(&compute, start + Duration::from_millis(120))?.is_none());
+    assert_eq!(run_count.get(), 0);
+
+    // one run with the latest value
+    let outputs = session.poll_run_at(&compute, start + Duration::from_millis(145))?;
+    assert_eq!(outputs, Some(vec![("show".to_string(), Value::from(9.0))]));
+    assert!(session.poll_run_at(&compute, start + Duration::from_millis(500))?.is_none());
+    assert_eq!(run_count.get(), 1);
+    assert_eq!(session.last_outputs(), Some([("show".to_string(), Value::from(9.0))].as_slice()));
+
+    // flush runs right away, once
+    session.request_run_at(start + Duration::from_millis(510), frame(1.0));
+    assert_eq!(session.flush_run(&compute)?, Some(vec![("show".to_string(), Value::from(1.0))]));
+    assert!(session.flush_run(&compute)?.is_none());
+    assert_eq!(run_count.get(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn tick_after_error() -> anyhow::Result<()> {
+    let value_function_id = FunctionId::unique();
+    let show_function_id = FunctionId::unique();
+
+    let mut value = Node::new();
+    value.name = "value".to_string();
+    value.function_id = value_function_id;
+    value.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        data_type_override: None,
+    });
+    let value_id = value.id();
+
+    let mut show = Node::new();
+    show.name = "show".to_string();
+    show.function_id = show_function_id;
+    show.is_output = true;
+    show.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::from_output_binding(value_id, 0),
+        const_value: None,
+    });
+
+    let mut graph = Graph::default();
+    graph.add_node(value);
+    graph.add_node(show);
+    graph.validate()?;
+
+    let mut invoker = LambdaInvoker::default();
+    invoker.add_lambda(value_function_id, |_, _, outputs| outputs[0] = Value::from(2.0).into());
+    invoker.add_lambda(show_function_id, |_, _, _| {});
+    let compute = Compute::from(FailOnceInvoker {
+        invoker,
+        failing_function_id: show_function_id,
+        has_failed: Cell::new(false),
+    });
+
+    // the consumer fails after the value node ran, leaving its output consumed by nobody
+    let mut session = Session::new(graph);
+    assert!(session.tick(&compute, &[]).is_err());
+    assert_eq!(session.frame_count(), 0);
+
+    assert_eq!(session.tick(&compute, &[])?, vec![("show".to_string(), Value::from(2.0))]);
+    assert_eq!(session.frame_count(), 1);
+
+    Ok(())
+}
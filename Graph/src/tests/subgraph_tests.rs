@@ -0,0 +1,196 @@
+use crate::data::DataType;
+use crate::graph::*;
+use crate::subgraph::{SubGraph, SubGraphId, SubInput, SubInputNodeConnection};
+
+#[test]
+fn subgraph_from_yaml() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_subgraph.yml")?;
+    assert_eq!(graph.subgraphs().len(), 1);
+    assert_eq!(graph.nodes().len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn subgraph_reorder_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_subgraph.yml")?;
+    let subgraph_id = graph.subgraphs()[0].id();
+    let area_node_id = graph.node_by_name("area").unwrap().id();
+
+    graph.subgraph_by_id_mut(subgraph_id).unwrap().inputs.push(SubInput {
+        name: "area radius".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        connections: vec![SubInputNodeConnection {
+            subnode_id: area_node_id,
+            subnode_input_index: 1,
+        }],
+    });
+
+    // instance fed by source on radius, consumer reading the area output
+    let mut source = Node::new();
+    source.outputs.push(Output {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        data_type_override: None,
+    });
+    let source_id = source.id();
+    graph.add_node(source);
+
+    let mut instance = Node::new();
+    instance.name = "instance".to_string();
+    instance.instance_of = Some(subgraph_id);
+    instance.inputs = ["radius", "area radius"]
+        .iter()
+        .map(|name| Input {
+            name: name.to_string(),
+            data_type: DataType::Float,
+            is_required: true,
+            binding: Binding::None,
+            const_value: None,
+        })
+        .collect();
+    instance.inputs[0].binding = Binding::from_output_binding(source_id, 0);
+    instance.outputs = ["circumference", "area"]
+        .iter()
+        .map(|name| Output {
+            name: name.to_string(),
+            data_type: DataType::Float,
+            data_type_override: None,
+        })
+        .collect();
+    let instance_id = instance.id();
+    graph.add_node(instance);
+
+    let mut consumer = Node::new();
+    consumer.name = "consumer".to_string();
+    consumer.inputs.push(Input {
+        name: "value".to_string(),
+        data_type: DataType::Float,
+        is_required: true,
+        binding: Binding::from_output_binding(instance_id, 1),
+        const_value: None,
+    });
+    graph.add_node(consumer);
+
+    graph.reorder_subgraph_inputs(subgraph_id, &[1, 0])?;
+    graph.reorder_subgraph_outputs(subgraph_id, &[1, 0])?;
+    assert!(graph.reorder_subgraph_inputs(subgraph_id, &[0, 0]).is_err());
+    assert!(graph.reorder_subgraph_outputs(subgraph_id, &[0]).is_err());
+
+    let subgraph = graph.subgraph_by_id(subgraph_id).unwrap();
+    assert_eq!(subgraph.inputs[0].name, "area radius");
+    assert_eq!(subgraph.inputs[1].name, "radius");
+    assert_eq!(subgraph.outputs[0].name, "area");
+    assert_eq!(subgraph.outputs[1].name, "circumference");
+
+    let connection = &subgraph.inputs[0].connections[0];
+    let node = graph.node_by_id(connection.subnode_id).unwrap();
+    assert_eq!(node.name, "area");
+    assert_eq!(node.inputs[connection.subnode_input_index as usize].name, "radius");
+
+    let node = graph.node_by_id(subgraph.outputs[0].subnode_id).unwrap();
+    assert_eq!(node.name, "area");
+
+    // the instance ports moved with the subgraph ports, keeping their bindings
+    let instance = graph.node_by_id(instance_id).unwrap();
+    assert_eq!(instance.inputs[0].name, "area radius");
+    assert!(instance.inputs[0].binding == Binding::None);
+    assert_eq!(instance.inputs[1].name, "radius");
+    assert_eq!(instance.inputs[1].binding.as_output_binding().unwrap().output_node_id, source_id);
+    assert_eq!(instance.outputs[0].name, "area");
+    let consumer = graph.node_by_name("consumer").unwrap();
+    assert_eq!(consumer.inputs[0].binding.as_output_binding().unwrap().output_index, 0);
+
+    graph.validate()?;
+
+    Ok(())
+}
+
+#[test]
+fn resolve_subgraph_ports_test() -> anyhow::Result<()> {
+    let graph = Graph::from_yaml_file("../test_resources/test_subgraph.yml")?;
+    let subgraph_id = graph.subgraphs()[0].id();
+    let circumference_id = graph.node_by_name("circumference").unwrap().id();
+    let area_id = graph.node_by_name("area").unwrap().id();
+
+    assert_eq!(graph.resolve_suboutput(subgraph_id, 0), Some((circumference_id, 0)));
+    assert_eq!(graph.resolve_suboutput(subgraph_id, 1), Some((area_id, 0)));
+    assert_eq!(graph.resolve_suboutput(subgraph_id, 2), None);
+
+    assert_eq!(
+        graph.resolve_subinput(subgraph_id, 0),
+        Some(vec![(circumference_id, 0), (area_id, 1)])
+    );
+    assert_eq!(graph.resolve_subinput(subgraph_id, 1), None);
+
+    assert_eq!(graph.resolve_suboutput(SubGraphId::unique(), 0), None);
+
+    Ok(())
+}
+
+#[test]
+fn subgraph_recursion_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let mut first = SubGraph::new();
+    first.name = "first".to_string();
+    let mut second = SubGraph::new();
+    second.name = "second".to_string();
+    graph.add_subgraph(&first);
+    graph.add_subgraph(&second);
+
+    let mut first_instance = Node::new();
+    first_instance.subgraph_id = Some(second.id());
+    first_instance.instance_of = Some(first.id());
+    graph.add_node(first_instance);
+    graph.validate()?;
+
+    let mut second_instance = Node::new();
+    second_instance.subgraph_id = Some(first.id());
+    second_instance.instance_of = Some(second.id());
+    graph.add_node(second_instance);
+
+    let error = graph.validate().unwrap_err().to_string();
+    assert!(error.contains("subgraph recursion detected"));
+    assert!(error.contains("first -> second -> first"));
+
+    Ok(())
+}
+
+#[test]
+fn export_subgraph_test() -> anyhow::Result<()> {
+    let mut graph = Graph::from_yaml_file("../test_resources/test_subgraph.yml")?;
+    let subgraph_id = graph.subgraphs()[0].id();
+    let circumference_id = graph.node_by_name("circumference").unwrap().id();
+    let area_id = graph.node_by_name("area").unwrap().id();
+
+    // area radius is bound externally but not exposed through a subgraph input
+    graph.subgraph_by_id_mut(subgraph_id).unwrap()
+        .inputs[0].connections
+        .retain(|connection| connection.subnode_id != area_id);
+
+    let exported = graph.export_subgraph(subgraph_id)?;
+    let exported = Graph::from_yaml(&exported.to_yaml()?)?;
+
+    assert_eq!(exported.nodes().len(), 2);
+    assert!(exported.node_by_name("value").is_none());
+    assert!(exported.node_by_name("print").is_none());
+
+    let area = exported.node_by_id(area_id).unwrap();
+    let internal_binding = area.inputs[0].binding.as_output_binding().unwrap();
+    assert_eq!(internal_binding.output_node_id, circumference_id);
+    assert_eq!(internal_binding.behavior, BindingBehavior::Once);
+    assert!(area.inputs[1].binding == Binding::None);
+    let circumference = exported.node_by_id(circumference_id).unwrap();
+    assert!(circumference.inputs[0].binding == Binding::None);
+
+    let subgraph = exported.subgraph_by_id(subgraph_id).unwrap();
+    assert_eq!(subgraph.outputs.len(), 2);
+    assert_eq!(subgraph.inputs.len(), 2);
+    assert_eq!(exported.resolve_subinput(subgraph_id, 1), Some(vec![(area_id, 1)]));
+
+    assert!(graph.export_subgraph(SubGraphId::unique()).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::data::DataType;
+use crate::graph::*;
+use crate::type_inference::PortKind;
+
+fn node(name: &str, inputs: Vec<(DataType, Binding)>, outputs: Vec<DataType>) -> Node {
+    let mut node = Node::new();
+    node.name = name.to_string();
+    node.inputs = inputs
+        .into_iter()
+        .map(|(data_type, binding)| Input {
+            name: "value".to_string(),
+            data_type,
+            is_required: true,
+            binding,
+            const_value: None,
+        })
+        .collect();
+    node.outputs = outputs
+        .into_iter()
+        .map(|data_type| Output {
+            name: "value".to_string(),
+            data_type,
+            data_type_override: None,
+        })
+        .collect();
+    node
+}
+
+#[test]
+fn infer_types_test() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let source = node("source", vec![], vec![DataType::Float]);
+    let source_id = source.id();
+    graph.add_node(source);
+
+    // Generic ports take the type of what they are bound to
+    let passthrough = node(
+        "passthrough",
+        vec![(DataType::Generic, Binding::from_output_binding(source_id, 0))],
+        vec![DataType::Generic],
+    );
+    let passthrough_id = passthrough.id();
+    graph.add_node(passthrough);
+
+    let mut sink = node(
+        "sink",
+        vec![(DataType::Float, Binding::from_output_binding(passthrough_id, 0))],
+        vec![],
+    );
+    sink.is_output = true;
+    graph.add_node(sink);
+
+    let types = graph.infer_types()?;
+    assert_eq!(types.len(), 4);
+    assert_eq!(types[&(passthrough_id, PortKind::Input, 0)], DataType::Float);
+    assert_eq!(types[&(passthrough_id, PortKind::Output, 0)], DataType::Float);
+
+    // nothing to resolve the generic output from
+    let unresolved = node("unresolved", vec![], vec![DataType::Generic]);
+    graph.add_node(unresolved);
+
+    let err = graph.infer_types().err().unwrap();
+    assert!(err.to_string().contains("generic output value of node unresolved"));
+
+    Ok(())
+}
+
+#[test]
+fn connect_generic_port() -> anyhow::Result<()> {
+    let mut graph = Graph::default();
+
+    let source = node("source", vec![], vec![DataType::Float]);
+    let source_id = source.id();
+    graph.add_node(source);
+    let passthrough = node("passthrough", vec![(DataType::Generic, Binding::None)], vec![DataType::Generic]);
+    let passthrough_id = passthrough.id();
+    graph.add_node(passthrough);
+    let sink = node("sink", vec![(DataType::Float, Binding::None)], vec![]);
+    let sink_id = sink.id();
+    graph.add_node(sink);
+
+    assert_eq!(graph.connection_check(passthrough_id, 0, source_id, 0), ConnectionStatus::Ok);
+    graph.connect(passthrough_id, 0, source_id, 0)?;
+    graph.connect(sink_id, 0, passthrough_id, 0)?;
+
+    let types = graph.infer_types()?;
+    assert_eq!(types[&(passthrough_id, PortKind::Output, 0)], DataType::Float);
+
+    // a half built input of the default Null type is not generic
+    let untyped = node("untyped", vec![(DataType::Null, Binding::None)], vec![]);
+    let untyped_id = untyped.id();
+    graph.add_node(untyped);
+    assert!(matches!(graph.connection_check(untyped_id, 0, source_id, 0), ConnectionStatus::Incompatible(_)));
+    assert!(graph.connect(untyped_id, 0, source_id, 0).is_err());
+
+    Ok(())
+}
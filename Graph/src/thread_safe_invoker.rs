@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::backend::TransitionKind;
+use crate::data::Value;
+use crate::functions::FunctionId;
+use crate::invoke::{InvokeArgs, Invoker};
+use crate::runtime_graph::InvokeContext;
+
+type InvokerFactory = dyn Fn() -> anyhow::Result<Box<dyn Invoker>> + Send + Sync;
+
+static NEXT_INSTANCE_ID: AtomicUsize = AtomicUsize::new(0);
+// ids of ThreadSafeInvokers not dropped yet
+static LIVE_INSTANCE_IDS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+// bumped on every drop, threads prune their instances of dropped invokers when it changes
+static DROP_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+// per thread invoker instances, keyed by ThreadSafeInvoker instance id;
+// created and dropped on the owning thread, so they don't need to be Send
+#[derive(Default)]
+struct ThreadInvokers {
+    generation: usize,
+    invokers: HashMap<usize, Box<dyn Invoker>>,
+}
+
+thread_local! {
+    static THREAD_INVOKERS: RefCell<ThreadInvokers> = RefCell::new(ThreadInvokers::default());
+}
+
+impl ThreadInvokers {
+    // instances of invokers dropped since the last check, returned so they are dropped
+    // after the thread map is released, they may hold thread safe invokers themselves
+    fn take_dropped(&mut self) -> Vec<Box<dyn Invoker>> {
+        let generation = DROP_GENERATION.load(Ordering::Acquire);
+        if generation == self.generation {
+            return Vec::new();
+        }
+        self.generation = generation;
+
+        let live_instance_ids = LIVE_INSTANCE_IDS.lock().unwrap();
+        let dropped_ids = self.invokers
+            .keys()
+            .copied()
+            .filter(|instance_id| !live_instance_ids.contains(instance_id))
+            .collect::<Vec<usize>>();
+
+        dropped_ids
+            .iter()
+            .filter_map(|instance_id| self.invokers.remove(instance_id))
+            .collect()
+    }
+}
+
+// shares a single threaded invoker (e.g. LuaInvoker) between threads
+// by lazily creating one instance per calling thread
+pub struct ThreadSafeInvoker {
+    instance_id: usize,
+    all_functions: Vec<FunctionId>,
+    factory: Arc<InvokerFactory>,
+}
+
+impl ThreadSafeInvoker {
+    pub fn new<F>(factory: F) -> anyhow::Result<ThreadSafeInvoker>
+    where F: Fn() -> anyhow::Result<Box<dyn Invoker>> + Send + Sync + 'static
+    {
+        let instance_id = NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed);
+
+        let invoker = factory()?;
+        let all_functions = invoker.all_functions();
+        LIVE_INSTANCE_IDS.lock().unwrap().insert(instance_id);
+        THREAD_INVOKERS.with(|invokers| {
+            invokers.borrow_mut().invokers.insert(instance_id, invoker);
+        });
+
+        Ok(ThreadSafeInvoker {
+            instance_id,
+            all_functions,
+            factory: Arc::new(factory),
+        })
+    }
+
+    // the instance is taken out of the map for the duration of the call,
+    // so the invoker can use other thread safe invokers itself
+    fn with_instance<R, F>(&self, f: F) -> anyhow::Result<R>
+    where F: FnOnce(&dyn Invoker) -> anyhow::Result<R>
+    {
+        let (invoker, dropped) = THREAD_INVOKERS.with(|invokers| {
+            let mut invokers = invokers.borrow_mut();
+            (invokers.invokers.remove(&self.instance_id), invokers.take_dropped())
+        });
+        drop(dropped);
+        let invoker = match invoker {
+            Some(invoker) => invoker,
+            None => (self.factory)()?,
+        };
+
+        let result = f(invoker.as_ref());
+
+        THREAD_INVOKERS.with(|invokers| {
+            invokers.borrow_mut().invokers.insert(self.instance_id, invoker);
+        });
+
+        result
+    }
+}
+
+impl Drop for ThreadSafeInvoker {
+    fn drop(&mut self) {
+        LIVE_INSTANCE_IDS.lock().unwrap().remove(&self.instance_id);
+        DROP_GENERATION.fetch_add(1, Ordering::Release);
+
+        // instances on other threads are released on their next call into any thread safe invoker,
+        // or when those threads exit
+        let invoker = THREAD_INVOKERS
+            .try_with(|invokers| invokers.borrow_mut().invokers.remove(&self.instance_id))
+            .ok()
+            .flatten();
+        drop(invoker);
+    }
+}
+
+impl Invoker for ThreadSafeInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        self.all_functions.clone()
+    }
+
+    fn invoke(&self,
+              function_id: FunctionId,
+              ctx: &mut InvokeContext,
+              inputs: &InvokeArgs,
+              outputs: &mut InvokeArgs)
+        -> anyhow::Result<()>
+    {
+        self.with_instance(|invoker| invoker.invoke(function_id, ctx, inputs, outputs))
+    }
+
+    fn transition(&self, function_id: FunctionId, kind: TransitionKind, value: &Value) -> anyhow::Result<Value> {
+        self.with_instance(|invoker| invoker.transition(function_id, kind, value))
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::data::DataType;
+use crate::graph::{Binding, Graph, Node, NodeId};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PortKind {
+    Input,
+    Output,
+}
+
+impl Graph {
+    // resolves the type flowing through every input and output without running the graph;
+    // ports declared as Generic are generic, all generic ports of a node share one type,
+    // taken from the values bound to its generic inputs or, failing that,
+    // from the concrete inputs its generic outputs are bound to
+    pub fn infer_types(&self) -> anyhow::Result<HashMap<(NodeId, PortKind, usize), DataType>> {
+        let mut consumers: HashMap<(NodeId, usize), Vec<&DataType>> = HashMap::new();
+        for node in self.nodes().iter() {
+            for input in node.inputs.iter() {
+                if let Some(output_binding) = input.binding.as_output_binding() {
+                    consumers
+                        .entry((output_binding.output_node_id, output_binding.output_index as usize))
+                        .or_default()
+                        .push(&input.data_type);
+                }
+            }
+        }
+
+        let mut types: HashMap<(NodeId, PortKind, usize), DataType> = HashMap::new();
+        for node_id in self.topological_order()? {
+            let node = self.node_by_id(node_id).unwrap();
+
+            let generic_type = match Self::generic_type_from_inputs(node, &types)? {
+                Some(data_type) => Some(data_type),
+                None => Self::generic_type_from_consumers(node, &consumers)?,
+            };
+
+            for (index, input) in node.inputs.iter().enumerate() {
+                let data_type = if input.data_type != DataType::Generic {
+                    input.data_type.clone()
+                } else {
+                    generic_type.clone().ok_or_else(|| anyhow::anyhow!(
+                        "Can't resolve the type of generic input {} of node {}",
+                        input.name,
+                        node.name
+                    ))?
+                };
+                types.insert((node_id, PortKind::Input, index), data_type);
+            }
+            for (index, output) in node.outputs.iter().enumerate() {
+                let data_type = if *output.effective_data_type() != DataType::Generic {
+                    output.effective_data_type().clone()
+                } else {
+                    generic_type.clone().ok_or_else(|| anyhow::anyhow!(
+                        "Can't resolve the type of generic output {} of node {}",
+                        output.name,
+                        node.name
+                    ))?
+                };
+                types.insert((node_id, PortKind::Output, index), data_type);
+            }
+        }
+
+        Ok(types)
+    }
+
+    fn generic_type_from_inputs(
+        node: &Node,
+        types: &HashMap<(NodeId, PortKind, usize), DataType>,
+    ) -> anyhow::Result<Option<DataType>> {
+        let mut generic_type: Option<DataType> = None;
+        for input in node.inputs.iter().filter(|input| input.data_type == DataType::Generic) {
+            let data_type = match &input.binding {
+                Binding::None => None,
+                Binding::Const => input.const_value
+                    .as_ref()
+                    .map(|value| value.data_type())
+                    .filter(|data_type| *data_type != DataType::Null),
+                // dependencies come first in topological order, so the output is already resolved
+                Binding::Output(output_binding) => types
+                    .get(&(output_binding.output_node_id, PortKind::Output, output_binding.output_index as usize))
+                    .cloned(),
+            };
+
+            match (&generic_type, data_type) {
+                (_, None) => {}
+                (None, Some(data_type)) => generic_type = Some(data_type),
+                (Some(existing), Some(data_type)) if *existing != data_type => {
+                    return Err(anyhow::anyhow!(
+                        "Generic inputs of node {} receive both {} and {}",
+                        node.name,
+                        existing,
+                        data_type
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(generic_type)
+    }
+
+    fn generic_type_from_consumers(
+        node: &Node,
+        consumers: &HashMap<(NodeId, usize), Vec<&DataType>>,
+    ) -> anyhow::Result<Option<DataType>> {
+        let mut generic_type: Option<&DataType> = None;
+        let consumer_types = node.outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, output)| *output.effective_data_type() == DataType::Generic)
+            .filter_map(|(index, _)| consumers.get(&(node.id(), index)))
+            .flatten()
+            .filter(|data_type| ***data_type != DataType::Generic);
+
+        for data_type in consumer_types {
+            match generic_type {
+                None => generic_type = Some(data_type),
+                Some(existing) if existing != *data_type => {
+                    return Err(anyhow::anyhow!(
+                        "Generic outputs of node {} are bound to both {} and {} inputs",
+                        node.name,
+                        existing,
+                        data_type
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(generic_type.cloned())
+    }
+}
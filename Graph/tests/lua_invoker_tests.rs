@@ -0,0 +1,21 @@
+// goes through the crate's public api, as dependent crates do
+use graph::lua_invoker::LuaInvoker;
+
+#[test]
+fn lua_list_globals() -> anyhow::Result<()> {
+    let mut invoker = LuaInvoker::default();
+    invoker.load_file("../test_resources/test_lua.lua")?;
+
+    let globals = invoker.list_globals();
+    for name in ["functions", "graph", "mult", "print_func", "sum", "val1", "val2"] {
+        assert!(globals.iter().any(|global| global == name), "missing global {}", name);
+    }
+    assert!(!globals.iter().any(|global| global == "string"));
+
+    let mult_func = invoker.describe_function("mult").unwrap();
+    assert_eq!(mult_func.inputs.len(), 2);
+    assert_eq!(mult_func.outputs.len(), 1);
+    assert!(invoker.describe_function("graph").is_none());
+
+    Ok(())
+}
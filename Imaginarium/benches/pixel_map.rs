@@ -0,0 +1,22 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use imaginarium::color_format::ColorFormat;
+use imaginarium::image::{Image, ImageDesc};
+
+fn map_pixels_4k(c: &mut Criterion) {
+    let mut image = Image::new_empty(ImageDesc::new(3840, 2160, ColorFormat::RGBA_U8)).unwrap();
+    for (index, byte) in image.bytes.iter_mut().enumerate() {
+        *byte = (index % 251) as u8;
+    }
+
+    c.bench_function("map_pixels invert 4k", |b| {
+        b.iter(|| image.map_pixels(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, a]))
+    });
+    #[cfg(feature = "parallel")]
+    c.bench_function("par_map_pixels invert 4k", |b| {
+        b.iter(|| image.par_map_pixels(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, a]))
+    });
+}
+
+criterion_group!(benches, map_pixels_4k);
+criterion_main!(benches);
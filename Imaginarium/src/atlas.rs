@@ -0,0 +1,85 @@
+use crate::image::{Image, ImageDesc};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl Image {
+    // shelf packer: images sorted by height are placed left to right,
+    // starting a new shelf when the row is full;
+    // rects are returned in the order of the input images
+    pub fn pack_atlas(images: &[&Image], padding: u32) -> anyhow::Result<(Image, Vec<Rect>)> {
+        let first = images.first()
+            .ok_or(anyhow::anyhow!("No images to pack"))?;
+        let color_format = first.desc.color_format();
+        if images.iter().any(|image| image.desc.color_format() != color_format) {
+            return Err(anyhow::anyhow!("All images must have the same color format"));
+        }
+
+        let padded_area: u64 = images
+            .iter()
+            .map(|image| (image.desc.width() + padding) as u64 * (image.desc.height() + padding) as u64)
+            .sum();
+        let widest = images
+            .iter()
+            .map(|image| image.desc.width())
+            .max().unwrap();
+        let atlas_width = ((padded_area as f64).sqrt().ceil() as u32).max(widest + padding) + padding;
+
+        let mut order = (0..images.len()).collect::<Vec<usize>>();
+        order.sort_by_key(|&index| std::cmp::Reverse(images[index].desc.height()));
+
+        let mut rects = vec![Rect::default(); images.len()];
+        let mut x = padding;
+        let mut y = padding;
+        let mut shelf_height = 0;
+        for index in order {
+            let desc = &images[index].desc;
+            if x + desc.width() + padding > atlas_width {
+                x = padding;
+                y += shelf_height + padding;
+                shelf_height = 0;
+            }
+
+            rects[index] = Rect {
+                x,
+                y,
+                width: desc.width(),
+                height: desc.height(),
+            };
+            x += desc.width() + padding;
+            shelf_height = shelf_height.max(desc.height());
+        }
+        let atlas_height = y + shelf_height + padding;
+
+        let mut atlas = Image::new_empty(ImageDesc::new(atlas_width, atlas_height, color_format))?;
+        let bytes_per_pixel = color_format.byte_count() as usize;
+        for (image, rect) in images.iter().zip(rects.iter()) {
+            let row_size = rect.width as usize * bytes_per_pixel;
+            for row in 0..rect.height as usize {
+                let src_offset = row * image.desc.stride() as usize;
+                let dst_offset =
+                    (rect.y as usize + row) * atlas.desc.stride() as usize
+                        + rect.x as usize * bytes_per_pixel;
+
+                atlas.bytes[dst_offset..dst_offset + row_size]
+                    .copy_from_slice(&image.bytes[src_offset..src_offset + row_size]);
+            }
+        }
+
+        Ok((atlas, rects))
+    }
+}
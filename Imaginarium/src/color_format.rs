@@ -29,6 +29,14 @@ pub enum ChannelType {
     Int,
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
 #[derive(Clone, Copy, Debug, Hash, Default, PartialEq, Eq)]
 pub struct ColorFormat {
     pub channel_count: ChannelCount,
@@ -68,12 +76,9 @@ impl ColorFormat {
         self.channel_count.byte_count(self.channel_size)
     }
     pub fn validate(&self) -> anyhow::Result<()> {
-        if self.channel_type == ChannelType::Float {
-            match self.channel_size {
-                ChannelSize::_8bit | ChannelSize::_16bit =>
-                    return Err(anyhow::anyhow!("Unsupported channel size for float: {:?}", self.channel_size)),
-                _ => {},
-            }
+        // 16 bit floats are for gpu textures, images only store their bytes and don't convert them
+        if self.channel_type == ChannelType::Float && self.channel_size == ChannelSize::_8bit {
+            return Err(anyhow::anyhow!("Unsupported channel size for float: {:?}", self.channel_size));
         }
 
         Ok(())
@@ -288,6 +293,11 @@ impl ColorFormat {
         channel_size: ChannelSize::_64bit,
         channel_type: ChannelType::Int,
     };
+    pub const RGBA_F16: ColorFormat = ColorFormat {
+        channel_count: ChannelCount::Rgba,
+        channel_size: ChannelSize::_16bit,
+        channel_type: ChannelType::Float,
+    };
     pub const RGBA_F32: ColorFormat = ColorFormat {
         channel_count: ChannelCount::Rgba,
         channel_size: ChannelSize::_32bit,
@@ -0,0 +1,147 @@
+use std::mem::size_of;
+
+use bytemuck::Pod;
+use num_traits::{Bounded, ToPrimitive};
+
+use crate::color_format::*;
+use crate::image::Image;
+
+// differences of normalized channel values: 0..1 for unsigned, -1..1 for signed, raw floats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageDiff {
+    pub max: f64,
+    // pixel with the largest difference, the first one on ties
+    pub max_x: u32,
+    pub max_y: u32,
+    pub mean_squared: f64,
+}
+
+impl Image {
+    pub fn abs_diff(&self, other: &Image) -> anyhow::Result<ImageDiff> {
+        if self.desc.width() != other.desc.width()
+            || self.desc.height() != other.desc.height()
+            || self.desc.color_format() != other.desc.color_format() {
+            return Err(anyhow::anyhow!(
+                "Images can't be compared: {}x{} {:?} vs {}x{} {:?}",
+                self.desc.width(),
+                self.desc.height(),
+                self.desc.color_format(),
+                other.desc.width(),
+                other.desc.height(),
+                other.desc.color_format()
+            ));
+        }
+
+        let color_format = self.desc.color_format();
+        let diff = match (color_format.channel_size, color_format.channel_type) {
+            // @formatter:off
+            (ChannelSize:: _8bit, ChannelType:: UInt) => abs_diff::<u8 >(self, other),
+            (ChannelSize:: _8bit, ChannelType::  Int) => abs_diff::<i8 >(self, other),
+            (ChannelSize::_16bit, ChannelType:: UInt) => abs_diff::<u16>(self, other),
+            (ChannelSize::_16bit, ChannelType::  Int) => abs_diff::<i16>(self, other),
+            (ChannelSize::_32bit, ChannelType:: UInt) => abs_diff::<u32>(self, other),
+            (ChannelSize::_32bit, ChannelType::  Int) => abs_diff::<i32>(self, other),
+            (ChannelSize::_32bit, ChannelType::Float) => abs_diff::<f32>(self, other),
+            (ChannelSize::_64bit, ChannelType:: UInt) => abs_diff::<u64>(self, other),
+            (ChannelSize::_64bit, ChannelType::  Int) => abs_diff::<i64>(self, other),
+            (ChannelSize::_64bit, ChannelType::Float) => abs_diff::<f64>(self, other),
+            (_, _) => return Err(anyhow::anyhow!("Invalid color format: {:?}", color_format)),
+            // @formatter:on
+        };
+
+        Ok(diff)
+    }
+
+    // in dB with a peak value of 1, infinite for equal images
+    pub fn psnr(&self, other: &Image) -> anyhow::Result<f64> {
+        Ok(self.abs_diff(other)?.psnr())
+    }
+}
+
+impl ImageDiff {
+    pub fn psnr(&self) -> f64 {
+        if self.mean_squared == 0.0 {
+            f64::INFINITY
+        } else {
+            -10.0 * self.mean_squared.log10()
+        }
+    }
+}
+
+// fails with the largest difference, where it is and the PSNR, used by assert_images_eq
+pub fn check_images_eq(left: &Image, right: &Image, tolerance: f64) -> anyhow::Result<()> {
+    let diff = left.abs_diff(right)?;
+    if diff.max <= tolerance {
+        return Ok(());
+    }
+
+    Err(anyhow::anyhow!(
+        "Images differ by up to {:.6} (tolerance {}) at pixel ({}, {}), PSNR {:.2} dB",
+        diff.max,
+        tolerance,
+        diff.max_x,
+        diff.max_y,
+        diff.psnr()
+    ))
+}
+
+// asserts the largest difference of normalized channel values is within tolerance
+#[cfg(any(test, feature = "test-utils"))]
+#[macro_export]
+macro_rules! assert_images_eq {
+    ($left:expr, $right:expr, $tolerance:expr) => {
+        if let Err(err) = $crate::compare::check_images_eq(&$left, &$right, $tolerance) {
+            panic!("{}", err);
+        }
+    };
+}
+
+fn abs_diff<T>(left: &Image, right: &Image) -> ImageDiff
+where T: Copy + Pod + Bounded + ToPrimitive,
+{
+    let color_format = left.desc.color_format();
+    assert_eq!(color_format.channel_size.byte_count(), size_of::<T>() as u32);
+
+    let max = T::max_value().to_f64().unwrap();
+    let normalize = |value: T| match color_format.channel_type {
+        ChannelType::UInt | ChannelType::Int => value.to_f64().unwrap() / max,
+        ChannelType::Float => value.to_f64().unwrap(),
+    };
+    let read = |image: &Image, offset: usize| -> T {
+        bytemuck::pod_read_unaligned(&image.bytes[offset..offset + size_of::<T>()])
+    };
+
+    let channel_count = color_format.channel_count.channel_count() as usize;
+    let pixel_size = color_format.byte_count() as usize;
+
+    let mut diff = ImageDiff {
+        max: 0.0,
+        max_x: 0,
+        max_y: 0,
+        mean_squared: 0.0,
+    };
+    let mut squared_sum = 0.0;
+    for y in 0..left.desc.height() {
+        for x in 0..left.desc.width() {
+            for channel in 0..channel_count {
+                let left_offset = y as usize * left.desc.stride() as usize + x as usize * pixel_size + channel * size_of::<T>();
+                let right_offset = y as usize * right.desc.stride() as usize + x as usize * pixel_size + channel * size_of::<T>();
+                let difference = (normalize(read(left, left_offset)) - normalize(read(right, right_offset))).abs();
+
+                squared_sum += difference * difference;
+                if difference > diff.max {
+                    diff.max = difference;
+                    diff.max_x = x;
+                    diff.max_y = y;
+                }
+            }
+        }
+    }
+
+    let value_count = left.desc.width() as usize * left.desc.height() as usize * channel_count;
+    if value_count > 0 {
+        diff.mean_squared = squared_sum / value_count as f64;
+    }
+
+    diff
+}
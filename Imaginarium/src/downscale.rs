@@ -0,0 +1,87 @@
+use std::mem::size_of;
+
+use bytemuck::Pod;
+use num_traits::{NumCast, ToPrimitive};
+
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+
+impl Image {
+    // averages each factor x factor block into a single pixel;
+    // trailing rows and columns that don't fill a whole block are cropped
+    pub fn downscale_box(&self, factor: u32) -> anyhow::Result<Image> {
+        if factor == 0 {
+            return Err(anyhow::anyhow!("Downscale factor must be positive"));
+        }
+
+        let width = self.desc.width() / factor;
+        let height = self.desc.height() / factor;
+        if width == 0 || height == 0 {
+            return Err(anyhow::anyhow!("Downscale factor is larger than the image"));
+        }
+
+        let color_format = self.desc.color_format();
+        let mut result = Image::new_empty(ImageDesc::new(width, height, color_format))?;
+
+        match (color_format.channel_size, color_format.channel_type) {
+            // @formatter:off
+            (ChannelSize:: _8bit, ChannelType:: UInt) => downscale_pixels::<u8 >(self, &mut result, factor),
+            (ChannelSize:: _8bit, ChannelType::  Int) => downscale_pixels::<i8 >(self, &mut result, factor),
+            (ChannelSize::_16bit, ChannelType:: UInt) => downscale_pixels::<u16>(self, &mut result, factor),
+            (ChannelSize::_16bit, ChannelType::  Int) => downscale_pixels::<i16>(self, &mut result, factor),
+            (ChannelSize::_32bit, ChannelType:: UInt) => downscale_pixels::<u32>(self, &mut result, factor),
+            (ChannelSize::_32bit, ChannelType::  Int) => downscale_pixels::<i32>(self, &mut result, factor),
+            (ChannelSize::_32bit, ChannelType::Float) => downscale_pixels::<f32>(self, &mut result, factor),
+            (ChannelSize::_64bit, ChannelType:: UInt) => downscale_pixels::<u64>(self, &mut result, factor),
+            (ChannelSize::_64bit, ChannelType::  Int) => downscale_pixels::<i64>(self, &mut result, factor),
+            (ChannelSize::_64bit, ChannelType::Float) => downscale_pixels::<f64>(self, &mut result, factor),
+            (_, _) => return Err(anyhow::anyhow!("Invalid channel type")),
+            // @formatter:on
+        }
+
+        Ok(result)
+    }
+}
+
+fn downscale_pixels<T>(from: &Image, to: &mut Image, factor: u32)
+where T: Copy + Pod + NumCast + ToPrimitive,
+{
+    assert_eq!(from.desc.color_format(), to.desc.color_format());
+    assert_eq!(from.desc.color_format().channel_size.byte_count(), size_of::<T>() as u32);
+
+    let channel_count = from.desc.color_format().channel_count.channel_count() as usize;
+    let pixel_size = from.desc.color_format().byte_count() as usize;
+    let is_float = from.desc.color_format().channel_type == ChannelType::Float;
+    let block_size = (factor * factor) as f64;
+    let factor = factor as usize;
+
+    let mut sums = vec![0.0f64; channel_count];
+
+    for y in 0..to.desc.height() as usize {
+        for x in 0..to.desc.width() as usize {
+            sums.fill(0.0);
+
+            for block_y in 0..factor {
+                let row_offset = (y * factor + block_y) * from.desc.stride() as usize;
+                for block_x in 0..factor {
+                    let pixel_offset = row_offset + (x * factor + block_x) * pixel_size;
+                    for (channel, sum) in sums.iter_mut().enumerate() {
+                        let offset = pixel_offset + channel * size_of::<T>();
+                        let value: T = bytemuck::pod_read_unaligned(&from.bytes[offset..offset + size_of::<T>()]);
+                        *sum += value.to_f64().unwrap();
+                    }
+                }
+            }
+
+            let pixel_offset = y * to.desc.stride() as usize + x * pixel_size;
+            for (channel, sum) in sums.iter().enumerate() {
+                let average = sum / block_size;
+                let average = if is_float { average } else { average.round() };
+                let value: T = NumCast::from(average).unwrap();
+
+                let offset = pixel_offset + channel * size_of::<T>();
+                to.bytes[offset..offset + size_of::<T>()].copy_from_slice(bytemuck::bytes_of(&value));
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::mem::size_of;
+
+use bytemuck::Pod;
+use num_traits::{Bounded, ToPrimitive};
+
+use crate::color_format::*;
+use crate::image::Image;
+
+impl Image {
+    // per-channel pixel counts over `bins` equal ranges of normalized values:
+    // 0..1 for unsigned, -1..1 for signed, float channels clamped to 0..1;
+    // channels are placed as in map_pixels, absent ones stay zero
+    pub fn histogram(&self, bins: usize) -> anyhow::Result<Vec<[u64; 4]>> {
+        if bins == 0 {
+            return Err(anyhow::anyhow!("Histogram needs at least one bin"));
+        }
+
+        let color_format = self.desc.color_format();
+        let histogram = match (color_format.channel_size, color_format.channel_type) {
+            // @formatter:off
+            (ChannelSize:: _8bit, ChannelType:: UInt) => histogram::<u8 >(self, bins),
+            (ChannelSize:: _8bit, ChannelType::  Int) => histogram::<i8 >(self, bins),
+            (ChannelSize::_16bit, ChannelType:: UInt) => histogram::<u16>(self, bins),
+            (ChannelSize::_16bit, ChannelType::  Int) => histogram::<i16>(self, bins),
+            (ChannelSize::_32bit, ChannelType:: UInt) => histogram::<u32>(self, bins),
+            (ChannelSize::_32bit, ChannelType::  Int) => histogram::<i32>(self, bins),
+            (ChannelSize::_32bit, ChannelType::Float) => histogram::<f32>(self, bins),
+            (ChannelSize::_64bit, ChannelType:: UInt) => histogram::<u64>(self, bins),
+            (ChannelSize::_64bit, ChannelType::  Int) => histogram::<i64>(self, bins),
+            (ChannelSize::_64bit, ChannelType::Float) => histogram::<f64>(self, bins),
+            (_, _) => return Err(anyhow::anyhow!("Invalid color format: {:?}", color_format)),
+            // @formatter:on
+        };
+
+        Ok(histogram)
+    }
+}
+
+fn histogram<T>(image: &Image, bins: usize) -> Vec<[u64; 4]>
+where T: Copy + Pod + Bounded + ToPrimitive,
+{
+    let color_format = image.desc.color_format();
+    assert_eq!(color_format.channel_size.byte_count(), size_of::<T>() as u32);
+
+    let max = T::max_value().to_f64().unwrap();
+    let normalize = |value: f64| match color_format.channel_type {
+        ChannelType::UInt => value / max,
+        ChannelType::Int => (value / max + 1.0) / 2.0,
+        ChannelType::Float => value,
+    };
+    let slots: &[usize] = match color_format.channel_count {
+        ChannelCount::Gray => &[0],
+        ChannelCount::GrayAlpha => &[0, 3],
+        ChannelCount::Rgb => &[0, 1, 2],
+        ChannelCount::Rgba => &[0, 1, 2, 3],
+    };
+
+    let pixel_size = color_format.byte_count() as usize;
+    let stride = image.desc.stride() as usize;
+
+    let mut histogram = vec![[0u64; 4]; bins];
+    for y in 0..image.desc.height() as usize {
+        for x in 0..image.desc.width() as usize {
+            let pixel_offset = y * stride + x * pixel_size;
+
+            for (channel, &slot) in slots.iter().enumerate() {
+                let offset = pixel_offset + channel * size_of::<T>();
+                let value: T = bytemuck::pod_read_unaligned(&image.bytes[offset..offset + size_of::<T>()]);
+                let value = normalize(value.to_f64().unwrap());
+                if value.is_nan() {
+                    continue;
+                }
+
+                let bin = (value.clamp(0.0, 1.0) * bins as f64) as usize;
+                histogram[bin.min(bins - 1)][slot] += 1;
+            }
+        }
+    }
+
+    histogram
+}
@@ -8,7 +8,7 @@ use crate::color_format::*;
 use crate::image_convertion::convert_image;
 use crate::tiff_extentions::save_tiff;
 
-fn get_file_extension(filename: &str) -> anyhow::Result<&str> {
+pub(crate) fn get_file_extension(filename: &str) -> anyhow::Result<&str> {
     let extension = Path::new(filename)
         .extension()
         .and_then(|os_str| os_str.to_str())
@@ -32,7 +32,7 @@ fn align_stride(n: u32) -> u32 {
 }
 
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct ImageDesc {
     width: u32,
     height: u32,
@@ -40,6 +40,13 @@ pub struct ImageDesc {
     color_format: ColorFormat,
 }
 
+#[derive(Clone, Default, Debug)]
+pub struct ImageDescBuilder {
+    width: u32,
+    height: u32,
+    color_format: Option<ColorFormat>,
+}
+
 #[derive(Clone)]
 pub struct Image {
     pub desc: ImageDesc,
@@ -74,6 +81,7 @@ impl Image {
             match extension {
                 "png" | "jpeg" | "jpg" => Image::load_png_jpeg(filename)?,
                 "tiff" => Image::load_tiff(filename)?,
+                "exr" | "hdr" => Image::load_hdr(filename)?,
 
                 _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
             };
@@ -81,11 +89,19 @@ impl Image {
         Ok(image)
     }
 
+    // 8 and 16 bit, gray, gray with alpha, RGB and RGBA
+    pub fn load_png(filename: &str) -> anyhow::Result<Image> {
+        let reader = std::io::BufReader::new(File::open(filename)?);
+        let img = image_lib::load(reader, ImageFormat::Png)?;
+
+        Image::from_dynamic_image(img)
+    }
     fn load_png_jpeg(filename: &str) -> anyhow::Result<Image> {
-        let img =
-            image_lib::open(filename)
-                .expect("Failed to open image");
+        let img = image_lib::open(filename)?;
 
+        Image::from_dynamic_image(img)
+    }
+    fn from_dynamic_image(img: image_lib::DynamicImage) -> anyhow::Result<Image> {
         let (channel_count, channel_size, channel_type) = match img.color() {
             // @formatter:off
             image_lib::ColorType::L8      => (ChannelCount::Gray,      ChannelSize:: _8bit, ChannelType::UInt  ),
@@ -98,10 +114,11 @@ impl Image {
             image_lib::ColorType::Rgba16  => (ChannelCount::Rgba,      ChannelSize::_16bit, ChannelType::UInt  ),
             image_lib::ColorType::Rgb32F  => (ChannelCount::Rgb,       ChannelSize::_32bit, ChannelType::Float ),
             image_lib::ColorType::Rgba32F => (ChannelCount::Rgba,      ChannelSize::_32bit, ChannelType::Float ),
-            _ =>  panic!("Unsupported color type: {:?}", img.color()),
+            _ => return Err(anyhow::anyhow!("Unsupported color type: {:?}", img.color())),
             // @formatter:on
         };
 
+        // 16 bit channels are in native byte order
         let bytes = img.as_bytes().to_vec();
 
         let image = Image {
@@ -186,6 +203,7 @@ impl Image {
             "png" => self.save_png(filename)?,
             "jpeg" | "jpg" => self.save_jpg(filename)?,
             "tiff" => self.save_tiff(filename)?,
+            "exr" | "hdr" => self.save_hdr(filename)?,
 
             _ => return Err(anyhow::anyhow!("Unsupported file extension: {}", extension)),
         };
@@ -220,7 +238,8 @@ impl Image {
 
         Ok(())
     }
-    fn save_png(&self, filename: &str) -> anyhow::Result<()> {
+    // 8 and 16 bit unsigned formats
+    pub fn save_png(&self, filename: &str) -> anyhow::Result<()> {
         if self.desc.color_format().channel_type != ChannelType::UInt {
             return Err(anyhow::anyhow!("Unsupported PNG channel type: {:?}", self.desc.color_format().channel_type));
         }
@@ -257,6 +276,64 @@ impl Image {
         save_tiff(self, filename)
     }
 
+    // OpenEXR (.exr) or Radiance HDR (.hdr), loaded as RGB_F32 or RGBA_F32
+    pub fn load_hdr(filename: &str) -> anyhow::Result<Image> {
+        let img = image_lib::open(filename)?;
+        let (width, height) = (img.width(), img.height());
+
+        let (color_format, bytes) = if img.color().has_alpha() {
+            (ColorFormat::RGBA_F32, bytemuck::cast_slice(img.into_rgba32f().as_raw()).to_vec())
+        } else {
+            (ColorFormat::RGB_F32, bytemuck::cast_slice(img.into_rgb32f().as_raw()).to_vec())
+        };
+
+        Ok(Image {
+            desc: ImageDesc::new(width, height, color_format),
+            bytes,
+        })
+    }
+    // other formats are converted to 32 bit float first;
+    // Radiance HDR has no alpha and stores colors with a shared exponent, so it is lossy
+    pub fn save_hdr(&self, filename: &str) -> anyhow::Result<()> {
+        let image_format = match get_file_extension(filename)? {
+            "exr" => ImageFormat::OpenExr,
+            "hdr" => ImageFormat::Hdr,
+            extension => return Err(anyhow::anyhow!("Unsupported HDR file extension: {}", extension)),
+        };
+
+        let has_alpha = matches!(self.desc.color_format.channel_count, ChannelCount::GrayAlpha | ChannelCount::Rgba);
+        let color_format = if has_alpha && image_format == ImageFormat::OpenExr {
+            ColorFormat::RGBA_F32
+        } else {
+            ColorFormat::RGB_F32
+        };
+
+        let converted;
+        let image = if self.desc.color_format == color_format {
+            self
+        } else {
+            converted = self.convert(color_format)?;
+            &converted
+        };
+
+        let color_type = if color_format == ColorFormat::RGBA_F32 {
+            image_lib::ColorType::Rgba32F
+        } else {
+            image_lib::ColorType::Rgb32F
+        };
+
+        image_lib::save_buffer_with_format(
+            filename,
+            &image.bytes,
+            image.desc.width,
+            image.desc.height,
+            color_type,
+            image_format,
+        )?;
+
+        Ok(())
+    }
+
 
     pub fn convert(
         &self,
@@ -282,6 +359,21 @@ impl Image {
         Ok(result)
     }
 
+    // like convert, but a copy of the image if it is already in the target format
+    pub fn convert_to(&self, target: ColorFormat) -> anyhow::Result<Image> {
+        if self.desc.color_format == target {
+            return Ok(self.clone());
+        }
+
+        self.convert(target)
+            .map_err(|err| anyhow::anyhow!(
+                "Can't convert image from {:?} to {:?}: {}",
+                self.desc.color_format,
+                target,
+                err
+            ))
+    }
+
     pub fn bytes_per_pixel(&self) -> u32 {
         self.desc.color_format().byte_count()
     }
@@ -315,4 +407,41 @@ impl ImageDesc {
     pub fn color_format(&self) -> ColorFormat {
         self.color_format
     }
+
+    pub fn builder() -> ImageDescBuilder {
+        ImageDescBuilder::default()
+    }
+}
+
+impl ImageDescBuilder {
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+    pub fn format(mut self, color_format: ColorFormat) -> Self {
+        self.color_format = Some(color_format);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<ImageDesc> {
+        if self.width == 0 || self.height == 0 {
+            return Err(anyhow::anyhow!("Image dimensions must be non-zero: {}x{}", self.width, self.height));
+        }
+        let color_format = self.color_format
+            .ok_or(anyhow::anyhow!("Image color format is not set"))?;
+        color_format.validate()?;
+
+        // stride and size must fit into u32
+        (self.width as u64)
+            .checked_mul(color_format.byte_count() as u64)
+            .and_then(|stride| stride.checked_mul(self.height as u64))
+            .filter(|&size| size <= u32::MAX as u64)
+            .ok_or(anyhow::anyhow!("Image is too large: {}x{}", self.width, self.height))?;
+
+        Ok(ImageDesc::new(self.width, self.height, color_format))
+    }
 }
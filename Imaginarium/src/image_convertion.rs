@@ -192,11 +192,6 @@ where From: Copy + Pod,
     assert_eq!(from.desc.color_format().channel_size.byte_count(), size_of::<From>() as u32);
     assert_eq!(to.desc.color_format().channel_size.byte_count(), size_of::<To>() as u32);
 
-    if from.desc.color_format().channel_count == to.desc.color_format().channel_count
-        && from.desc.color_format().channel_size == to.desc.color_format().channel_size {
-        return;
-    }
-
     let to_pixel_size = to.desc.color_format().byte_count();
     let from_pixel_size = from.desc.color_format().byte_count();
 
@@ -4,8 +4,14 @@
 mod tests;
 
 pub mod image;
+pub mod atlas;
+mod downscale;
+mod pixel_map;
+mod histogram;
+mod matte;
 mod image_convertion;
 mod tiff_extentions;
 #[cfg(feature = "wgpu")]
 pub mod wgpu;
 pub mod color_format;
+pub mod compare;
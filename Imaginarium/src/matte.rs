@@ -0,0 +1,160 @@
+use std::mem::size_of;
+
+use bytemuck::Pod;
+use num_traits::{Bounded, NumCast, ToPrimitive};
+
+use crate::color_format::*;
+use crate::image::{Image, ImageDesc};
+
+impl Image {
+    // single channel image of the same size and channel type,
+    // gray stands for red, green and blue
+    pub fn extract_channel(&self, channel: Channel) -> anyhow::Result<Image> {
+        let color_format = self.desc.color_format();
+        let channel_index = match (color_format.channel_count, channel) {
+            // @formatter:off
+            (ChannelCount::Gray     , Channel::Alpha) => None,
+            (ChannelCount::Gray     , _             ) => Some(0),
+            (ChannelCount::GrayAlpha, Channel::Alpha) => Some(1),
+            (ChannelCount::GrayAlpha, _             ) => Some(0),
+            (ChannelCount::Rgb      , Channel::Alpha) => None,
+            (_                      , Channel::Red  ) => Some(0),
+            (_                      , Channel::Green) => Some(1),
+            (_                      , Channel::Blue ) => Some(2),
+            (ChannelCount::Rgba     , Channel::Alpha) => Some(3),
+            // @formatter:on
+        }
+            .ok_or_else(|| anyhow::anyhow!("No {:?} channel in {:?}", channel, color_format))?;
+
+        let mut result = Image::new_empty(ImageDesc::new(
+            self.desc.width(),
+            self.desc.height(),
+            ColorFormat {
+                channel_count: ChannelCount::Gray,
+                ..color_format
+            },
+        ))?;
+
+        let channel_size = color_format.channel_size.byte_count() as usize;
+        let pixel_size = color_format.byte_count() as usize;
+        let stride = self.desc.stride() as usize;
+        let result_stride = result.desc.stride() as usize;
+
+        for y in 0..self.desc.height() as usize {
+            for x in 0..self.desc.width() as usize {
+                let offset = y * stride + x * pixel_size + channel_index * channel_size;
+                let result_offset = y * result_stride + x * channel_size;
+                result.bytes[result_offset..result_offset + channel_size]
+                    .copy_from_slice(&self.bytes[offset..offset + channel_size]);
+            }
+        }
+
+        Ok(result)
+    }
+
+    // multiplies alpha by the single channel of the matte, normalized and clamped to 0..1
+    pub fn apply_matte(&self, matte: &Image) -> anyhow::Result<Image> {
+        let color_format = self.desc.color_format();
+        let matte_format = matte.desc.color_format();
+        if matte_format.channel_count != ChannelCount::Gray {
+            return Err(anyhow::anyhow!("Matte must have a single channel, got {:?}", matte_format));
+        }
+        if !matches!(color_format.channel_count, ChannelCount::GrayAlpha | ChannelCount::Rgba) {
+            return Err(anyhow::anyhow!("Image has no alpha channel: {:?}", color_format));
+        }
+        if matte.desc.width() != self.desc.width() || matte.desc.height() != self.desc.height() {
+            return Err(anyhow::anyhow!(
+                "Matte size {}x{} does not match image size {}x{}",
+                matte.desc.width(), matte.desc.height(),
+                self.desc.width(), self.desc.height()
+            ));
+        }
+
+        let factors = match (matte_format.channel_size, matte_format.channel_type) {
+            // @formatter:off
+            (ChannelSize:: _8bit, ChannelType:: UInt) => matte_factors::<u8 >(matte),
+            (ChannelSize:: _8bit, ChannelType::  Int) => matte_factors::<i8 >(matte),
+            (ChannelSize::_16bit, ChannelType:: UInt) => matte_factors::<u16>(matte),
+            (ChannelSize::_16bit, ChannelType::  Int) => matte_factors::<i16>(matte),
+            (ChannelSize::_32bit, ChannelType:: UInt) => matte_factors::<u32>(matte),
+            (ChannelSize::_32bit, ChannelType::  Int) => matte_factors::<i32>(matte),
+            (ChannelSize::_32bit, ChannelType::Float) => matte_factors::<f32>(matte),
+            (ChannelSize::_64bit, ChannelType:: UInt) => matte_factors::<u64>(matte),
+            (ChannelSize::_64bit, ChannelType::  Int) => matte_factors::<i64>(matte),
+            (ChannelSize::_64bit, ChannelType::Float) => matte_factors::<f64>(matte),
+            (_, _) => return Err(anyhow::anyhow!("Invalid color format: {:?}", matte_format)),
+            // @formatter:on
+        };
+
+        let mut result = self.clone();
+        match (color_format.channel_size, color_format.channel_type) {
+            // @formatter:off
+            (ChannelSize:: _8bit, ChannelType:: UInt) => multiply_alpha::<u8 >(&mut result, &factors),
+            (ChannelSize:: _8bit, ChannelType::  Int) => multiply_alpha::<i8 >(&mut result, &factors),
+            (ChannelSize::_16bit, ChannelType:: UInt) => multiply_alpha::<u16>(&mut result, &factors),
+            (ChannelSize::_16bit, ChannelType::  Int) => multiply_alpha::<i16>(&mut result, &factors),
+            (ChannelSize::_32bit, ChannelType:: UInt) => multiply_alpha::<u32>(&mut result, &factors),
+            (ChannelSize::_32bit, ChannelType::  Int) => multiply_alpha::<i32>(&mut result, &factors),
+            (ChannelSize::_32bit, ChannelType::Float) => multiply_alpha::<f32>(&mut result, &factors),
+            (ChannelSize::_64bit, ChannelType:: UInt) => multiply_alpha::<u64>(&mut result, &factors),
+            (ChannelSize::_64bit, ChannelType::  Int) => multiply_alpha::<i64>(&mut result, &factors),
+            (ChannelSize::_64bit, ChannelType::Float) => multiply_alpha::<f64>(&mut result, &factors),
+            (_, _) => return Err(anyhow::anyhow!("Invalid color format: {:?}", color_format)),
+            // @formatter:on
+        }
+
+        Ok(result)
+    }
+}
+
+// row-major factors in 0..1
+fn matte_factors<T>(matte: &Image) -> Vec<f64>
+where T: Copy + Pod + Bounded + ToPrimitive,
+{
+    let color_format = matte.desc.color_format();
+    assert_eq!(color_format.channel_size.byte_count(), size_of::<T>() as u32);
+
+    let scale = match color_format.channel_type {
+        ChannelType::UInt | ChannelType::Int => T::max_value().to_f64().unwrap(),
+        ChannelType::Float => 1.0,
+    };
+    let stride = matte.desc.stride() as usize;
+
+    let mut factors = Vec::with_capacity((matte.desc.width() * matte.desc.height()) as usize);
+    for y in 0..matte.desc.height() as usize {
+        for x in 0..matte.desc.width() as usize {
+            let offset = y * stride + x * size_of::<T>();
+            let value: T = bytemuck::pod_read_unaligned(&matte.bytes[offset..offset + size_of::<T>()]);
+            factors.push((value.to_f64().unwrap() / scale).clamp(0.0, 1.0));
+        }
+    }
+
+    factors
+}
+
+fn multiply_alpha<T>(image: &mut Image, factors: &[f64])
+where T: Copy + Pod + NumCast + ToPrimitive,
+{
+    let color_format = image.desc.color_format();
+    assert_eq!(color_format.channel_size.byte_count(), size_of::<T>() as u32);
+
+    // alpha is the last channel
+    let alpha_offset = (color_format.channel_count.channel_count() as usize - 1) * size_of::<T>();
+    let pixel_size = color_format.byte_count() as usize;
+    let stride = image.desc.stride() as usize;
+    let width = image.desc.width() as usize;
+
+    for y in 0..image.desc.height() as usize {
+        for x in 0..width {
+            let offset = y * stride + x * pixel_size + alpha_offset;
+            let value: T = bytemuck::pod_read_unaligned(&image.bytes[offset..offset + size_of::<T>()]);
+
+            let mut value = value.to_f64().unwrap() * factors[y * width + x];
+            if color_format.channel_type != ChannelType::Float {
+                value = value.round();
+            }
+            let value: T = NumCast::from(value).unwrap();
+            image.bytes[offset..offset + size_of::<T>()].copy_from_slice(bytemuck::bytes_of(&value));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::mem::size_of;
+
+use bytemuck::Pod;
+use num_traits::{Bounded, NumCast, ToPrimitive};
+
+use crate::color_format::*;
+use crate::image::Image;
+
+impl Image {
+    // applies f to every pixel as normalized RGBA:
+    // 0..1 for unsigned, -1..1 for signed, unchanged for float channels;
+    // channels absent from the format are passed as 0 (alpha as 1) and their results are dropped,
+    // gray is passed in the red channel
+    pub fn map_pixels<F>(&self, f: F) -> Image
+    where F: Fn([f32; 4]) -> [f32; 4]
+    {
+        let color_format = self.desc.color_format();
+        let width = self.desc.width() as usize;
+        let map_row = row_mapper::<F>(color_format);
+
+        let mut result = self.clone();
+        let (rows, stride) = rows_mut(&mut result);
+        rows
+            .chunks_mut(stride)
+            .for_each(|row| map_row(row, width, color_format, &f));
+
+        result
+    }
+
+    // same as map_pixels with rows mapped on the rayon thread pool
+    #[cfg(feature = "parallel")]
+    pub fn par_map_pixels<F>(&self, f: F) -> Image
+    where F: Fn([f32; 4]) -> [f32; 4] + Sync
+    {
+        use rayon::prelude::*;
+
+        let color_format = self.desc.color_format();
+        let width = self.desc.width() as usize;
+        let map_row = row_mapper::<F>(color_format);
+
+        let mut result = self.clone();
+        let (rows, stride) = rows_mut(&mut result);
+        rows
+            .par_chunks_mut(stride)
+            .for_each(|row| map_row(row, width, color_format, &f));
+
+        result
+    }
+}
+
+type MapRow<F> = fn(&mut [u8], usize, ColorFormat, &F);
+
+fn row_mapper<F>(color_format: ColorFormat) -> MapRow<F>
+where F: Fn([f32; 4]) -> [f32; 4]
+{
+    match (color_format.channel_size, color_format.channel_type) {
+        // @formatter:off
+        (ChannelSize:: _8bit, ChannelType:: UInt) => map_row::<u8 , F>,
+        (ChannelSize:: _8bit, ChannelType::  Int) => map_row::<i8 , F>,
+        (ChannelSize::_16bit, ChannelType:: UInt) => map_row::<u16, F>,
+        (ChannelSize::_16bit, ChannelType::  Int) => map_row::<i16, F>,
+        (ChannelSize::_32bit, ChannelType:: UInt) => map_row::<u32, F>,
+        (ChannelSize::_32bit, ChannelType::  Int) => map_row::<i32, F>,
+        (ChannelSize::_32bit, ChannelType::Float) => map_row::<f32, F>,
+        (ChannelSize::_64bit, ChannelType:: UInt) => map_row::<u64, F>,
+        (ChannelSize::_64bit, ChannelType::  Int) => map_row::<i64, F>,
+        (ChannelSize::_64bit, ChannelType::Float) => map_row::<f64, F>,
+        (_, _) => panic!("Invalid color format: {:?}", color_format),
+        // @formatter:on
+    }
+}
+
+// pixel rows without trailing bytes, and the stride to split them by
+fn rows_mut(image: &mut Image) -> (&mut [u8], usize) {
+    let stride = image.desc.stride() as usize;
+    if stride == 0 {
+        // chunks_mut panics on a zero chunk size
+        return (&mut [], 1);
+    }
+    let height = image.desc.height() as usize;
+
+    (&mut image.bytes[..stride * height], stride)
+}
+
+fn map_row<T, F>(row: &mut [u8], width: usize, color_format: ColorFormat, f: &F)
+where
+    T: Copy + Pod + Bounded + NumCast + ToPrimitive,
+    F: Fn([f32; 4]) -> [f32; 4],
+{
+    assert_eq!(color_format.channel_size.byte_count(), size_of::<T>() as u32);
+
+    let (scale, min) = match color_format.channel_type {
+        ChannelType::UInt => (T::max_value().to_f64().unwrap(), 0.0),
+        ChannelType::Int => (T::max_value().to_f64().unwrap(), -1.0),
+        ChannelType::Float => (1.0, f64::MIN),
+    };
+    // slot in the RGBA pixel for each channel of the format
+    let slots: &[usize] = match color_format.channel_count {
+        ChannelCount::Gray => &[0],
+        ChannelCount::GrayAlpha => &[0, 3],
+        ChannelCount::Rgb => &[0, 1, 2],
+        ChannelCount::Rgba => &[0, 1, 2, 3],
+    };
+    let pixel_size = color_format.byte_count() as usize;
+
+    for x in 0..width {
+        let pixel_offset = x * pixel_size;
+
+        let mut rgba = [0.0, 0.0, 0.0, 1.0];
+        for (channel, &slot) in slots.iter().enumerate() {
+            let offset = pixel_offset + channel * size_of::<T>();
+            let value: T = bytemuck::pod_read_unaligned(&row[offset..offset + size_of::<T>()]);
+            rgba[slot] = (value.to_f64().unwrap() / scale) as f32;
+        }
+
+        let rgba = f(rgba);
+
+        for (channel, &slot) in slots.iter().enumerate() {
+            let mut value = (rgba[slot] as f64).max(min) * scale;
+            if color_format.channel_type != ChannelType::Float {
+                value = value.round().min(scale);
+            }
+            let value: T = NumCast::from(value).unwrap();
+
+            let offset = pixel_offset + channel * size_of::<T>();
+            row[offset..offset + size_of::<T>()].copy_from_slice(bytemuck::bytes_of(&value));
+        }
+    }
+}
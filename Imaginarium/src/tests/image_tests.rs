@@ -1,5 +1,6 @@
+use crate::atlas::Rect;
 use crate::color_format::*;
-use crate::image::Image;
+use crate::image::{Image, ImageDesc};
 
 #[test]
 fn it_works() {
@@ -82,3 +83,327 @@ fn image_convertion() {
         .convert(ColorFormat::RGBA_U16).unwrap()
         .save_file("../test_output/convertion-x2-rgba-u16.tiff").unwrap();
 }
+
+#[test]
+fn pack_atlas() -> anyhow::Result<()> {
+    let images = (0..4u8)
+        .map(|index| {
+            let desc = ImageDesc::new(8, 8, ColorFormat::RGBA_U8);
+            let bytes = vec![index + 1; desc.size_in_bytes() as usize];
+            Image::new_with_data(desc, bytes)
+        })
+        .collect::<anyhow::Result<Vec<Image>>>()?;
+    let image_refs = images.iter().collect::<Vec<&Image>>();
+
+    let (atlas, rects) = Image::pack_atlas(&image_refs, 2)?;
+    assert_eq!(rects.len(), 4);
+
+    for (index, rect) in rects.iter().enumerate() {
+        assert_eq!((rect.width, rect.height), (8, 8));
+        assert!(rect.x + rect.width <= atlas.desc.width());
+        assert!(rect.y + rect.height <= atlas.desc.height());
+
+        let others = rects.iter()
+            .enumerate()
+            .filter(|(other_index, _)| *other_index != index)
+            .map(|(_, other)| other)
+            .collect::<Vec<&Rect>>();
+        assert!(others.iter().all(|other| !rect.intersects(other)));
+
+        // corner pixel comes from the packed image
+        let offset = (rect.y * atlas.desc.stride() + rect.x * atlas.bytes_per_pixel()) as usize;
+        assert_eq!(atlas.bytes[offset], index as u8 + 1);
+    }
+
+    let gray = Image::new_empty(ImageDesc::new(8, 8, ColorFormat::GRAY_U8))?;
+    assert!(Image::pack_atlas(&[&images[0], &gray], 2).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn downscale_box() -> anyhow::Result<()> {
+    let desc = ImageDesc::new(4, 4, ColorFormat::GRAY_U8);
+    let bytes: Vec<u8> = vec![
+        0, 2, 10, 20,
+        4, 6, 30, 41,
+        100, 100, 1, 1,
+        200, 200, 1, 2,
+    ];
+    let image = Image::new_with_data(desc, bytes)?;
+
+    let downscaled = image.downscale_box(2)?;
+    assert_eq!(downscaled.desc.width(), 2);
+    assert_eq!(downscaled.desc.height(), 2);
+    assert_eq!(downscaled.bytes, vec![3, 25, 150, 1]);
+
+    // odd dimensions are cropped to the largest divisible size
+    let downscaled = image.downscale_box(3)?;
+    assert_eq!(downscaled.desc.width(), 1);
+    assert_eq!(downscaled.bytes, vec![28]);
+
+    assert!(image.downscale_box(0).is_err());
+    assert!(image.downscale_box(5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn map_pixels_invert() -> anyhow::Result<()> {
+    let desc = ImageDesc::new(2, 1, ColorFormat::RGBA_U8);
+    let image = Image::new_with_data(desc, vec![0, 51, 255, 200, 10, 20, 30, 40])?;
+
+    let inverted = image.map_pixels(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, a]);
+    assert_eq!(inverted.bytes, vec![255, 204, 0, 200, 245, 235, 225, 40]);
+    assert_eq!(inverted.desc, image.desc);
+
+    // absent channels are ignored
+    let desc = ImageDesc::new(2, 1, ColorFormat::GRAY_U8);
+    let gray = Image::new_with_data(desc, vec![0, 100])?;
+    let inverted = gray.map_pixels(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, a]);
+    assert_eq!(inverted.bytes, vec![255, 155]);
+
+    Ok(())
+}
+
+#[test]
+fn hdr_round_trip() -> anyhow::Result<()> {
+    let pixels: Vec<f32> = vec![
+        0.0, 0.5, 1.0, 1.0,
+        2.5, 16.0, 1000.0, 0.25,
+        -0.5, 0.125, 3.75, 0.0,
+        65.5, 0.001, 7.0, 1.0,
+    ];
+    let desc = ImageDesc::new(2, 2, ColorFormat::RGBA_F32);
+    let image = Image::new_with_data(desc, bytemuck::cast_slice(&pixels).to_vec())?;
+
+    let path = std::env::temp_dir().join("imaginarium-hdr-round-trip.exr");
+    let path = path.to_str().unwrap();
+    image.save_file(path)?;
+    let loaded = Image::read_file(path)?;
+    std::fs::remove_file(path)?;
+
+    assert_eq!(loaded.desc, image.desc);
+    let loaded_pixels: &[f32] = bytemuck::cast_slice(&loaded.bytes);
+    assert_eq!(loaded_pixels, pixels.as_slice());
+
+    assert!(image.save_hdr("image.png").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn image_desc_builder() -> anyhow::Result<()> {
+    let desc = ImageDesc::builder()
+        .width(30)
+        .height(20)
+        .format(ColorFormat::RGB_U8)
+        .build()?;
+    assert_eq!(desc, ImageDesc::new(30, 20, ColorFormat::RGB_U8));
+    assert_eq!(desc.stride(), 90);
+    assert_eq!(desc.size_in_bytes(), 1800);
+
+    assert!(ImageDesc::builder().width(0).height(20).format(ColorFormat::RGB_U8).build().is_err());
+    assert!(ImageDesc::builder().width(30).height(20).build().is_err());
+    assert!(ImageDesc::builder().width(u32::MAX).height(2).format(ColorFormat::RGBA_F32).build().is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn map_pixels_parallel_matches_serial() -> anyhow::Result<()> {
+    let formats = [ColorFormat::RGBA_U8, ColorFormat::GRAY_ALPHA_I16, ColorFormat::RGB_F32];
+    for color_format in formats {
+        let mut image = Image::new_empty(ImageDesc::new(67, 45, color_format))?;
+        for (index, byte) in image.bytes.iter_mut().enumerate() {
+            *byte = (index * 31 % 251) as u8;
+        }
+        // nan float patterns would not compare equal
+        if color_format.channel_type == ChannelType::Float {
+            image = image.map_pixels(|rgba| rgba.map(|value| if value.is_finite() { value } else { 0.5 }));
+        }
+
+        let f = |[r, g, b, a]: [f32; 4]| [1.0 - r, g * 0.5, b * b, a];
+        let serial = image.map_pixels(f);
+        let parallel = image.par_map_pixels(f);
+        assert_eq!(serial.bytes, parallel.bytes, "{:?}", color_format);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn histogram() -> anyhow::Result<()> {
+    let mut img = Image::new_empty(ImageDesc::new(256, 2, ColorFormat::GRAY_U8))?;
+    for (index, byte) in img.bytes.iter_mut().enumerate() {
+        *byte = (index % 256) as u8;
+    }
+
+    let histogram = img.histogram(256)?;
+    assert!(histogram.iter().all(|bin| *bin == [2, 0, 0, 0]));
+    let histogram = img.histogram(4)?;
+    assert!(histogram.iter().all(|bin| *bin == [128, 0, 0, 0]));
+    assert!(img.histogram(0).is_err());
+
+    // float values are clamped to 0..1
+    let mut img = Image::new_empty(ImageDesc::new(4, 1, ColorFormat::RGBA_F32))?;
+    let values: [f32; 16] = [
+        -0.5, 0.1, 0.1, 1.0,
+        0.25, 0.1, 0.1, 1.0,
+        0.75, 0.1, 0.9, 1.0,
+        2.0, 0.1, 0.9, 0.0,
+    ];
+    img.bytes.copy_from_slice(bytemuck::cast_slice(&values));
+
+    let histogram = img.histogram(2)?;
+    assert_eq!(histogram, vec![[2, 4, 2, 1], [2, 0, 2, 3]]);
+
+    Ok(())
+}
+
+#[test]
+fn extract_channel_and_matte() -> anyhow::Result<()> {
+    let mut img = Image::new_empty(ImageDesc::new(3, 2, ColorFormat::RGBA_U8))?;
+    let stride = img.desc.stride() as usize;
+    for y in 0..2 {
+        for x in 0..3 {
+            let offset = y * stride + x * 4;
+            img.bytes[offset..offset + 4].copy_from_slice(&[(10 * x + y) as u8, 100, 200, 255]);
+        }
+    }
+
+    let red = img.extract_channel(Channel::Red)?;
+    assert_eq!(red.desc.color_format(), ColorFormat::GRAY_U8);
+    assert_eq!((red.desc.width(), red.desc.height()), (3, 2));
+    let red_stride = red.desc.stride() as usize;
+    for y in 0..2 {
+        for x in 0..3 {
+            assert_eq!(red.bytes[y * red_stride + x], (10 * x + y) as u8);
+        }
+    }
+    assert!(red.extract_channel(Channel::Alpha).is_err());
+
+    // 50% gray matte halves the alpha, color is untouched
+    let mut matte = Image::new_empty(ImageDesc::new(3, 2, ColorFormat::GRAY_U8))?;
+    matte.bytes.fill(128);
+    let matted = img.apply_matte(&matte)?;
+    for y in 0..2 {
+        for x in 0..3 {
+            let offset = y * stride + x * 4;
+            assert_eq!(matted.bytes[offset..offset + 4], [(10 * x + y) as u8, 100, 200, 128]);
+        }
+    }
+
+    assert!(img.apply_matte(&img).is_err());
+    assert!(img.apply_matte(&Image::new_empty(ImageDesc::new(2, 2, ColorFormat::GRAY_U8))?).is_err());
+    assert!(red.apply_matte(&matte).is_err());
+
+    Ok(())
+}
+
+fn gradient(width: u32, height: u32) -> Image {
+    let desc = ImageDesc::new(width, height, ColorFormat::RGB_U8);
+    let mut img = Image::new_empty(desc).unwrap();
+    img.bytes
+        .iter_mut()
+        .enumerate()
+        .for_each(|(index, byte)| *byte = (index * 3) as u8);
+    img
+}
+
+#[test]
+fn image_compare() {
+    let img = gradient(5, 4);
+    let mut changed = img.clone();
+    // green of pixel (3, 2) off by 51, blue of pixel (1, 1) off by 1
+    let stride = img.desc.stride() as usize;
+    changed.bytes[2 * stride + 3 * 3 + 1] = changed.bytes[2 * stride + 3 * 3 + 1].wrapping_add(51);
+    changed.bytes[stride + 3 + 2] = changed.bytes[stride + 3 + 2].wrapping_add(1);
+
+    assert_eq!(img.psnr(&img).unwrap(), f64::INFINITY);
+    crate::assert_images_eq!(img, img.clone(), 0.0);
+
+    let diff = img.abs_diff(&changed).unwrap();
+    assert!((diff.max - 0.2).abs() < 1e-9);
+    assert_eq!((diff.max_x, diff.max_y), (3, 2));
+    assert!(diff.psnr() > 20.0 && diff.psnr().is_finite());
+    crate::assert_images_eq!(img, changed, 0.21);
+
+    let other_format = img.convert(ColorFormat::RGBA_U8).unwrap();
+    assert!(img.abs_diff(&other_format).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Images differ by up to 0.200000 (tolerance 0.1) at pixel (3, 2), PSNR")]
+fn image_compare_failure_message() {
+    let img = gradient(5, 4);
+    let mut changed = img.clone();
+    let offset = 2 * img.desc.stride() as usize + 3 * 3 + 1;
+    changed.bytes[offset] = changed.bytes[offset].wrapping_add(51);
+
+    crate::assert_images_eq!(img, changed, 0.1);
+}
+
+#[test]
+fn png_round_trip() {
+    let png = Image::load_png("../test_resources/rgba-sample-8bit.png").unwrap();
+    assert_eq!(png.desc.color_format(), ColorFormat::RGBA_U8);
+
+    // 16 bit gray with alpha, values that differ in both bytes
+    let desc = ImageDesc::new(7, 3, ColorFormat::GRAY_ALPHA_U16);
+    let mut img = Image::new_empty(desc).unwrap();
+    bytemuck::cast_slice_mut::<u8, u16>(&mut img.bytes)
+        .iter_mut()
+        .enumerate()
+        .for_each(|(index, value)| *value = (index as u16).wrapping_mul(2741));
+
+    img.save_png("../test_output/gray-alpha-16bit.png").unwrap();
+    let loaded = Image::load_png("../test_output/gray-alpha-16bit.png").unwrap();
+    assert_eq!(loaded.desc, img.desc);
+    assert_eq!(loaded.bytes, img.bytes);
+
+    assert!(Image::load_png("../test_resources/rgb-sample-32bit.tiff").is_err());
+    assert!(img.convert(ColorFormat::GRAY_ALPHA_F32).unwrap().save_png("../test_output/float.png").is_err());
+}
+
+#[test]
+fn convert_to_formats() {
+    let rgb = gradient(5, 4);
+    let rgb_stride = rgb.desc.stride() as usize;
+
+    let rgba = rgb.convert_to(ColorFormat::RGBA_U8).unwrap();
+    assert_eq!(rgba.desc.color_format(), ColorFormat::RGBA_U8);
+    let rgba_stride = rgba.desc.stride() as usize;
+    for y in 0..4 {
+        for x in 0..5 {
+            let rgb_pixel = &rgb.bytes[y * rgb_stride + x * 3..][..3];
+            let rgba_pixel = &rgba.bytes[y * rgba_stride + x * 4..][..4];
+            assert_eq!(&rgba_pixel[..3], rgb_pixel);
+            assert_eq!(rgba_pixel[3], 255);
+        }
+    }
+
+    // 8 bit values spread over the full 16 bit range
+    let rgba16 = rgba.convert_to(ColorFormat::RGBA_U16).unwrap();
+    let rgba16_stride = rgba16.desc.stride() as usize;
+    for y in 0..4 {
+        let row8 = &rgba.bytes[y * rgba_stride..][..5 * 4];
+        let row16: &[u16] = bytemuck::cast_slice(&rgba16.bytes[y * rgba16_stride..][..5 * 4 * 2]);
+        for (value8, value16) in row8.iter().zip(row16) {
+            assert_eq!(*value16, *value8 as u16 * 257);
+        }
+    }
+
+    let gray = rgb.convert_to(ColorFormat::GRAY_U8).unwrap();
+    let gray_rgba = gray.convert_to(ColorFormat::RGBA_U8).unwrap();
+    assert_eq!(&gray_rgba.bytes[..4], &[gray.bytes[0], gray.bytes[0], gray.bytes[0], 255]);
+
+    // same channel count and size, only the channel type changes
+    let signed = rgba.convert_to(ColorFormat::RGBA_I8).unwrap();
+    assert_eq!(signed.bytes[4 * 4 + 3], 127);
+
+    assert_eq!(rgb.convert_to(ColorFormat::RGB_U8).unwrap().bytes, rgb.bytes);
+    let err = rgb.convert_to((ChannelCount::Rgb, ChannelSize::_16bit, ChannelType::Float).into()).err().unwrap();
+    assert!(err.to_string().starts_with("Can't convert image from"));
+}
@@ -0,0 +1,17 @@
+struct VertexOutput {
+    @location(0) tex1_coord: vec2<f32>,
+    @location(1) tex2_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var the_sampler: sampler;
+@group(0)
+@binding(1)
+var tex_1: texture_2d<f32>;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(tex_1, the_sampler, vertex.tex1_coord);
+}
@@ -0,0 +1,101 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::image::Image;
+use crate::wgpu::wgpu_context::{Action, TextureWithTransform, WgpuContext};
+
+struct CacheEntry {
+    key: u64,
+    // compared on a key match, equal hashes don't guarantee equal content
+    image: Image,
+    texture: Rc<TextureWithTransform>,
+}
+
+// keeps uploaded textures of images by content, so unchanged inputs are not uploaded again;
+// least recently used textures are evicted once their total size exceeds the capacity,
+// a copy of each cached image is kept on the CPU to tell images with equal hashes apart
+pub(crate) struct ImageCache {
+    capacity_bytes: u64,
+    used_bytes: u64,
+    // least recently used first
+    entries: Vec<CacheEntry>,
+}
+
+impl ImageCache {
+    pub fn new(capacity_bytes: u64) -> Self {
+        Self {
+            capacity_bytes,
+            used_bytes: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get_or_upload(&mut self, context: &WgpuContext, image: &Image) -> Rc<TextureWithTransform> {
+        let key = Self::content_hash(image);
+
+        let position = self.entries
+            .iter()
+            .position(|entry| {
+                entry.key == key
+                    && entry.image.desc == image.desc
+                    && entry.image.bytes == image.bytes
+            });
+        if let Some(position) = position {
+            let entry = self.entries.remove(position);
+            let texture = Rc::clone(&entry.texture);
+            self.entries.push(entry);
+
+            return texture;
+        }
+
+        let texture = context.create_texture(image.desc.clone());
+        context.perform(&[
+            Action::ImgToTex(vec![(image, &texture)])
+        ]);
+
+        let texture = Rc::new(TextureWithTransform::from_texture(texture));
+        self.used_bytes += image.desc.size_in_bytes() as u64;
+        self.entries.push(CacheEntry {
+            key,
+            image: image.clone(),
+            texture: Rc::clone(&texture),
+        });
+        self.evict();
+
+        texture
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes
+    }
+    pub fn set_capacity(&mut self, capacity_bytes: u64) {
+        self.capacity_bytes = capacity_bytes;
+        self.evict();
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used_bytes = 0;
+    }
+
+    // the most recent entry is kept even if it alone exceeds the capacity
+    fn evict(&mut self) {
+        while self.used_bytes > self.capacity_bytes && self.entries.len() > 1 {
+            let entry = self.entries.remove(0);
+            self.used_bytes -= entry.image.desc.size_in_bytes() as u64;
+        }
+    }
+
+    fn content_hash(image: &Image) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        image.desc.hash(&mut hasher);
+        image.bytes.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops::Mul;
 
 use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -73,6 +74,47 @@ impl Transform2D {
     pub fn size_in_bytes(&self) -> usize {
         std::mem::size_of::<Self>()
     }
+
+    pub fn apply_point(&self, p: Vec2) -> Vec2 {
+        let x = self.r0[0] * p.x + self.r1[0] * p.y + self.r2[0];
+        let y = self.r0[1] * p.x + self.r1[1] * p.y + self.r2[1];
+        let w = self.r0[2] * p.x + self.r1[2] * p.y + self.r2[2];
+
+        Vec2::new(x / w, y / w)
+    }
+    // none for degenerate transforms, e.g. scaled to zero along an axis
+    pub fn inverse(&self) -> Option<Transform2D> {
+        let [a, b, c] = self.r0;
+        let [d, e, f] = self.r1;
+        let [g, h, i] = self.r2;
+
+        let det = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+        if !det.is_normal() {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        Some(Transform2D {
+            r0: [
+                (e * i - f * h) * inv_det,
+                (c * h - b * i) * inv_det,
+                (b * f - c * e) * inv_det,
+            ],
+            r1: [
+                (f * g - d * i) * inv_det,
+                (a * i - c * g) * inv_det,
+                (c * d - a * f) * inv_det,
+            ],
+            r2: [
+                (d * h - e * g) * inv_det,
+                (b * g - a * h) * inv_det,
+                (a * e - b * d) * inv_det,
+            ],
+            pad0: 0.0,
+            pad1: 0.0,
+            pad2: 0.0,
+        })
+    }
 }
 
 impl<'a, 'b> Mul<&'b Transform2D> for &'a Transform2D {
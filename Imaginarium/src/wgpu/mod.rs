@@ -5,4 +5,8 @@ mod tests;
 pub mod utils;
 pub mod math;
 pub mod image_texture;
+pub mod push_constants;
+pub mod image_cache;
+#[cfg(feature = "graph")]
+pub mod shader_function;
 
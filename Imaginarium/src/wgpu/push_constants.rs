@@ -0,0 +1,84 @@
+use crate::wgpu::wgpu_context::Shader;
+
+// packs fragment push constants following the WGSL host-shareable layout:
+// every field is aligned to its own alignment and the total size
+// is rounded up to the largest field alignment, as for a WGSL struct
+#[derive(Clone, Default, Debug)]
+pub(crate) struct PushConstants {
+    bytes: Vec<u8>,
+    align: usize,
+}
+
+impl PushConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_f32(&mut self, value: f32) -> &mut Self {
+        self.add(4, &[value])
+    }
+    pub fn add_u32(&mut self, value: u32) -> &mut Self {
+        self.add_aligned(4, bytemuck::bytes_of(&value));
+
+        self
+    }
+    pub fn add_vec2(&mut self, value: [f32; 2]) -> &mut Self {
+        self.add(8, &value)
+    }
+    pub fn add_vec3(&mut self, value: [f32; 3]) -> &mut Self {
+        self.add(16, &value)
+    }
+    pub fn add_vec4(&mut self, value: [f32; 4]) -> &mut Self {
+        self.add(16, &value)
+    }
+    // column major, each column is padded to vec4
+    pub fn add_mat3(&mut self, columns: [[f32; 3]; 3]) -> &mut Self {
+        for column in columns {
+            self.add(16, &column);
+        }
+        self.pad_to(16);
+
+        self
+    }
+
+    // total size, including trailing padding
+    pub fn size(&self) -> u32 {
+        Self::aligned(self.bytes.len(), self.align.max(1)) as u32
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        bytes.resize(self.size() as usize, 0);
+
+        bytes
+    }
+    // fails if the packed size differs from the shader fragment push constant size
+    pub fn build(&self, shader: &Shader) -> anyhow::Result<Vec<u8>> {
+        let expected_size = shader.fragment_push_constant_size();
+        if self.size() != expected_size {
+            return Err(anyhow::anyhow!(
+                "Push constant size mismatch: packed {} bytes, shader expects {}",
+                self.size(),
+                expected_size
+            ));
+        }
+
+        Ok(self.to_bytes())
+    }
+
+    fn add(&mut self, align: usize, values: &[f32]) -> &mut Self {
+        self.add_aligned(align, bytemuck::cast_slice(values));
+
+        self
+    }
+    fn add_aligned(&mut self, align: usize, bytes: &[u8]) {
+        self.pad_to(align);
+        self.bytes.extend_from_slice(bytes);
+    }
+    fn pad_to(&mut self, align: usize) {
+        self.align = self.align.max(align);
+        self.bytes.resize(Self::aligned(self.bytes.len(), align), 0);
+    }
+    fn aligned(size: usize, align: usize) -> usize {
+        size.next_multiple_of(align)
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use graph_lib::data::{DataType, ImageHandle, Value};
+use graph_lib::functions::{Function, FunctionId, InputInfo, OutputInfo};
+use graph_lib::graph::FunctionBehavior;
+use graph_lib::invoke::{InvokeArgs, Invoker};
+use graph_lib::runtime_graph::InvokeContext;
+
+use crate::image::Image;
+use crate::wgpu::push_constants::PushConstants;
+use crate::wgpu::wgpu_context::{Action, Shader, WgpuContext};
+
+// scalar fragment parameter, packed in declaration order after the input textures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ShaderParam {
+    // Float input, f32 in the shader
+    F32,
+    // Int input, u32 in the shader
+    U32,
+}
+
+struct ShaderFunction {
+    shader: Shader,
+    entry_name: String,
+    params: Vec<ShaderParam>,
+}
+
+// runs shaders added with add_shader as graph functions;
+// images are passed as ImageHandle values holding an imaginarium Image
+pub(crate) struct ShaderInvoker {
+    context: Rc<WgpuContext>,
+    all_functions: Vec<FunctionId>,
+    functions: HashMap<FunctionId, ShaderFunction>,
+}
+
+// one image input per shader input texture followed by one input per param, one image output;
+// fails if the params don't pack to the fragment param size of the shader
+pub(crate) fn function_for_shader(
+    shader: &Shader,
+    name: &str,
+    params: &[(&str, ShaderParam)],
+) -> anyhow::Result<Function> {
+    if shader.input_count() == 0 {
+        return Err(anyhow::anyhow!("Shader function {} needs an input image to size its output", name));
+    }
+    pack_params(shader, params.iter().map(|(_, param)| (*param, 0.0)))?;
+
+    let mut function = Function::new(FunctionId::unique());
+    function.name = name.to_string();
+    function.behavior = FunctionBehavior::Passive;
+    function.inputs = (0..shader.input_count())
+        .map(|index| InputInfo {
+            name: format!("image{}", index),
+            data_type: DataType::Image,
+            ..InputInfo::default()
+        })
+        .chain(params.iter().map(|(param_name, param)| {
+            let (data_type, const_value) = match param {
+                ShaderParam::F32 => (DataType::Float, Value::from(0.0)),
+                ShaderParam::U32 => (DataType::Int, Value::from(0)),
+            };
+            InputInfo {
+                name: param_name.to_string(),
+                data_type,
+                const_value: Some(const_value),
+                ..InputInfo::default()
+            }
+        }))
+        .collect();
+    function.outputs = vec![OutputInfo {
+        name: "image".to_string(),
+        data_type: DataType::Image,
+    }];
+
+    Ok(function)
+}
+
+fn pack_params<I>(shader: &Shader, values: I) -> anyhow::Result<Vec<u8>>
+where I: Iterator<Item=(ShaderParam, f64)>
+{
+    let mut push_constants = PushConstants::new();
+    for (param, value) in values {
+        match param {
+            ShaderParam::F32 => push_constants.add_f32(value as f32),
+            ShaderParam::U32 => push_constants.add_u32(value as u32),
+        };
+    }
+
+    push_constants.build(shader)
+}
+
+impl ShaderInvoker {
+    pub(crate) fn new(context: Rc<WgpuContext>) -> ShaderInvoker {
+        ShaderInvoker {
+            context,
+            all_functions: Vec::new(),
+            functions: HashMap::new(),
+        }
+    }
+
+    // returns the generated function, see function_for_shader
+    pub(crate) fn add_shader(
+        &mut self,
+        name: &str,
+        shader: Shader,
+        entry_name: &str,
+        params: &[(&str, ShaderParam)],
+    ) -> anyhow::Result<Function> {
+        let function = function_for_shader(&shader, name, params)?;
+
+        self.functions.insert(function.id(), ShaderFunction {
+            shader,
+            entry_name: entry_name.to_string(),
+            params: params.iter().map(|(_, param)| *param).collect(),
+        });
+        self.all_functions.push(function.id());
+
+        Ok(function)
+    }
+}
+
+impl Invoker for ShaderInvoker {
+    fn all_functions(&self) -> Vec<FunctionId> {
+        self.all_functions.clone()
+    }
+
+    fn invoke(
+        &self,
+        function_id: FunctionId,
+        _ctx: &mut InvokeContext,
+        inputs: &InvokeArgs,
+        outputs: &mut InvokeArgs,
+    ) -> anyhow::Result<()> {
+        let function = self.functions
+            .get(&function_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown shader function {}", function_id))?;
+        let image_count = function.shader.input_count() as usize;
+
+        let images = inputs[..image_count]
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                input
+                    .as_ref()
+                    .and_then(|value| match value {
+                        Value::Image(image) => image.downcast_ref::<Image>(),
+                        _ => None,
+                    })
+                    .ok_or_else(|| anyhow::anyhow!("Shader input {} is not an image", index))
+            })
+            .collect::<anyhow::Result<Vec<&Image>>>()?;
+
+        let param_values = function.params
+            .iter()
+            .zip(inputs[image_count..].iter())
+            .map(|(&param, input)| {
+                let value = match (param, input) {
+                    (ShaderParam::F32, Some(Value::Float(value))) => *value,
+                    (ShaderParam::U32, Some(Value::Int(value))) if u32::try_from(*value).is_ok() => *value as f64,
+                    _ => return Err(anyhow::anyhow!("Invalid shader param {:?}: {:?}", param, input)),
+                };
+                Ok((param, value))
+            })
+            .collect::<anyhow::Result<Vec<(ShaderParam, f64)>>>()?;
+        let params = pack_params(&function.shader, param_values.into_iter())?;
+
+        let input_textures = images
+            .iter()
+            .map(|image| self.context.upload_cached(image))
+            .collect::<Vec<_>>();
+        let output = self.context.create_texture(images[0].desc.clone());
+
+        let input_textures = input_textures.iter().map(Rc::as_ref).collect();
+        let action = if function.shader.uses_uniform_buffer() {
+            Action::RunShaderUbo {
+                shader: &function.shader,
+                shader_entry_name: &function.entry_name,
+                input_textures,
+                output_texture: &output,
+                fragment_uniform: &params,
+                clear_color: None,
+                label: None,
+            }
+        } else {
+            Action::RunShader {
+                shader: &function.shader,
+                shader_entry_name: &function.entry_name,
+                input_textures,
+                output_texture: &output,
+                fragment_push_constant: &params,
+                clear_color: None,
+                label: None,
+            }
+        };
+        self.context.try_perform(&[action])?;
+
+        outputs[0] = Some(Value::Image(ImageHandle::new(output.read(&self.context)?)));
+
+        Ok(())
+    }
+}
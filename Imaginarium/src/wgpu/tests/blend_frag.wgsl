@@ -24,3 +24,8 @@ fn fs_mult_blend(vertex: VertexOutput) -> @location(0) vec4<f32> {
 
     return vec4<f32>(color1 * color2);
 }
+
+@fragment
+fn fs_discard(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    discard;
+}
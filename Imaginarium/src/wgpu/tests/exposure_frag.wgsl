@@ -0,0 +1,26 @@
+struct VertexOutput {
+    @location(0) tex1_coord: vec2<f32>,
+    @location(1) tex2_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct PushConstants {
+    // set for the vertex stage, see common_vert.wgsl
+    tex1_transform: mat3x3<f32>,
+    tex2_transform: mat3x3<f32>,
+    exposure: f32,
+};
+var<push_constant> pc: PushConstants;
+
+@group(0)
+@binding(0)
+var the_sampler: sampler;
+@group(0)
+@binding(1)
+var tex_1: texture_2d<f32>;
+
+@fragment
+fn fs_exposure(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(tex_1, the_sampler, vertex.tex1_coord);
+    return vec4<f32>(color.rgb * pc.exposure, color.a);
+}
@@ -0,0 +1,18 @@
+@group(0)
+@binding(0)
+var input_tex: texture_2d<f32>;
+@group(0)
+@binding(1)
+var output_tex: texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(input_tex);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+
+    let color = textureLoad(input_tex, vec2<i32>(id.xy), 0);
+    textureStore(output_tex, vec2<i32>(id.xy), vec4<f32>(1.0 - color.rgb, color.a));
+}
@@ -1 +1,3 @@
 mod wgpu_tests;
+#[cfg(feature = "graph")]
+mod shader_function_tests;
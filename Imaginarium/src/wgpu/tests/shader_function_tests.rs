@@ -0,0 +1,102 @@
+use std::rc::Rc;
+
+use graph_lib::compute::Compute;
+use graph_lib::data::{DataType, ImageHandle, Value};
+use graph_lib::functions::Function;
+use graph_lib::graph::{Binding, Graph, Node};
+use graph_lib::preprocess::Preprocess;
+use graph_lib::runtime_graph::RuntimeGraph;
+
+use crate::image::Image;
+use crate::wgpu::shader_function::{function_for_shader, ShaderInvoker, ShaderParam};
+use crate::wgpu::wgpu_context::{IDENTITY_SHADER_ENTRY_NAME, WgpuContext};
+
+// runs a graph of a single output node created from the function, returns its output image
+fn run_node(compute: &Compute, function: &Function, inputs: Vec<Value>) -> Image {
+    let mut node = Node::from_function(function);
+    node.is_output = true;
+    for (input, value) in node.inputs.iter_mut().zip(inputs) {
+        input.binding = Binding::Const;
+        input.const_value = Some(value);
+    }
+    let node_id = node.id();
+
+    let mut graph = Graph::default();
+    graph.add_node(node);
+    graph.validate().unwrap();
+
+    let mut runtime_graph = Preprocess::default().run(&graph, &mut RuntimeGraph::default()).unwrap();
+    let mut output: Option<Value> = None;
+    compute.run_with_progress(&graph, &mut runtime_graph, |id, outputs| {
+        if id == node_id {
+            output = outputs[0].clone();
+        }
+    }).unwrap();
+
+    output
+        .unwrap()
+        .as_image()
+        .downcast_ref::<Image>()
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn identity_shader_node() {
+    let context = Rc::new(WgpuContext::new().unwrap());
+    let mut invoker = ShaderInvoker::new(Rc::clone(&context));
+    let shader = context.create_shader_ubo(include_str!("../identity_frag.wgsl"), 1, 0).unwrap();
+    let identity = invoker.add_shader("identity", shader, IDENTITY_SHADER_ENTRY_NAME, &[]).unwrap();
+
+    assert_eq!(identity.name, "identity");
+    assert_eq!(identity.inputs.len(), 1);
+    assert_eq!(identity.inputs[0].data_type, DataType::Image);
+    assert_eq!(identity.outputs.len(), 1);
+    assert_eq!(identity.outputs[0].data_type, DataType::Image);
+
+    let compute = Compute::from(invoker);
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let result = run_node(&compute, &identity, vec![Value::from(ImageHandle::new(img.clone()))]);
+
+    assert_eq!(result.desc, img.desc);
+    assert_eq!(result.bytes, img.bytes);
+}
+
+#[test]
+fn shader_node_params() {
+    let context = Rc::new(WgpuContext::new().unwrap());
+    let mut invoker = ShaderInvoker::new(Rc::clone(&context));
+    let params = [
+        ("r", ShaderParam::F32),
+        ("g", ShaderParam::F32),
+        ("b", ShaderParam::F32),
+        ("a", ShaderParam::F32),
+    ];
+
+    // params have to fill the 16 bytes of the shader uniform
+    let shader = context.create_shader_ubo(include_str!("ubo_frag.wgsl"), 1, 16).unwrap();
+    assert!(function_for_shader(&shader, "fill", &params[..2]).is_err());
+    let fill = invoker.add_shader("fill", shader, "fs_fill", &params).unwrap();
+
+    let input_names = fill.inputs
+        .iter()
+        .map(|input| input.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(input_names, ["image0", "r", "g", "b", "a"]);
+    assert!(fill.inputs[1..].iter().all(|input| input.data_type == DataType::Float));
+    assert_eq!(fill.inputs[1].const_value, Some(Value::from(0.0)));
+
+    let compute = Compute::from(invoker);
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let inputs = vec![
+        Value::from(ImageHandle::new(img.clone())),
+        Value::from(1.0),
+        Value::from(0.0),
+        Value::from(0.0),
+        Value::from(1.0),
+    ];
+    let result = run_node(&compute, &fill, inputs);
+
+    assert_eq!(result.desc, img.desc);
+    assert!(result.bytes.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+}
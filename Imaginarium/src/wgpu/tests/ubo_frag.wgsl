@@ -0,0 +1,17 @@
+struct VertexOutput {
+    @location(0) tex1_coord: vec2<f32>,
+    @location(1) tex2_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+struct Params {
+    color: vec4<f32>,
+};
+@group(1)
+@binding(1)
+var<uniform> params: Params;
+
+@fragment
+fn fs_fill(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return params.color;
+}
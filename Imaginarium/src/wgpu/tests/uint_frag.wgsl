@@ -0,0 +1,19 @@
+struct VertexOutput {
+    @location(0) tex1_coord: vec2<f32>,
+    @location(1) tex2_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var the_sampler: sampler;
+@group(0)
+@binding(1)
+var tex_1: texture_2d<u32>;
+
+// integer textures can't be sampled, texels are loaded at the fragment position
+@fragment
+fn fs_increment(vertex: VertexOutput) -> @location(0) vec4<u32> {
+    let value = textureLoad(tex_1, vec2<i32>(vertex.position.xy), 0);
+    return value + vec4<u32>(1u, 1u, 1u, 1u);
+}
@@ -1,8 +1,14 @@
 use std::cell::RefCell;
+use std::time::Duration;
 
-use crate::image::Image;
+use glam::Vec2;
+
+use crate::color_format::ColorFormat;
+use crate::image::{Image, ImageDesc};
+use crate::wgpu::image_cache::ImageCache;
 use crate::wgpu::math::Transform2D;
-use crate::wgpu::wgpu_context::{Action, TextureWithTransform, WgpuContext};
+use crate::wgpu::push_constants::PushConstants;
+use crate::wgpu::wgpu_context::{Action, ClearColor, DEFAULT_MAX_TIMED_PASSES, device_features, IDENTITY_SHADER_ENTRY_NAME, Shader, TextureWithTransform, WgpuContext};
 
 #[test]
 fn it_works2() {
@@ -36,7 +42,7 @@ fn it_works2() {
         include_str!("blend_frag.wgsl"),
         2,
         0,
-    );
+    ).unwrap();
 
     context.perform(&[
         Action::ImgToTex(vec![(&img1, &tex1.texture), (&img2, &tex2.texture)])
@@ -52,6 +58,8 @@ fn it_works2() {
             input_textures: vec![&tex1, &tex2],
             output_texture: &tex3.texture,
             fragment_push_constant: &[],
+            clear_color: None,
+            label: None,
         },
         Action::RunShader {
             shader: &shader,
@@ -59,6 +67,8 @@ fn it_works2() {
             input_textures: vec![&tex3, &tex2],
             output_texture: &tex1.texture,
             fragment_push_constant: &[],
+            clear_color: None,
+            label: None,
         },
     ]);
 
@@ -74,11 +84,1226 @@ fn it_works2() {
         ]),
     ]);
 
-    context.sync();
+    context.sync().unwrap();
 
     drop(tex1);
     drop(tex3);
 
     img3.save_file("../test_output/compute1.png").unwrap();
     img4.save_file("../test_output/compute2.png").unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn shader_gpu_time() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let tex3 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+
+    let shader = context.create_shader(
+        include_str!("blend_frag.wgsl"),
+        2,
+        0,
+    ).unwrap();
+
+    assert!(context.last_shader_gpu_time().is_none());
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &tex1.texture), (&img, &tex2.texture)]),
+        Action::RunShader {
+            shader: &shader,
+            shader_entry_name: "fs_mult_blend",
+            input_textures: vec![&tex1, &tex2],
+            output_texture: &tex3.texture,
+            fragment_push_constant: &[],
+            clear_color: None,
+            label: None,
+        },
+    ]);
+
+    // None is valid on adapters without timestamp query support
+    if let Some(gpu_time) = context.last_shader_gpu_time() {
+        assert!(gpu_time < Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn batched_readbacks() {
+    let context = WgpuContext::new().unwrap();
+
+    let desc = ImageDesc::new(64, 32, ColorFormat::RGBA_U8);
+    let sources = (0..4u8)
+        .map(|index| {
+            let mut img = Image::new_empty(desc.clone()).unwrap();
+            img.bytes.fill(index * 50 + 10);
+            img
+        })
+        .collect::<Vec<Image>>();
+    let textures = (0..4)
+        .map(|_| context.create_texture(desc.clone()))
+        .collect::<Vec<_>>();
+    context.perform(&[
+        Action::ImgToTex(sources.iter().zip(textures.iter()).collect())
+    ]);
+
+    let mut images = (0..4)
+        .map(|_| Image::new_empty(desc.clone()).unwrap())
+        .collect::<Vec<Image>>();
+    context.perform(&[
+        Action::TexToImg(
+            textures.iter()
+                .zip(images.iter_mut())
+                .map(|(tex, image)| (tex, RefCell::new(image)))
+                .collect()
+        ),
+    ]);
+    context.sync().unwrap();
+
+    // all copies go into one submit, mapped with a single wait
+    assert_eq!(context.peak_inflight_readbacks(), 4);
+    images
+        .iter()
+        .zip(sources.iter())
+        .for_each(|(image, source)| assert_eq!(image.bytes, source.bytes));
+}
+
+#[test]
+fn fragment_push_constant() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let input = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let output = context.create_texture(img.desc.clone());
+
+    let shader = context.create_shader(
+        include_str!("exposure_frag.wgsl"),
+        1,
+        4,
+    ).unwrap();
+    assert_eq!(shader.push_constant_stages(), wgpu::ShaderStages::VERTEX_FRAGMENT);
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+    ]);
+    for exposure in [1.0f32, 0.5] {
+        let mut result = Image::new_empty(img.desc.clone()).unwrap();
+        context.perform(&[
+            Action::RunShader {
+                shader: &shader,
+                shader_entry_name: "fs_exposure",
+                input_textures: vec![&input],
+                output_texture: &output,
+                fragment_push_constant: bytemuck::bytes_of(&exposure),
+                clear_color: None,
+                label: None,
+            },
+            Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+        ]);
+        context.sync().unwrap();
+
+        result.bytes
+            .chunks_exact(4)
+            .zip(img.bytes.chunks_exact(4))
+            .for_each(|(exposed, original)| {
+                for channel in 0..3 {
+                    let expected = original[channel] as f32 * exposure;
+                    assert!((exposed[channel] as f32 - expected).abs() <= 1.0);
+                }
+                assert_eq!(exposed[3], original[3]);
+            });
+    }
+}
+
+#[test]
+fn pass_timings() {
+    let mut context = WgpuContext::new().unwrap();
+    if context.enable_timing(3).is_err() {
+        // adapter without timestamp queries
+        return;
+    }
+    assert!(context.enable_timing(0).is_err());
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let tex3 = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+
+    let shader = context.create_shader(
+        include_str!("blend_frag.wgsl"),
+        2,
+        0,
+    ).unwrap();
+    let compute_shader = context
+        .create_compute_shader(include_str!("invert_comp.wgsl"), 1, 1, 0)
+        .unwrap();
+
+    let blend = |input_textures, output_texture, label| Action::RunShader {
+        shader: &shader,
+        shader_entry_name: "fs_mult_blend",
+        input_textures,
+        output_texture,
+        fragment_push_constant: &[],
+        clear_color: None,
+        label: Some(label),
+    };
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &tex1.texture), (&img, &tex2.texture)]),
+        blend(vec![&tex1, &tex2], &tex3.texture, "first"),
+        blend(vec![&tex3, &tex2], &tex1.texture, "second"),
+        Action::RunCompute {
+            shader: &compute_shader,
+            inputs: vec![&tex1.texture],
+            outputs: vec![&tex3.texture],
+            workgroups: [img.desc.width().div_ceil(8), img.desc.height().div_ceil(8), 1],
+            push_constants: &[],
+            label: Some("invert"),
+        },
+        // over the limit given to enable_timing
+        blend(vec![&tex3, &tex2], &tex1.texture, "fourth"),
+    ]);
+    context.sync().unwrap();
+
+    // the last recorded pass
+    assert!(context.last_shader_gpu_time().is_some());
+
+    let timings = context.last_timings();
+    assert_eq!(
+        timings.iter().map(|(label, _)| label.as_str()).collect::<Vec<&str>>(),
+        vec!["first", "second", "invert"]
+    );
+    assert!(timings.iter().all(|(_, duration)| !duration.is_zero()));
+
+    // drained by the previous call
+    assert!(context.last_timings().is_empty());
+    assert!(context.last_shader_gpu_time().is_none());
+
+    context.enable_timing(DEFAULT_MAX_TIMED_PASSES).unwrap();
+}
+
+#[test]
+fn max_inflight_readbacks() {
+    let mut context = WgpuContext::new().unwrap();
+    context.set_max_inflight_readbacks(Some(2));
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let textures = (0..5)
+        .map(|_| context.create_texture(img.desc.clone()))
+        .collect::<Vec<_>>();
+
+    context.perform(&[
+        Action::ImgToTex(textures.iter().map(|tex| (&img, tex)).collect())
+    ]);
+
+    let mut images = (0..5)
+        .map(|_| Image::new_empty(img.desc.clone()).unwrap())
+        .collect::<Vec<Image>>();
+
+    context.perform(&[
+        Action::TexToImg(
+            textures.iter()
+                .zip(images.iter_mut())
+                .map(|(tex, image)| (tex, RefCell::new(image)))
+                .collect()
+        ),
+    ]);
+    context.sync().unwrap();
+
+    assert_eq!(context.peak_inflight_readbacks(), 2);
+    assert!(images.iter().all(|image| image.bytes == img.bytes));
+}
+
+#[test]
+fn shader_compilation_error() {
+    let context = WgpuContext::new().unwrap();
+
+    let result = context.create_shader(
+        "@fragment fn fs_main( -> @location(0) vec4<f32> { return 1.0 }",
+        0,
+        0,
+    );
+
+    let error = result.err().unwrap().to_string();
+    assert!(error.contains("Shader creation failed"));
+    // the WGSL parser message reaches the shader author
+    assert!(error.contains("expected identifier"), "{}", error);
+
+    let error = context
+        .create_compute_shader("@compute fn cs_main() { let x = ; }", 0, 0, 0)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Shader creation failed"), "{}", error);
+}
+
+#[test]
+fn clear_gray_target() {
+    let context = WgpuContext::new().unwrap();
+
+    let img_desc = ImageDesc::new(64, 64, ColorFormat::GRAY_U8);
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex3 = context.create_texture(img_desc.clone());
+
+    let shader = context.create_shader(
+        include_str!("blend_frag.wgsl"),
+        2,
+        0,
+    ).unwrap();
+
+    // the shader writes nothing, so the target keeps the clear color
+    context.perform(&[
+        Action::RunShader {
+            shader: &shader,
+            shader_entry_name: "fs_discard",
+            input_textures: vec![&tex1, &tex2],
+            output_texture: &tex3,
+            fragment_push_constant: &[],
+            clear_color: Some(ClearColor::Gray(0.2)),
+            label: None,
+        },
+    ]);
+
+    let mut img = Image::new_empty(img_desc.clone()).unwrap();
+    context.perform(&[
+        Action::TexToImg(vec![(&tex3, RefCell::new(&mut img))]),
+    ]);
+    context.sync().unwrap();
+
+    assert!(img.bytes.iter().all(|&byte| byte == 51));
+}
+
+#[test]
+fn lazy_texture_allocation() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let unused = context.create_lazy_texture(img.desc.clone());
+    let used = context.create_lazy_texture(img.desc.clone());
+    assert_eq!(context.texture_allocation_count(), 0);
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, used.get(&context))])
+    ]);
+    context.sync().unwrap();
+
+    assert!(used.is_allocated());
+    assert!(!unused.is_allocated());
+    assert_eq!(context.texture_allocation_count(), 1);
+
+    let _ = used.get(&context);
+    assert_eq!(context.texture_allocation_count(), 1);
+}
+
+#[test]
+fn run_shader_ubo() {
+    let context = WgpuContext::new().unwrap();
+
+    let img_desc = ImageDesc::new(64, 64, ColorFormat::RGBA_U8);
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex3 = context.create_texture(img_desc.clone());
+
+    let color: [f32; 4] = [0.2, 0.4, 0.6, 1.0];
+    let shader = context.create_shader_ubo(
+        include_str!("ubo_frag.wgsl"),
+        2,
+        std::mem::size_of_val(&color) as u32,
+    ).unwrap();
+
+    context.perform(&[
+        Action::RunShaderUbo {
+            shader: &shader,
+            shader_entry_name: "fs_fill",
+            input_textures: vec![&tex1, &tex2],
+            output_texture: &tex3,
+            fragment_uniform: bytemuck::cast_slice(&color),
+            clear_color: None,
+            label: None,
+        },
+    ]);
+
+    let mut img = Image::new_empty(img_desc.clone()).unwrap();
+    context.perform(&[
+        Action::TexToImg(vec![(&tex3, RefCell::new(&mut img))]),
+    ]);
+    context.sync().unwrap();
+
+    assert!(img.bytes.chunks(4).all(|pixel| pixel == [51, 102, 153, 255]));
+}
+
+#[test]
+fn identity_shader() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let input = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let output = context.create_texture(img.desc.clone());
+
+    // created once per context
+    assert!(std::ptr::eq(context.identity_shader(), context.identity_shader()));
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+        Action::RunShaderUbo {
+            shader: context.identity_shader(),
+            shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+            input_textures: vec![&input],
+            output_texture: &output,
+            fragment_uniform: &[],
+            clear_color: None,
+            label: None,
+        },
+    ]);
+
+    let mut result = Image::new_empty(img.desc.clone()).unwrap();
+    context.perform(&[
+        Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+    ]);
+    context.sync().unwrap();
+
+    assert_eq!(result.bytes, img.bytes);
+}
+
+#[test]
+fn shader_output_formats() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    assert_eq!(img.desc.color_format(), ColorFormat::RGBA_U8);
+    let input = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+    ]);
+
+    // the same shader renders into targets of different formats, each gets its own pipeline
+    let mut results = Vec::new();
+    for color_format in [ColorFormat::GRAY_U8, ColorFormat::RGBA_F16, ColorFormat::RGBA_F32] {
+        let desc = ImageDesc::new(img.desc.width(), img.desc.height(), color_format);
+        let output = context.create_texture(desc.clone());
+        let mut result = Image::new_empty(desc).unwrap();
+        context.perform(&[
+            Action::RunShaderUbo {
+                shader: context.identity_shader(),
+                shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+                input_textures: vec![&input],
+                output_texture: &output,
+                fragment_uniform: &[],
+                clear_color: None,
+                label: None,
+            },
+            Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+        ]);
+        context.sync().unwrap();
+        results.push(result);
+    }
+
+    // gray keeps the red channel
+    let gray = &results[0];
+    assert_eq!(gray.bytes.len(), img.bytes.len() / 4);
+    gray.bytes
+        .iter()
+        .zip(img.bytes.chunks_exact(4))
+        .for_each(|(gray, rgba)| assert_eq!(*gray, rgba[0]));
+
+    // half floats keep 11 significant bits
+    let half: &[u16] = bytemuck::cast_slice(&results[1].bytes);
+    assert_eq!(half.len(), img.bytes.len());
+    half
+        .iter()
+        .zip(img.bytes.iter())
+        .for_each(|(&half, byte)| assert!((f16_to_f32(half) - *byte as f32 / 255.0).abs() < 1e-3));
+
+    let float: &[f32] = bytemuck::cast_slice(&results[2].bytes);
+    assert_eq!(float.len(), img.bytes.len());
+    float
+        .iter()
+        .zip(img.bytes.iter())
+        .for_each(|(float, byte)| assert!((float - *byte as f32 / 255.0).abs() < 1e-6));
+}
+
+// finite half precision values only
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32 / 1024.0;
+
+    if exponent == 0 {
+        sign * mantissa * 2f32.powi(-14)
+    } else {
+        sign * (1.0 + mantissa) * 2f32.powi(exponent - 15)
+    }
+}
+
+#[test]
+fn pipeline_per_output_format() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let input = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let rgba_output = context.create_texture(img.desc.clone());
+    let gray_output = context.create_texture(
+        ImageDesc::new(img.desc.width(), img.desc.height(), ColorFormat::GRAY_U8)
+    );
+
+    let shader = context.create_shader_ubo(include_str!("../identity_frag.wgsl"), 1, 0).unwrap();
+    assert_eq!(shader.cached_pipeline_count(), 0);
+
+    let run = |output| Action::RunShaderUbo {
+        shader: &shader,
+        shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+        input_textures: vec![&input],
+        output_texture: output,
+        fragment_uniform: &[],
+        clear_color: None,
+        label: None,
+    };
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+        run(&rgba_output),
+        run(&gray_output),
+        run(&rgba_output),
+    ]);
+    context.sync().unwrap();
+
+    // the module is compiled once, the pipeline is built once per format
+    assert_eq!(shader.cached_pipeline_count(), 2);
+}
+
+#[test]
+fn texture_new_image() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let texture = context.create_texture(img.desc.clone());
+    assert_eq!(texture.image_desc(), img.desc);
+
+    let mut result = texture.new_image();
+    assert_eq!(result.desc, img.desc);
+    assert!(result.bytes.iter().all(|&byte| byte == 0));
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &texture)]),
+        Action::TexToImg(vec![(&texture, RefCell::new(&mut result))]),
+    ]);
+    context.sync().unwrap();
+
+    assert_eq!(result.bytes, img.bytes);
+}
+
+#[test]
+fn push_constants_layout() {
+    #[repr(C)]
+    #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Reference {
+        scale: f32,
+        pad0: f32,
+        offset: [f32; 2],
+        transform: [[f32; 4]; 3],
+        tint: [f32; 3],
+        opacity: f32,
+        count: u32,
+        pad1: [u32; 3],
+    }
+    let reference = Reference {
+        scale: 0.5,
+        pad0: 0.0,
+        offset: [1.0, 2.0],
+        transform: [
+            [1.0, 2.0, 3.0, 0.0],
+            [4.0, 5.0, 6.0, 0.0],
+            [7.0, 8.0, 9.0, 0.0],
+        ],
+        tint: [0.1, 0.2, 0.3],
+        opacity: 0.75,
+        count: 3,
+        pad1: [0; 3],
+    };
+
+    let mut push_constants = PushConstants::new();
+    push_constants
+        .add_f32(0.5)
+        .add_vec2([1.0, 2.0])
+        .add_mat3([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]])
+        .add_vec3([0.1, 0.2, 0.3])
+        .add_f32(0.75)
+        .add_u32(3);
+
+    assert_eq!(push_constants.size() as usize, std::mem::size_of::<Reference>());
+    assert_eq!(push_constants.to_bytes(), bytemuck::bytes_of(&reference));
+}
+
+#[test]
+fn image_cache_reuses_uploads() {
+    let context = WgpuContext::new().unwrap();
+
+    let img1 = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let mut img2 = img1.clone();
+    img2.bytes[0] = img2.bytes[0].wrapping_add(1);
+    let size = img1.desc.size_in_bytes() as u64;
+
+    let mut cache = ImageCache::new(size);
+    let tex1 = cache.get_or_upload(&context, &img1);
+    let tex1_again = cache.get_or_upload(&context, &img1.clone());
+    assert!(std::rc::Rc::ptr_eq(&tex1, &tex1_again));
+    assert_eq!(context.texture_write_count(), 1);
+    assert_eq!(context.texture_allocation_count(), 1);
+
+    // different content, the least recently used image is evicted to fit the capacity
+    let _tex2 = cache.get_or_upload(&context, &img2);
+    assert_eq!(context.texture_write_count(), 2);
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.used_bytes(), size);
+
+    let _ = cache.get_or_upload(&context, &img1);
+    assert_eq!(context.texture_write_count(), 3);
+}
+
+#[test]
+fn cached_shader_input() {
+    let context = WgpuContext::new().unwrap();
+
+    let background = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let output = context.create_texture(background.desc.clone());
+    let mut result = background.clone();
+    result.bytes.fill(0);
+
+    // the same background on every run is uploaded once
+    for _ in 0..3 {
+        let input = context.upload_cached(&background.clone());
+        context.perform(&[
+            Action::RunShaderUbo {
+                shader: context.identity_shader(),
+                shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+                input_textures: vec![&*input],
+                output_texture: &output,
+                fragment_uniform: &[],
+                clear_color: None,
+                label: None,
+            },
+            Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+        ]);
+        assert_eq!(result.bytes, background.bytes);
+    }
+    assert_eq!(context.texture_write_count(), 1);
+
+    let mut changed = background.clone();
+    changed.bytes[0] = changed.bytes[0].wrapping_add(1);
+    let input = context.upload_cached(&changed);
+    assert!(!std::rc::Rc::ptr_eq(&input, &context.upload_cached(&background)));
+    assert_eq!(context.texture_write_count(), 2);
+}
+
+#[test]
+fn uncaptured_error_handling() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::new_empty(ImageDesc::new(64, 64, ColorFormat::RGBA_U8)).unwrap();
+    let tex = context.create_texture(img.desc.clone());
+    // less data than the texture copy requires
+    let mut truncated = img.clone();
+    truncated.bytes.truncate(16);
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&truncated, &tex)])
+    ]);
+    assert!(context.sync().is_err());
+    // errors are reported once
+    assert!(context.sync().is_ok());
+
+    let error_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    {
+        let error_count = error_count.clone();
+        context.set_error_handler(Box::new(move |_| {
+            error_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }));
+    }
+
+    context.perform(&[
+        Action::ImgToTex(vec![(&truncated, &tex)])
+    ]);
+    context.sync().unwrap();
+    assert_eq!(error_count.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[test]
+fn bind_group_reuse() {
+    let context = WgpuContext::new().unwrap();
+
+    let img_desc = ImageDesc::new(16, 16, ColorFormat::RGBA_U8);
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex3 = context.create_texture(img_desc.clone());
+
+    let shader = context.create_shader(
+        include_str!("blend_frag.wgsl"),
+        2,
+        0,
+    ).unwrap();
+
+    for _ in 0..100 {
+        context.perform(&[
+            Action::RunShader {
+                shader: &shader,
+                shader_entry_name: "fs_mult_blend",
+                input_textures: vec![&tex1, &tex2],
+                output_texture: &tex3,
+                fragment_push_constant: &[],
+                clear_color: None,
+                label: None,
+            },
+        ]);
+    }
+    context.sync().unwrap();
+    assert_eq!(context.bind_group_creation_count(), 1);
+
+    // a different input set, then the original one again after one input is replaced
+    let tex4 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    for input_textures in [vec![&tex1, &tex4], vec![&tex1, &tex2]] {
+        context.perform(&[
+            Action::RunShader {
+                shader: &shader,
+                shader_entry_name: "fs_mult_blend",
+                input_textures,
+                output_texture: &tex3,
+                fragment_push_constant: &[],
+                clear_color: None,
+                label: None,
+            },
+        ]);
+    }
+    context.sync().unwrap();
+    assert_eq!(context.bind_group_creation_count(), 2);
+    assert_eq!(context.cached_bind_group_count(), 2);
+
+    // bind groups of dropped textures are released by sync without further draws
+    drop(tex4);
+    context.sync().unwrap();
+    assert_eq!(context.cached_bind_group_count(), 1);
+}
+
+#[test]
+fn vram_usage() {
+    let context = WgpuContext::new().unwrap();
+    assert_eq!(context.estimated_vram_usage(), 0);
+
+    let desc1 = ImageDesc::new(64, 32, ColorFormat::RGBA_U8);
+    let desc2 = ImageDesc::new(16, 16, ColorFormat::GRAY_F32);
+    let tex1 = context.create_texture(desc1.clone());
+    let tex2 = context.create_texture(desc2.clone());
+    let tex3 = context.create_texture(desc1.clone());
+
+    let expected = 2 * desc1.size_in_bytes() as u64 + desc2.size_in_bytes() as u64;
+    assert_eq!(context.estimated_vram_usage(), expected);
+
+    drop(tex2);
+    assert_eq!(context.estimated_vram_usage(), 2 * desc1.size_in_bytes() as u64);
+
+    drop(tex1);
+    drop(tex3);
+    assert_eq!(context.estimated_vram_usage(), 0);
+}
+
+#[test]
+fn transform_inverse() {
+    let mut transform = Transform2D::default();
+    transform
+        .aspect(16, 9)
+        .scale(2.0, 0.5)
+        .rotate(0.7)
+        .translate(0.3, -1.2)
+        .center();
+    let inverse = transform.inverse().unwrap();
+
+    for p in [Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(-3.5, 12.25)] {
+        let round_trip = transform.apply_point(inverse.apply_point(p));
+        assert!((round_trip - p).length() < 1e-4);
+        let round_trip = inverse.apply_point(transform.apply_point(p));
+        assert!((round_trip - p).length() < 1e-4);
+    }
+
+    let identity = &transform * &inverse;
+    let p = Vec2::new(2.0, 3.0);
+    assert!((identity.apply_point(p) - p).length() < 1e-4);
+
+    let mut degenerate = Transform2D::default();
+    degenerate.scale(0.0, 1.0);
+    assert!(degenerate.inverse().is_none());
+}
+
+#[test]
+fn shader_hot_reload() {
+    let context = WgpuContext::new().unwrap();
+
+    let shader_source = |gray: &str| format!(
+        "{}\n@fragment\nfn fs_fill(vertex: VertexOutput) -> @location(0) vec4<f32> {{ return vec4<f32>({}); }}\n",
+        include_str!("blend_frag.wgsl"),
+        gray,
+    );
+    let path = std::env::temp_dir().join(format!("shader_hot_reload_{}.wgsl", std::process::id()));
+    std::fs::write(&path, shader_source("0.2")).unwrap();
+
+    let img_desc = ImageDesc::new(16, 16, ColorFormat::GRAY_U8);
+    let tex1 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex2 = TextureWithTransform::from_texture(context.create_texture(img_desc.clone()));
+    let tex3 = context.create_texture(img_desc.clone());
+
+    let shader = context.create_shader_from_file(&path, 2, 0).unwrap();
+    let render = || {
+        context.perform(&[
+            Action::RunShader {
+                shader: &shader,
+                shader_entry_name: "fs_fill",
+                input_textures: vec![&tex1, &tex2],
+                output_texture: &tex3,
+                fragment_push_constant: &[],
+                clear_color: None,
+                label: None,
+            },
+        ]);
+        let mut img = Image::new_empty(img_desc.clone()).unwrap();
+        context.perform(&[
+            Action::TexToImg(vec![(&tex3, RefCell::new(&mut img))]),
+        ]);
+        context.sync().unwrap();
+        img.bytes[0]
+    };
+
+    assert_eq!(render(), 51);
+    assert_eq!(context.reload_shaders().unwrap(), 0);
+
+    std::fs::write(&path, shader_source("0.6")).unwrap();
+    assert_eq!(context.reload_shaders().unwrap(), 1);
+    assert_eq!(render(), 153);
+
+    // a broken edit is reported and the last working module stays in use
+    std::fs::write(&path, "@fragment fn fs_fill( {").unwrap();
+    assert!(context.reload_shaders().is_err());
+    assert_eq!(render(), 153);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn texture_save_file() {
+    let context = WgpuContext::new().unwrap();
+
+    let img_desc = ImageDesc::new(16, 16, ColorFormat::RGBA_U8);
+    let mut img = Image::new_empty(img_desc.clone()).unwrap();
+    img.bytes
+        .chunks_exact_mut(4)
+        .for_each(|pixel| pixel.copy_from_slice(&[200, 100, 50, 255]));
+    let texture = context.create_texture(img_desc);
+    context.perform(&[Action::ImgToTex(vec![(&img, &texture)])]);
+
+    texture.save_file(&context, "../test_output/texture_save_file.png").unwrap();
+    let png = Image::read_file("../test_output/texture_save_file.png").unwrap();
+    assert_eq!(png.desc, img.desc);
+    assert_eq!(png.bytes, img.bytes);
+
+    // jpeg has no alpha channel
+    texture.save_file(&context, "../test_output/texture_save_file.jpg").unwrap();
+    let jpg = Image::read_file("../test_output/texture_save_file.jpg").unwrap();
+    assert_eq!(jpg.desc.color_format(), ColorFormat::RGB_U8);
+    jpg.bytes
+        .chunks_exact(3)
+        .for_each(|pixel| {
+            assert!(pixel.iter().zip([200u8, 100, 50]).all(|(&a, b)| a.abs_diff(b) <= 2));
+        });
+}
+
+#[test]
+fn batch_textures() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let input = TextureWithTransform::from_texture(context.create_texture(img.desc.clone()));
+    let output = context.create_texture(img.desc.clone());
+
+    let actions = [
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+        Action::RunShaderUbo {
+            shader: context.identity_shader(),
+            shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+            input_textures: vec![&input],
+            output_texture: &output,
+            fragment_uniform: &[],
+            clear_color: None,
+            label: None,
+        },
+    ];
+
+    let textures = actions[1].textures();
+    assert_eq!(textures.len(), 2);
+    assert!(std::ptr::eq(textures[0], &input.texture));
+    assert!(std::ptr::eq(textures[1], &output));
+
+    // the uploaded texture is shared by both actions and listed once
+    let textures = WgpuContext::batch_textures(&actions);
+    assert_eq!(textures.len(), 2);
+    assert!(std::ptr::eq(textures[0], &input.texture));
+    assert!(std::ptr::eq(textures[1], &output));
+}
+
+#[test]
+fn try_perform_rejects_integer_inputs() {
+    let context = WgpuContext::new().unwrap();
+
+    let desc = ImageDesc::new(16, 16, ColorFormat::GRAY_U8);
+    let input = TextureWithTransform::from_texture(context.create_texture(desc.clone()));
+    let integer_input = TextureWithTransform::from_texture(
+        context.create_texture(ImageDesc::new(16, 16, ColorFormat::GRAY_U32))
+    );
+    let output = context.create_texture(desc);
+
+    let run_identity = |input: &TextureWithTransform| {
+        context.try_perform(&[
+            Action::RunShaderUbo {
+                shader: context.identity_shader(),
+                shader_entry_name: IDENTITY_SHADER_ENTRY_NAME,
+                input_textures: vec![input],
+                output_texture: &output,
+                fragment_uniform: &[],
+                clear_color: None,
+                label: None,
+            },
+        ])
+    };
+
+    run_identity(&input).unwrap();
+    let error = run_identity(&integer_input).err().unwrap().to_string();
+    assert!(error.contains("can't be sampled as float"), "{}", error);
+
+    context.sync().unwrap();
+}
+
+#[test]
+fn uint_texture_shader() {
+    let context = WgpuContext::new().unwrap();
+
+    let desc = ImageDesc::new(8, 8, ColorFormat::GRAY_U32);
+    let values: Vec<u32> = (0..64u32).map(|index| index * 1_000_003).collect();
+    let img = Image::new_with_data(desc.clone(), bytemuck::cast_slice(&values).to_vec()).unwrap();
+    let input = TextureWithTransform::from_texture(context.create_texture(desc.clone()));
+    let output = context.create_texture(desc.clone());
+
+    let shader = context.create_shader_ubo_with_input_format(
+        include_str!("uint_frag.wgsl"),
+        1,
+        0,
+        wgpu::TextureFormat::R32Uint,
+    ).unwrap();
+
+    context.try_perform(&[
+        Action::ImgToTex(vec![(&img, &input.texture)]),
+        Action::RunShaderUbo {
+            shader: &shader,
+            shader_entry_name: "fs_increment",
+            input_textures: vec![&input],
+            output_texture: &output,
+            fragment_uniform: &[],
+            clear_color: None,
+            label: None,
+        },
+    ]).unwrap();
+
+    let mut result = Image::new_empty(desc).unwrap();
+    context.perform(&[
+        Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+    ]);
+    context.sync().unwrap();
+
+    let result: &[u32] = bytemuck::cast_slice(&result.bytes);
+    let expected: Vec<u32> = values.iter().map(|value| value + 1).collect();
+    assert_eq!(result, expected.as_slice());
+
+    // float inputs don't fit the integer shader
+    let float_input = TextureWithTransform::from_texture(
+        context.create_texture(ImageDesc::new(8, 8, ColorFormat::GRAY_U8))
+    );
+    let error = context.try_perform(&[
+        Action::RunShaderUbo {
+            shader: &shader,
+            shader_entry_name: "fs_increment",
+            input_textures: vec![&float_input],
+            output_texture: &output,
+            fragment_uniform: &[],
+            clear_color: None,
+            label: None,
+        },
+    ]).err().unwrap().to_string();
+    assert!(error.contains("can't be sampled as uint"), "{}", error);
+}
+
+#[test]
+fn uint8_texture_shader() {
+    let context = WgpuContext::new().unwrap();
+
+    let desc = ImageDesc::new(8, 8, ColorFormat::GRAY_U8);
+    let values: Vec<u8> = (0..64u8).map(|index| index * 3).collect();
+    let img = Image::new_with_data(desc.clone(), values.clone()).unwrap();
+    let input = TextureWithTransform::from_texture(context.create_integer_texture(desc.clone()).unwrap());
+    let output = context.create_integer_texture(desc.clone()).unwrap();
+    assert_eq!(input.texture.format, wgpu::TextureFormat::R8Uint);
+    assert!(context.create_integer_texture(ImageDesc::new(8, 8, ColorFormat::GRAY_F32)).is_err());
+
+    let run = |shader: &Shader, input: &TextureWithTransform| {
+        context.try_perform(&[
+            Action::ImgToTex(vec![(&img, &input.texture)]),
+            Action::RunShaderUbo {
+                shader,
+                shader_entry_name: "fs_increment",
+                input_textures: vec![input],
+                output_texture: &output,
+                fragment_uniform: &[],
+                clear_color: None,
+                label: None,
+            },
+        ])
+    };
+    let read_output = || {
+        let mut result = Image::new_empty(desc.clone()).unwrap();
+        context.perform(&[
+            Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+        ]);
+        context.sync().unwrap();
+        result.bytes
+    };
+    let expected: Vec<u8> = values.iter().map(|value| value + 1).collect();
+
+    let shader = context.create_shader_ubo_with_input_format(
+        include_str!("uint_frag.wgsl"),
+        1,
+        0,
+        wgpu::TextureFormat::R8Uint,
+    ).unwrap();
+    run(&shader, &input).unwrap();
+    assert_eq!(read_output(), expected);
+
+    // the same bytes in a normalized texture are sampled as floats
+    let normalized_input = TextureWithTransform::from_texture(context.create_texture(desc.clone()));
+    let error = run(&shader, &normalized_input).err().unwrap().to_string();
+    assert!(error.contains("can't be sampled as uint"), "{}", error);
+
+    // push constants are optional on some adapters
+    if let Ok(shader) = context.create_shader_with_input_format(
+        include_str!("uint_frag.wgsl"),
+        1,
+        0,
+        wgpu::TextureFormat::R8Uint,
+    ) {
+        context.try_perform(&[
+            Action::RunShader {
+                shader: &shader,
+                shader_entry_name: "fs_increment",
+                input_textures: vec![&input],
+                output_texture: &output,
+                fragment_push_constant: &[],
+                clear_color: None,
+                label: None,
+            },
+        ]).unwrap();
+        assert_eq!(read_output(), expected);
+    }
+}
+
+#[test]
+fn shader_bindings() {
+    let context = WgpuContext::new().unwrap();
+
+    let shader = context.create_shader_ubo(include_str!("ubo_frag.wgsl"), 1, 16).unwrap();
+    assert_eq!(shader.input_count(), 1);
+    assert_eq!(shader.fragment_push_constant_size(), 16);
+    assert_eq!(shader.push_constant_size(), 0);
+    assert_eq!(shader.push_constant_stages(), wgpu::ShaderStages::NONE);
+
+    // push constants are optional on some adapters
+    if let Ok(shader) = context.create_shader(include_str!("blend_frag.wgsl"), 2, 0) {
+        let transforms_size = 2 * std::mem::size_of::<Transform2D>() as u32;
+        assert_eq!(shader.input_count(), 2);
+        assert_eq!(shader.fragment_push_constant_size(), 0);
+        assert_eq!(shader.push_constant_size(), transforms_size);
+        assert_eq!(shader.push_constant_stages(), wgpu::ShaderStages::VERTEX);
+    }
+}
+
+#[test]
+fn readback_unaligned_stride() {
+    let context = WgpuContext::new().unwrap();
+
+    // 400 and 101 bytes per row, neither is a multiple of 256
+    for desc in [
+        ImageDesc::new(100, 100, ColorFormat::RGBA_U8),
+        ImageDesc::new(101, 7, ColorFormat::GRAY_U8),
+    ] {
+        let mut img = Image::new_empty(desc.clone()).unwrap();
+        let row_size = (desc.width() * desc.color_format().byte_count()) as usize;
+        img.bytes
+            .chunks_exact_mut(desc.stride() as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                row[..row_size]
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(x, byte)| *byte = (x * 7 + y * 13) as u8);
+            });
+        let texture = context.create_texture(desc.clone());
+
+        let mut result = Image::new_empty(desc.clone()).unwrap();
+        context.perform(&[
+            Action::ImgToTex(vec![(&img, &texture)]),
+            Action::TexToImg(vec![(&texture, RefCell::new(&mut result))]),
+        ]);
+        context.sync().unwrap();
+
+        assert_eq!(result.bytes.len(), img.bytes.len());
+        assert_eq!(result.bytes, img.bytes);
+    }
+}
+
+#[test]
+fn readback_ring() {
+    let context = WgpuContext::new().unwrap();
+
+    const CAPACITY: usize = 3;
+    // 101 bytes per row, padded in the staging buffers
+    let desc = ImageDesc::new(101, 7, ColorFormat::GRAY_U8);
+    let texture = context.create_texture(desc.clone());
+    let (mut ring, receiver) = context.create_readback_ring(desc.clone(), CAPACITY);
+
+    for frame in 0..10u8 {
+        let mut img = Image::new_empty(desc.clone()).unwrap();
+        img.bytes.fill(frame);
+        context.perform(&[
+            Action::ImgToTex(vec![(&img, &texture)]),
+        ]);
+        ring.push(&context, &texture);
+    }
+    ring.flush(&context);
+    context.sync().unwrap();
+
+    let frames = receiver.try_iter().collect::<Vec<Image>>();
+    assert_eq!(frames.len(), 10);
+    for (index, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.desc, desc);
+        assert!(frame.bytes.iter().all(|&byte| byte == index as u8));
+    }
+    assert!(ring.buffer_allocation_count() <= CAPACITY);
+}
+
+#[test]
+fn compute_shader_invert() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::read_file("../test_resources/rainbow256x256.png").unwrap();
+    let input = context.create_texture(img.desc.clone());
+    let output = context.create_texture(img.desc.clone());
+
+    let shader = context
+        .create_compute_shader(include_str!("invert_comp.wgsl"), 1, 1, 0)
+        .unwrap();
+    assert_eq!(shader.output_count(), 1);
+
+    let mut result = Image::new_empty(img.desc.clone()).unwrap();
+    context.try_perform(&[
+        Action::ImgToTex(vec![(&img, &input)]),
+        Action::RunCompute {
+            shader: &shader,
+            inputs: vec![&input],
+            outputs: vec![&output],
+            // 8x8 workgroups
+            workgroups: [img.desc.width().div_ceil(8), img.desc.height().div_ceil(8), 1],
+            push_constants: &[],
+            label: None,
+        },
+        Action::TexToImg(vec![(&output, RefCell::new(&mut result))]),
+    ]).unwrap();
+    context.sync().unwrap();
+
+    result.bytes
+        .chunks_exact(4)
+        .zip(img.bytes.chunks_exact(4))
+        .for_each(|(inverted, original)| {
+            for channel in 0..3 {
+                assert!((inverted[channel] as i32 - (255 - original[channel]) as i32).abs() <= 1);
+            }
+            assert_eq!(inverted[3], original[3]);
+        });
+
+    // inputs and outputs are bound separately, swapped counts are rejected
+    assert!(context.try_perform(&[
+        Action::RunCompute {
+            shader: &shader,
+            inputs: vec![&input, &output],
+            outputs: vec![],
+            workgroups: [1, 1, 1],
+            push_constants: &[],
+            label: None,
+        },
+    ]).is_err());
+}
+
+// doesn't need a GPU
+#[test]
+fn missing_device_features() {
+    let adapter_features = wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER | wgpu::Features::TIMESTAMP_QUERY;
+
+    // optional features are used when the adapter has them
+    let features = device_features(adapter_features, wgpu::Features::empty()).unwrap();
+    assert_eq!(features, adapter_features);
+
+    let err = device_features(adapter_features, wgpu::Features::PUSH_CONSTANTS).err().unwrap();
+    assert!(err.to_string().contains("PUSH_CONSTANTS"));
+
+    let err = device_features(wgpu::Features::empty(), wgpu::Features::empty()).err().unwrap();
+    assert!(err.to_string().contains("ADDRESS_MODE_CLAMP_TO_BORDER"));
+
+    // creating the context fails with an error instead of panicking
+    let result = std::panic::catch_unwind(|| {
+        WgpuContext::with_required_features(wgpu::Features::PUSH_CONSTANTS).map(|_| ())
+    });
+    assert!(result.is_ok());
+}
+
+#[test]
+fn png_texture_round_trip() {
+    let context = WgpuContext::new().unwrap();
+
+    let img = Image::load_png("../test_resources/rgba-sample-8bit.png").unwrap();
+    let texture = context.create_texture(img.desc.clone());
+    let mut result = texture.new_image();
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &texture)]),
+        Action::TexToImg(vec![(&texture, RefCell::new(&mut result))]),
+    ]);
+    context.sync().unwrap();
+
+    result.save_png("../test_output/png_texture_round_trip.png").unwrap();
+    let saved = Image::load_png("../test_output/png_texture_round_trip.png").unwrap();
+    assert_eq!(saved.desc, img.desc);
+    assert_eq!(saved.bytes, img.bytes);
+}
+
+#[tokio::test]
+async fn texture_read_async() {
+    let context = WgpuContext::new().unwrap();
+
+    // 404 bytes per row, padded in the staging buffer
+    let desc = ImageDesc::new(101, 7, ColorFormat::RGBA_U8);
+    let mut img = Image::new_empty(desc.clone()).unwrap();
+    img.bytes
+        .iter_mut()
+        .enumerate()
+        .for_each(|(index, byte)| *byte = index as u8);
+    let texture = context.create_texture(desc.clone());
+    context.perform(&[
+        Action::ImgToTex(vec![(&img, &texture)]),
+    ]);
+
+    let read = texture.read_async(&context).await.unwrap();
+    assert_eq!(read.desc, desc);
+    assert_eq!(read.bytes, img.bytes);
+
+    assert_eq!(texture.read(&context).unwrap().bytes, img.bytes);
+}
@@ -5,9 +5,20 @@ impl From<wgpu::TextureFormat> for ColorFormat {
         match value {
             wgpu::TextureFormat::R8Unorm => ColorFormat::GRAY_U8,
             wgpu::TextureFormat::R8Snorm => ColorFormat::GRAY_I8,
+            wgpu::TextureFormat::R8Uint => ColorFormat::GRAY_U8,
+            wgpu::TextureFormat::R8Sint => ColorFormat::GRAY_I8,
+            wgpu::TextureFormat::R32Uint => ColorFormat::GRAY_U32,
+            wgpu::TextureFormat::R32Sint => ColorFormat::GRAY_I32,
+            wgpu::TextureFormat::R32Float => ColorFormat::GRAY_F32,
 
             wgpu::TextureFormat::Rgba8Unorm => ColorFormat::RGBA_U8,
             wgpu::TextureFormat::Rgba8Snorm => ColorFormat::RGBA_I8,
+            wgpu::TextureFormat::Rgba8Uint => ColorFormat::RGBA_U8,
+            wgpu::TextureFormat::Rgba8Sint => ColorFormat::RGBA_I8,
+            wgpu::TextureFormat::Rgba32Uint => ColorFormat::RGBA_U32,
+            wgpu::TextureFormat::Rgba32Sint => ColorFormat::RGBA_I32,
+            wgpu::TextureFormat::Rgba16Float => ColorFormat::RGBA_F16,
+            wgpu::TextureFormat::Rgba32Float => ColorFormat::RGBA_F32,
 
             _ => panic!("Not implemented texture format: {:?}", value),
         }
@@ -20,9 +31,17 @@ impl From<&ColorFormat> for wgpu::TextureFormat {
         match value {
             &ColorFormat::GRAY_U8 => wgpu::TextureFormat::R8Unorm,
             &ColorFormat::GRAY_I8 => wgpu::TextureFormat::R8Snorm,
+            // integer formats are not normalized, shaders read them with textureLoad
+            &ColorFormat::GRAY_U32 => wgpu::TextureFormat::R32Uint,
+            &ColorFormat::GRAY_I32 => wgpu::TextureFormat::R32Sint,
+            &ColorFormat::GRAY_F32 => wgpu::TextureFormat::R32Float,
 
             &ColorFormat::RGBA_U8 => wgpu::TextureFormat::Rgba8Unorm,
             &ColorFormat::RGBA_I8 => wgpu::TextureFormat::Rgba8Snorm,
+            &ColorFormat::RGBA_U32 => wgpu::TextureFormat::Rgba32Uint,
+            &ColorFormat::RGBA_I32 => wgpu::TextureFormat::Rgba32Sint,
+            &ColorFormat::RGBA_F16 => wgpu::TextureFormat::Rgba16Float,
+            &ColorFormat::RGBA_F32 => wgpu::TextureFormat::Rgba32Float,
 
             _ => panic!("Not implemented color format: {:?}", value.to_string()),
         }
@@ -30,3 +49,46 @@ impl From<&ColorFormat> for wgpu::TextureFormat {
 }
 
 
+
+// same bytes as the format From<&ColorFormat> picks, read by shaders as integers;
+// 8 bit formats are normalized by default and have Uint/Sint variants, e.g. R8Uint
+pub(crate) fn integer_texture_format(color_format: &ColorFormat) -> Option<wgpu::TextureFormat> {
+    match *color_format {
+        ColorFormat::GRAY_U8 => Some(wgpu::TextureFormat::R8Uint),
+        ColorFormat::GRAY_I8 => Some(wgpu::TextureFormat::R8Sint),
+        ColorFormat::RGBA_U8 => Some(wgpu::TextureFormat::Rgba8Uint),
+        ColorFormat::RGBA_I8 => Some(wgpu::TextureFormat::Rgba8Sint),
+        ColorFormat::GRAY_U32
+        | ColorFormat::GRAY_I32
+        | ColorFormat::RGBA_U32
+        | ColorFormat::RGBA_I32 => Some(wgpu::TextureFormat::from(color_format)),
+        _ => None,
+    }
+}
+
+// how shaders read textures of the format: normalized formats as floats,
+// Uint and Sint formats as integers
+pub(crate) fn sample_type(format: wgpu::TextureFormat) -> wgpu::TextureSampleType {
+    match format.sample_type(None) {
+        Some(wgpu::TextureSampleType::Float { .. }) => wgpu::TextureSampleType::Float { filterable: false },
+        Some(sample_type) => sample_type,
+        None => panic!("Texture format {:?} can't be sampled", format),
+    }
+}
+
+pub(crate) fn sample_type_name(sample_type: wgpu::TextureSampleType) -> &'static str {
+    match sample_type {
+        wgpu::TextureSampleType::Float { .. } => "float",
+        wgpu::TextureSampleType::Uint => "uint",
+        wgpu::TextureSampleType::Sint => "sint",
+        wgpu::TextureSampleType::Depth => "depth",
+    }
+}
+
+// formats compute shaders can write to without optional device features
+pub(crate) fn is_storage_format(format: wgpu::TextureFormat) -> bool {
+    format
+        .guaranteed_format_features(wgpu::Features::empty())
+        .allowed_usages
+        .contains(wgpu::TextureUsages::STORAGE_BINDING)
+}
@@ -1,16 +1,27 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::num::NonZeroU64;
 use std::ops::RangeBounds;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::task::{Poll, Waker};
 use std::thread;
+use std::time::Duration;
 
 use bytemuck::Pod;
 use pollster::FutureExt;
 use wgpu::util::DeviceExt;
 
-use crate::color_format::ColorFormat;
-use crate::image::{Image, ImageDesc};
+use crate::color_format::{ChannelCount, ChannelType, ColorFormat};
+use crate::image::{get_file_extension, Image, ImageDesc};
+use crate::wgpu::image_cache::ImageCache;
 use crate::wgpu::math::{Transform2D, Vert2D};
+use crate::wgpu::utils::{integer_texture_format, is_storage_format, sample_type, sample_type_name};
 
 fn aligned_size_of_uniform<U: Sized>() -> u64 {
     let uniform_size = std::mem::size_of::<U>();
@@ -28,23 +39,147 @@ pub(crate) enum Action<'a> {
         input_textures: Vec<&'a TextureWithTransform>,
         output_texture: &'a Texture,
         fragment_push_constant: &'a [u8],
+        // transparent black if not set
+        clear_color: Option<ClearColor>,
+        // names the pass in last_timings, the entry name if not set
+        label: Option<&'a str>,
+    },
+    // for shaders created with create_shader_ubo
+    RunShaderUbo {
+        shader: &'a Shader,
+        shader_entry_name: &'a str,
+        input_textures: Vec<&'a TextureWithTransform>,
+        output_texture: &'a Texture,
+        fragment_uniform: &'a [u8],
+        clear_color: Option<ClearColor>,
+        // names the pass in last_timings, the entry name if not set
+        label: Option<&'a str>,
     },
     ImgToTex(Vec<(&'a Image, &'a Texture)>),
     TexToImg(Vec<(&'a Texture, RefCell<&'a mut Image>)>),
+    // for shaders created with create_compute_shader
+    RunCompute {
+        shader: &'a ComputeShader,
+        inputs: Vec<&'a Texture>,
+        outputs: Vec<&'a Texture>,
+        workgroups: [u32; 3],
+        push_constants: &'a [u8],
+        // names the pass in last_timings, the entry name if not set
+        label: Option<&'a str>,
+    },
+}
+
+// channel values in the normalized range of the target format:
+// 0..1 for unsigned, -1..1 for signed, unbounded for float
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ClearColor {
+    Gray(f64),
+    Rgba(f64, f64, f64, f64),
 }
 
 pub(crate) struct WgpuContext {
-    device: wgpu::Device,
+    // shared with the threads polling for read_async
+    device: Arc<wgpu::Device>,
     queue: wgpu::Queue,
     limits: wgpu::Limits,
     rect_one_vb: VertexBuffer,
     default_sampler: wgpu::Sampler,
     encoder: RefCell<Option<wgpu::CommandEncoder>>,
-    common_vertex_shader_module: wgpu::ShaderModule,
+    // none if push constants are not supported by the adapter
+    common_vertex_shader_module: Option<wgpu::ShaderModule>,
+    common_vertex_shader_ubo_module: wgpu::ShaderModule,
+    // none if timestamp queries are not supported by the adapter
+    timestamp_query: Option<TimestampQuery>,
+    max_inflight_readbacks: Option<usize>,
+    peak_inflight_readbacks: Cell<usize>,
+    texture_allocation_count: Cell<usize>,
+    texture_write_count: Cell<usize>,
+    // uncaptured device errors collected by the default error handler
+    uncaptured_errors: Arc<Mutex<Vec<String>>>,
+    bind_group_cache: RefCell<HashMap<BindGroupKey, CachedBindGroup>>,
+    bind_group_creation_count: Cell<usize>,
+    // liveness tokens and byte sizes of allocated textures
+    live_textures: RefCell<Vec<(Weak<()>, u64)>>,
+    // shaders created from files, recompiled by reload_shaders
+    file_shaders: RefCell<Vec<FileShader>>,
+    identity_shader: OnceCell<Shader>,
+    // textures of images uploaded with upload_cached
+    image_cache: RefCell<ImageCache>,
 }
 
+struct FileShader {
+    path: PathBuf,
+    source: String,
+    fragment: Weak<ShaderFragment>,
+}
+
+// features to request from the device: the required ones and clamp to border must be supported,
+// push constants and timestamp queries are used when available
+pub(crate) fn device_features(
+    adapter_features: wgpu::Features,
+    required_features: wgpu::Features,
+) -> anyhow::Result<wgpu::Features> {
+    let required_features = required_features | wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER;
+    let missing_features = required_features.difference(adapter_features);
+    if !missing_features.is_empty() {
+        return Err(anyhow::anyhow!("GPU adapter doesn't support {:?}", missing_features));
+    }
+
+    let optional_features = wgpu::Features::PUSH_CONSTANTS | wgpu::Features::TIMESTAMP_QUERY;
+    Ok(required_features | (adapter_features & optional_features))
+}
+
+pub(crate) const IDENTITY_SHADER_ENTRY_NAME: &str = "fs_main";
+
+// total size of the textures kept by upload_cached, see set_image_cache_capacity
+pub(crate) const DEFAULT_IMAGE_CACHE_CAPACITY: u64 = 256 * 1024 * 1024;
+
+// transforms declared by common_vert.wgsl and common_vert_ubo.wgsl
+const COMMON_VERTEX_TRANSFORM_COUNT: u32 = 2;
+
+// texture_2d<f32> inputs of shaders not created for a specific input format
+const FLOAT_SAMPLE_TYPE: wgpu::TextureSampleType = wgpu::TextureSampleType::Float { filterable: false };
+
+// addresses of the shader and input texture liveness tokens
+type BindGroupKey = (usize, Vec<usize>);
+
+struct CachedBindGroup {
+    bind_group: Rc<wgpu::BindGroup>,
+    // the entry is dropped once the shader or any of the bound textures is dropped
+    resources: Vec<Weak<()>>,
+}
+
+enum ShaderParams<'a> {
+    PushConstant(&'a [u8]),
+    Uniform {
+        bind_group: &'a wgpu::BindGroup,
+        offsets: &'a [u32],
+    },
+}
+
+// start and end timestamps of the shader and compute passes, in pairs;
+// only the most recent pass is kept until enable_timing, then every pass up to the capacity
+struct TimestampQuery {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+    period: f32,
+    capacity: u32,
+    keeps_all_passes: bool,
+    // one per recorded pass
+    labels: RefCell<Vec<String>>,
+}
+
+// passes timed between two calls to last_timings unless enable_timing is given another limit
+pub(crate) const DEFAULT_MAX_TIMED_PASSES: u32 = 64;
+
 impl WgpuContext {
     pub fn new() -> anyhow::Result<WgpuContext> {
+        Self::with_required_features(wgpu::Features::empty())
+    }
+    // fails if the adapter lacks any of the features instead of falling back, e.g. to uniform buffers
+    // when PUSH_CONSTANTS is not supported
+    pub fn with_required_features(required_features: wgpu::Features) -> anyhow::Result<WgpuContext> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
             dx12_shader_compiler: wgpu::Dx12Compiler::Dxc { dxil_path: None, dxc_path: None },
@@ -56,28 +191,39 @@ impl WgpuContext {
                 compatible_surface: None,
             })
             .block_on()
-            .expect("Unable to find a suitable GPU adapter.");
+            .ok_or_else(|| anyhow::anyhow!("Unable to find a suitable GPU adapter"))?;
 
-        assert!(adapter.features().contains(wgpu::Features::PUSH_CONSTANTS));
+        let features = device_features(adapter.features(), required_features)?;
+        let is_push_constant_supported = features.contains(wgpu::Features::PUSH_CONSTANTS);
 
         let _limits = adapter.limits();
         let limits = wgpu::Limits {
-            max_push_constant_size: 256,
+            max_push_constant_size: if is_push_constant_supported { 256 } else { 0 },
             max_texture_dimension_1d: 16384,
             max_texture_dimension_2d: 16384,
             ..Default::default()
         };
 
+        let is_timestamp_supported = features.contains(wgpu::Features::TIMESTAMP_QUERY);
+
         let device_descriptor = wgpu::DeviceDescriptor {
             label: None,
-            features: wgpu::Features::PUSH_CONSTANTS | wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER,
+            features,
             limits: limits.clone(),
         };
 
         let (device, queue) = adapter
             .request_device(&device_descriptor, None)
             .block_on()
-            .expect("Unable to find a suitable GPU device.");
+            .map_err(|err| anyhow::anyhow!("Unable to create GPU device: {}", err))?;
+
+        let uncaptured_errors: Arc<Mutex<Vec<String>>> = Arc::default();
+        {
+            let uncaptured_errors = Arc::clone(&uncaptured_errors);
+            device.on_uncaptured_error(Box::new(move |error| {
+                uncaptured_errors.lock().unwrap().push(error.to_string());
+            }));
+        }
 
         let rect_one_vb = VertexBuffer::from_slice(&device, &Vert2D::rect_one());
 
@@ -88,24 +234,58 @@ impl WgpuContext {
             ..Default::default()
         });
 
-        let common_vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        let common_vertex_shader = is_push_constant_supported.then(|| {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(include_str!("common_vert.wgsl").into()),
+            })
+        });
+        let common_vertex_shader_ubo = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(include_str!("common_vert.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(include_str!("common_vert_ubo.wgsl").into()),
         });
 
+        let timestamp_query = if is_timestamp_supported {
+            Some(TimestampQuery::new(&device, &queue, 1, false))
+        } else {
+            None
+        };
+
         Ok(WgpuContext {
-            device,
+            device: Arc::new(device),
             queue,
             limits,
             rect_one_vb,
             default_sampler,
             encoder: RefCell::new(None),
             common_vertex_shader_module: common_vertex_shader,
+            common_vertex_shader_ubo_module: common_vertex_shader_ubo,
+            timestamp_query,
+            max_inflight_readbacks: None,
+            peak_inflight_readbacks: Cell::new(0),
+            texture_allocation_count: Cell::new(0),
+            texture_write_count: Cell::new(0),
+            uncaptured_errors,
+            bind_group_cache: RefCell::new(HashMap::new()),
+            bind_group_creation_count: Cell::new(0),
+            live_textures: RefCell::new(Vec::new()),
+            file_shaders: RefCell::new(Vec::new()),
+            identity_shader: OnceCell::new(),
+            image_cache: RefCell::new(ImageCache::new(DEFAULT_IMAGE_CACHE_CAPACITY)),
         })
     }
 
+    // perform that validates the whole batch first and runs nothing if any action is invalid
+    pub fn try_perform(&self, actions: &[Action]) -> anyhow::Result<()> {
+        actions
+            .iter()
+            .try_for_each(|action| action.validate())?;
+        self.perform(actions);
+
+        Ok(())
+    }
     pub fn perform(&self, actions: &[Action]) {
-        let mut buffer_images: Option<Vec<BufferImage>> = None;
+        let mut buffer_images: Vec<BufferImage> = Vec::new();
 
         for (action_index, action) in actions.iter().enumerate() {
             match action {
@@ -115,6 +295,8 @@ impl WgpuContext {
                     input_textures,
                     output_texture,
                     fragment_push_constant,
+                    clear_color,
+                    label,
                 } => {
                     let mut encoder_temp = self.encoder.borrow_mut();
                     let encoder = encoder_temp
@@ -127,15 +309,124 @@ impl WgpuContext {
                             bytemuck::bytes_of(&t.transform).to_vec()
                         })
                         .collect::<Vec<u8>>();
+                    push_constant.resize(shader.vertex_push_constant_size as usize, 0);
                     push_constant.extend_from_slice(fragment_push_constant);
 
                     self.run_shader(
                         encoder,
                         shader,
                         shader_entry_name,
+                        label.unwrap_or(shader_entry_name),
+                        input_textures,
+                        output_texture,
+                        ShaderParams::PushConstant(push_constant.as_slice()),
+                        *clear_color,
+                    );
+                }
+
+                Action::RunShaderUbo {
+                    shader,
+                    shader_entry_name,
+                    input_textures,
+                    output_texture,
+                    fragment_uniform,
+                    clear_color,
+                    label,
+                } => {
+                    let uniform_bind_group_layout = shader.uniform_bind_group_layout
+                        .as_ref()
+                        .expect("Shader was not created with create_shader_ubo");
+                    assert_eq!(shader.fragment_push_constant_size, fragment_uniform.len() as u32);
+
+                    let mut encoder_temp = self.encoder.borrow_mut();
+                    let encoder = encoder_temp
+                        .get_or_insert_with(|| self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: None,
+                        }));
+
+                    // transforms for the vertex stage first, fragment params at the next aligned offset
+                    let mut uniform_data = input_textures.iter()
+                        .flat_map(|t| {
+                            bytemuck::bytes_of(&t.transform).to_vec()
+                        })
+                        .collect::<Vec<u8>>();
+                    uniform_data.resize(shader.vertex_push_constant_size as usize, 0);
+                    let alignment = self.limits.min_uniform_buffer_offset_alignment as usize;
+                    let fragment_offset = uniform_data.len().next_multiple_of(alignment);
+                    uniform_data.resize(fragment_offset, 0);
+                    uniform_data.extend_from_slice(fragment_uniform);
+
+                    let uniform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: None,
+                        contents: &uniform_data,
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
+
+                    let uniform_bindings = [
+                        (0, shader.vertex_push_constant_size, 0),
+                        (1, shader.fragment_push_constant_size, fragment_offset as u32),
+                    ];
+                    let bind_entries = uniform_bindings
+                        .iter()
+                        .filter(|(_, size, _)| *size > 0)
+                        .map(|&(binding, size, _)| wgpu::BindGroupEntry {
+                            binding,
+                            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                                buffer: &uniform_buffer,
+                                offset: 0,
+                                size: NonZeroU64::new(size as u64),
+                            }),
+                        })
+                        .collect::<Vec<wgpu::BindGroupEntry>>();
+                    let offsets = uniform_bindings
+                        .iter()
+                        .filter(|(_, size, _)| *size > 0)
+                        .map(|&(_, _, offset)| offset)
+                        .collect::<Vec<u32>>();
+
+                    let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: uniform_bind_group_layout,
+                        entries: bind_entries.as_slice(),
+                        label: None,
+                    });
+
+                    self.run_shader(
+                        encoder,
+                        shader,
+                        shader_entry_name,
+                        label.unwrap_or(shader_entry_name),
                         input_textures,
                         output_texture,
-                        push_constant.as_slice(),
+                        ShaderParams::Uniform {
+                            bind_group: &bind_group,
+                            offsets: offsets.as_slice(),
+                        },
+                        *clear_color,
+                    );
+                }
+
+                Action::RunCompute {
+                    shader,
+                    inputs,
+                    outputs,
+                    workgroups,
+                    push_constants,
+                    label,
+                } => {
+                    let mut encoder_temp = self.encoder.borrow_mut();
+                    let encoder = encoder_temp
+                        .get_or_insert_with(|| self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: None,
+                        }));
+
+                    self.run_compute(
+                        encoder,
+                        shader,
+                        label.unwrap_or(COMPUTE_SHADER_ENTRY_NAME),
+                        inputs,
+                        outputs,
+                        *workgroups,
+                        push_constants,
                     );
                 }
 
@@ -156,31 +447,38 @@ impl WgpuContext {
                             },
                             texture.extent,
                         );
+                        self.texture_write_count.set(self.texture_write_count.get() + 1);
                     }
                 }
 
                 Action::TexToImg(tex_img) => {
-                    let mut encoder_temp = self.encoder.borrow_mut();
-                    let encoder = encoder_temp
-                        .get_or_insert_with(|| self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                            label: None,
-                        }));
-
                     for (index_in_action, (texture, image)) in tex_img.iter().enumerate() {
+                        if self.max_inflight_readbacks.is_some_and(|max| buffer_images.len() >= max) {
+                            self.finish_readbacks(actions, &mut buffer_images);
+                        }
+
                         let image = image.borrow();
 
                         if image.desc != texture.desc {
                             panic!("Image and texture must have the same dimensions");
                         }
                         let desc = &image.desc;
+                        // buffer copies need rows aligned to 256 bytes, the padding is stripped in finish_readbacks
+                        let padded_stride = desc.stride().next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
 
                         let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
-                            size: desc.size_in_bytes() as wgpu::BufferAddress,
+                            size: padded_stride as wgpu::BufferAddress * desc.height() as wgpu::BufferAddress,
                             usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
                             mapped_at_creation: false,
                             label: None,
                         });
 
+                        let mut encoder_temp = self.encoder.borrow_mut();
+                        let encoder = encoder_temp
+                            .get_or_insert_with(|| self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                label: None,
+                            }));
+
                         encoder.copy_texture_to_buffer(
                             wgpu::ImageCopyTexture {
                                 texture: &texture.texture,
@@ -192,85 +490,437 @@ impl WgpuContext {
                                 buffer: &buffer,
                                 layout: wgpu::ImageDataLayout {
                                     offset: 0,
-                                    bytes_per_row: Some(desc.stride()),
+                                    bytes_per_row: Some(padded_stride),
                                     rows_per_image: Some(desc.height()),
                                 },
                             },
                             texture.extent,
                         );
 
-                        buffer_images
-                            .get_or_insert_with(Vec::new)
-                            .push(BufferImage {
-                                buffer,
-                                image_index: (action_index, index_in_action),
-                            });
+                        buffer_images.push(BufferImage {
+                            buffer,
+                            image_index: (action_index, index_in_action),
+                            padded_stride,
+                        });
+                        self.peak_inflight_readbacks.set(
+                            self.peak_inflight_readbacks.get().max(buffer_images.len())
+                        );
                     }
                 }
             }
         }
 
-        if let Some(buffer_images) = buffer_images {
-            self.sync();
+        self.finish_readbacks(actions, &mut buffer_images);
+    }
 
-            let slices = buffer_images
-                .iter()
-                .map(|buf_img| {
-                    let slice = buf_img.buffer.slice(..);
-                    slice.map_async(wgpu::MapMode::Read, |result| {
-                        result.unwrap();
-                    });
-                    slice
-                })
-                .collect::<Vec<wgpu::BufferSlice>>();
+    // limits the number of staging buffers alive at once,
+    // readbacks over the limit wait for the pending ones to complete
+    pub fn set_max_inflight_readbacks(&mut self, max_inflight_readbacks: Option<usize>) {
+        assert_ne!(max_inflight_readbacks, Some(0));
+        self.max_inflight_readbacks = max_inflight_readbacks;
+    }
+    pub(crate) fn peak_inflight_readbacks(&self) -> usize {
+        self.peak_inflight_readbacks.get()
+    }
+    // for streaming frames of the same size, at most `capacity` staging buffers are allocated
+    pub(crate) fn create_readback_ring(&self, desc: ImageDesc, capacity: usize) -> (ReadbackRing, Receiver<Image>) {
+        assert_ne!(capacity, 0);
+        let (sender, receiver) = channel();
+        let padded_stride = desc.stride().next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let ring = ReadbackRing {
+            desc,
+            padded_stride,
+            capacity,
+            free_buffers: Vec::new(),
+            pending: VecDeque::new(),
+            buffer_allocation_count: 0,
+            sender,
+        };
 
-            self.device.poll(wgpu::Maintain::Wait);
+        (ring, receiver)
+    }
+    // textures touched by a batch, each once, in order of first use
+    pub(crate) fn batch_textures<'a>(actions: &[Action<'a>]) -> Vec<&'a Texture> {
+        let mut textures: Vec<&'a Texture> = Vec::new();
+        actions
+            .iter()
+            .flat_map(|action| action.textures())
+            .for_each(|texture| {
+                if !textures.iter().any(|&other| std::ptr::eq(other, texture)) {
+                    textures.push(texture);
+                }
+            });
 
-            for (slice_index, buf_img) in buffer_images.iter().enumerate() {
-                let (action_index, index_in_action) = buf_img.image_index;
-                if let Action::TexToImg(tex_to_img) = &actions[action_index] {
-                    let mut image = tex_to_img[index_in_action].1.borrow_mut();
+        textures
+    }
 
-                    let data = slices[slice_index].get_mapped_range();
-                    image.bytes = data.to_vec();
-                    drop(data);
+    fn finish_readbacks(&self, actions: &[Action], buffer_images: &mut Vec<BufferImage>) {
+        if buffer_images.is_empty() {
+            return;
+        }
 
-                    buf_img.buffer.unmap();
-                } else {
-                    panic!("Expected TexToImg action.");
-                }
+        self.submit();
+
+        let slices = buffer_images
+            .iter()
+            .map(|buf_img| {
+                let slice = buf_img.buffer.slice(..);
+                slice.map_async(wgpu::MapMode::Read, |result| {
+                    result.unwrap();
+                });
+                slice
+            })
+            .collect::<Vec<wgpu::BufferSlice>>();
+
+        self.device.poll(wgpu::Maintain::Wait);
+
+        for (slice_index, buf_img) in buffer_images.iter().enumerate() {
+            let (action_index, index_in_action) = buf_img.image_index;
+            if let Action::TexToImg(tex_to_img) = &actions[action_index] {
+                let mut image = tex_to_img[index_in_action].1.borrow_mut();
+
+                let data = slices[slice_index].get_mapped_range();
+                image.bytes = unpad_rows(&data, buf_img.padded_stride, image.desc.stride());
+                drop(data);
+
+                buf_img.buffer.unmap();
+            } else {
+                panic!("Expected TexToImg action.");
             }
         }
+
+        drop(slices);
+        buffer_images.clear();
     }
 
-    pub fn sync(&self) {
-        if let Some(encoder) = self.encoder.replace(None) {
-            self.queue.submit(Some(encoder.finish()));
+    // waits for submitted work, failing with device errors reported since the last sync
+    pub fn sync(&self) -> anyhow::Result<()> {
+        self.submit();
+        self.evict_dead_bind_groups();
+        self.take_error()
+    }
+    fn submit(&self) {
+        if self.submit_without_wait().is_some() {
             self.device.poll(wgpu::Maintain::Wait);
         }
     }
+    // none if nothing was recorded since the last submit
+    fn submit_without_wait(&self) -> Option<wgpu::SubmissionIndex> {
+        self.encoder
+            .replace(None)
+            .map(|encoder| self.queue.submit(Some(encoder.finish())))
+    }
+
+    // replaces the default handler, errors are no longer reported by sync
+    pub fn set_error_handler(&self, handler: Box<dyn Fn(wgpu::Error) + Send>) {
+        self.device.on_uncaptured_error(Box::new(handler));
+    }
+    pub fn take_error(&self) -> anyhow::Result<()> {
+        let errors = std::mem::take(&mut *self.uncaptured_errors.lock().unwrap());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("GPU error: {}", errors.join("\n")))
+        }
+    }
 
+    // times every following shader and compute pass until the context is dropped; passes after the first
+    // max_timed_passes since the last call to last_timings are not timed, see DEFAULT_MAX_TIMED_PASSES.
+    // Fails if timestamp queries are not supported by the adapter
+    pub fn enable_timing(&mut self, max_timed_passes: u32) -> anyhow::Result<()> {
+        if !self.device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return Err(anyhow::anyhow!("Timestamp queries are not supported by the GPU adapter"));
+        }
+        if max_timed_passes == 0 || max_timed_passes > wgpu::QUERY_SET_MAX_QUERIES / 2 {
+            return Err(anyhow::anyhow!(
+                "Timed pass count must be between 1 and {}",
+                wgpu::QUERY_SET_MAX_QUERIES / 2
+            ));
+        }
+
+        // passes recorded so far are resolved from the old query set
+        self.submit();
+        self.timestamp_query = Some(TimestampQuery::new(&self.device, &self.queue, max_timed_passes, true));
+
+        Ok(())
+    }
+    // labels and GPU durations of the passes run since the previous call, in order;
+    // empty if timing is not enabled
+    pub fn last_timings(&self) -> Vec<(String, Duration)> {
+        let Some(timestamp_query) = self.timestamp_query.as_ref().filter(|query| query.keeps_all_passes) else {
+            return Vec::new();
+        };
+
+        let durations = self.read_pass_durations(timestamp_query);
+        std::mem::take(&mut *timestamp_query.labels.borrow_mut())
+            .into_iter()
+            .zip(durations)
+            .collect()
+    }
+    // GPU execution time of the most recent timed shader or compute pass,
+    // None if timestamp queries are not supported by the adapter
+    pub fn last_shader_gpu_time(&self) -> Option<Duration> {
+        let timestamp_query = self.timestamp_query.as_ref()?;
+
+        self.read_pass_durations(timestamp_query).pop()
+    }
+
+    fn read_pass_durations(&self, timestamp_query: &TimestampQuery) -> Vec<Duration> {
+        let query_count = timestamp_query.labels.borrow().len() as u32 * 2;
+        if query_count == 0 {
+            return Vec::new();
+        }
+
+        let size = query_count as wgpu::BufferAddress * TimestampQuery::TIMESTAMP_SIZE;
+        {
+            let mut encoder_temp = self.encoder.borrow_mut();
+            let encoder = encoder_temp
+                .get_or_insert_with(|| self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: None,
+                }));
+            encoder.resolve_query_set(&timestamp_query.query_set, 0..query_count, &timestamp_query.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&timestamp_query.resolve_buffer, 0, &timestamp_query.read_buffer, 0, size);
+        }
+        self.submit();
+
+        let slice = timestamp_query.read_buffer.slice(..size);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.unwrap();
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let timestamps: &[u64] = bytemuck::cast_slice(&data);
+        let durations = timestamps
+            .chunks_exact(2)
+            .map(|timestamps| {
+                let nanoseconds = timestamps[1].saturating_sub(timestamps[0]) as f64 * timestamp_query.period as f64;
+                Duration::from_nanos(nanoseconds as u64)
+            })
+            .collect();
+        drop(data);
+        timestamp_query.read_buffer.unmap();
+
+        durations
+    }
+
+    // fragment params follow the vertex transforms in the push constant block:
+    // declare them after one mat3x3<f32> per input texture, at least two
     pub(crate) fn create_shader(
         &self,
         shader: &str,
         input_texture_count: u32,
         push_constant_size: u32,
-    ) -> Shader {
-        Shader::new(&self.device, shader, input_texture_count, push_constant_size)
+    ) -> anyhow::Result<Shader> {
+        if self.common_vertex_shader_module.is_none() {
+            return Err(anyhow::anyhow!("Push constants are not supported, use create_shader_ubo"));
+        }
+
+        self.create_shader_impl(shader, input_texture_count, push_constant_size, false, FLOAT_SAMPLE_TYPE)
+    }
+    // same as create_shader for inputs of the given texture format, see create_shader_ubo_with_input_format
+    pub(crate) fn create_shader_with_input_format(
+        &self,
+        shader: &str,
+        input_texture_count: u32,
+        push_constant_size: u32,
+        input_format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Shader> {
+        if self.common_vertex_shader_module.is_none() {
+            return Err(anyhow::anyhow!("Push constants are not supported, use create_shader_ubo_with_input_format"));
+        }
+
+        self.create_shader_impl(shader, input_texture_count, push_constant_size, false, sample_type(input_format))
+    }
+    // shader receives parameters from a uniform buffer instead of push constants:
+    // @group(1) @binding(1) for the fragment stage, binding 0 is used by the vertex stage
+    pub(crate) fn create_shader_ubo(
+        &self,
+        shader: &str,
+        input_texture_count: u32,
+        uniform_size: u32,
+    ) -> anyhow::Result<Shader> {
+        self.create_shader_impl(shader, input_texture_count, uniform_size, true, FLOAT_SAMPLE_TYPE)
+    }
+    // same as create_shader_ubo for inputs of the given texture format, e.g. texture_2d<u32>
+    // for R8Uint or R32Uint; integer textures can't be sampled, read them with textureLoad
+    pub(crate) fn create_shader_ubo_with_input_format(
+        &self,
+        shader: &str,
+        input_texture_count: u32,
+        uniform_size: u32,
+        input_format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Shader> {
+        self.create_shader_impl(shader, input_texture_count, uniform_size, true, sample_type(input_format))
     }
+    // inputs are bound as texture_2d<f32> at @group(0) @binding(0..input_texture_count),
+    // outputs as texture_storage_2d<format, write> at the following bindings,
+    // the entry point is COMPUTE_SHADER_ENTRY_NAME
+    pub(crate) fn create_compute_shader(
+        &self,
+        shader: &str,
+        input_texture_count: u32,
+        output_texture_count: u32,
+        push_constant_size: u32,
+    ) -> anyhow::Result<ComputeShader> {
+        if push_constant_size > 0 && self.common_vertex_shader_module.is_none() {
+            return Err(anyhow::anyhow!("Push constants are not supported"));
+        }
+
+        Ok(ComputeShader {
+            module: self.create_shader_module(shader)?,
+            input_texture_count,
+            output_texture_count,
+            push_constant_size,
+            pipeline_cache: RefCell::default(),
+        })
+    }
+    // passthrough of input 0, created once per context; works without push constant support,
+    // run it with Action::RunShaderUbo, IDENTITY_SHADER_ENTRY_NAME and an empty fragment uniform
+    pub(crate) fn identity_shader(&self) -> &Shader {
+        self.identity_shader.get_or_init(|| {
+            self.create_shader_ubo(include_str!("identity_frag.wgsl"), 1, 0)
+                .expect("Identity shader failed to compile")
+        })
+    }
+    // same as create_shader, the shader is recompiled by reload_shaders when the file changes
+    pub(crate) fn create_shader_from_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        input_texture_count: u32,
+        push_constant_size: u32,
+    ) -> anyhow::Result<Shader> {
+        let path = path.as_ref().to_path_buf();
+        let source = std::fs::read_to_string(&path)?;
+        let shader = self.create_shader(&source, input_texture_count, push_constant_size)?;
+
+        self.file_shaders.borrow_mut().push(FileShader {
+            path,
+            source,
+            fragment: Rc::downgrade(&shader.fragment),
+        });
+
+        Ok(shader)
+    }
+    // recompiles file-backed shaders whose source changed, returns the number of reloaded shaders;
+    // shaders failing to compile keep the previous module
+    pub fn reload_shaders(&self) -> anyhow::Result<usize> {
+        let mut file_shaders = self.file_shaders.borrow_mut();
+        file_shaders.retain(|file_shader| file_shader.fragment.strong_count() > 0);
+
+        let mut reloaded_count = 0;
+        let mut errors: Vec<String> = Vec::new();
+        for file_shader in file_shaders.iter_mut() {
+            let source = match std::fs::read_to_string(&file_shader.path) {
+                Ok(source) => source,
+                Err(err) => {
+                    errors.push(format!("{}: {}", file_shader.path.display(), err));
+                    continue;
+                }
+            };
+            if source == file_shader.source {
+                continue;
+            }
+
+            let module = match self.create_shader_module(&source) {
+                Ok(module) => module,
+                Err(err) => {
+                    errors.push(format!("{}: {}", file_shader.path.display(), err));
+                    continue;
+                }
+            };
 
+            let fragment = file_shader.fragment.upgrade().unwrap();
+            *fragment.module.borrow_mut() = module;
+            fragment.pipeline_cache.borrow_mut().clear();
+            file_shader.source = source;
+            reloaded_count += 1;
+        }
+
+        if errors.is_empty() {
+            Ok(reloaded_count)
+        } else {
+            Err(anyhow::anyhow!("Shader reload failed:\n{}", errors.join("\n")))
+        }
+    }
+    fn create_shader_module(&self, shader: &str) -> anyhow::Result<wgpu::ShaderModule> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        if let Some(error) = self.device.pop_error_scope().block_on() {
+            return Err(anyhow::anyhow!("Shader creation failed: {}", error));
+        }
+
+        Ok(module)
+    }
+    fn create_shader_impl(
+        &self,
+        shader: &str,
+        input_texture_count: u32,
+        fragment_param_size: u32,
+        use_uniform_buffer: bool,
+        input_sample_type: wgpu::TextureSampleType,
+    ) -> anyhow::Result<Shader> {
+        // catch WGSL compilation errors here instead of on first use of the module
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = Shader::new(
+            &self.device,
+            shader,
+            input_texture_count,
+            fragment_param_size,
+            use_uniform_buffer,
+            input_sample_type,
+        );
+        if let Some(error) = self.device.pop_error_scope().block_on() {
+            return Err(anyhow::anyhow!("Shader creation failed: {}", error));
+        }
+
+        Ok(shader)
+    }
+
+    // shader input holding the image; images with the same content as a cached one,
+    // e.g. a static background passed on every run, are not uploaded again
+    pub(crate) fn upload_cached(&self, image: &Image) -> Rc<TextureWithTransform> {
+        self.image_cache.borrow_mut().get_or_upload(self, image)
+    }
+    // least recently used textures are released to fit, the last upload is always kept
+    pub(crate) fn set_image_cache_capacity(&self, capacity_bytes: u64) {
+        self.image_cache.borrow_mut().set_capacity(capacity_bytes);
+    }
     pub(crate) fn create_texture(&self, image_desc: ImageDesc) -> Texture {
+        let format = wgpu::TextureFormat::from(&image_desc.color_format());
+        self.create_texture_with_format(image_desc, format)
+    }
+    // texture read by shaders as integers instead of normalized floats, e.g. R8Uint for GRAY_U8;
+    // uploads and readbacks are the same as for create_texture
+    pub(crate) fn create_integer_texture(&self, image_desc: ImageDesc) -> anyhow::Result<Texture> {
+        let format = integer_texture_format(&image_desc.color_format())
+            .ok_or_else(|| anyhow::anyhow!(
+                "Color format {:?} has no integer texture format",
+                image_desc.color_format()
+            ))?;
+
+        Ok(self.create_texture_with_format(image_desc, format))
+    }
+    fn create_texture_with_format(&self, image_desc: ImageDesc, format: wgpu::TextureFormat) -> Texture {
         let extent = wgpu::Extent3d {
             width: image_desc.width(),
             height: image_desc.height(),
             depth_or_array_layers: 1,
         };
 
-        let usage =
+        let mut usage =
             wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING
                 | wgpu::TextureUsages::COPY_DST
                 | wgpu::TextureUsages::COPY_SRC;
+        // compute shader outputs
+        if is_storage_format(format) {
+            usage |= wgpu::TextureUsages::STORAGE_BINDING;
+        }
 
         let texture = self.device.create_texture(&wgpu::TextureDescriptor {
             label: None,
@@ -278,64 +928,160 @@ impl WgpuContext {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::from(&image_desc.color_format()),
+            format,
             usage,
             view_formats: &[],
         });
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.texture_allocation_count.set(self.texture_allocation_count.get() + 1);
+
+        let alive = Rc::new(());
+        self.live_textures.borrow_mut()
+            .push((Rc::downgrade(&alive), image_desc.size_in_bytes() as u64));
 
         Texture {
             desc: image_desc,
+            format,
             texture,
             view,
             extent,
+            alive,
         }
     }
 
+    // bytes held by live textures; readback staging buffers are released
+    // at the end of each perform call and are not counted
+    pub fn estimated_vram_usage(&self) -> u64 {
+        let mut live_textures = self.live_textures.borrow_mut();
+        live_textures.retain(|(alive, _)| alive.strong_count() > 0);
+
+        live_textures
+            .iter()
+            .map(|(_, size)| size)
+            .sum()
+    }
+
+    pub(crate) fn create_lazy_texture(&self, image_desc: ImageDesc) -> LazyTexture {
+        LazyTexture {
+            desc: image_desc,
+            texture: OnceCell::new(),
+        }
+    }
+    pub(crate) fn texture_allocation_count(&self) -> usize {
+        self.texture_allocation_count.get()
+    }
+    pub(crate) fn texture_write_count(&self) -> usize {
+        self.texture_write_count.get()
+    }
+    pub(crate) fn bind_group_creation_count(&self) -> usize {
+        self.bind_group_creation_count.get()
+    }
+    pub(crate) fn cached_bind_group_count(&self) -> usize {
+        self.bind_group_cache.borrow().len()
+    }
+
+    // releases bind groups of dropped shaders and textures, run on every lookup and sync
+    fn evict_dead_bind_groups(&self) {
+        self.bind_group_cache
+            .borrow_mut()
+            .retain(|_, cached| {
+                cached.resources
+                    .iter()
+                    .all(|resource| resource.strong_count() > 0)
+            });
+    }
+    fn get_bind_group(&self, shader: &Shader, input_textures: &[&TextureWithTransform]) -> Rc<wgpu::BindGroup> {
+        self.evict_dead_bind_groups();
+        let mut cache = self.bind_group_cache.borrow_mut();
+
+        let key: BindGroupKey = (
+            Rc::as_ptr(&shader.alive) as usize,
+            input_textures.iter()
+                .map(|tex| Rc::as_ptr(&tex.texture.alive) as usize)
+                .collect(),
+        );
+
+        let cached = cache
+            .entry(key)
+            .or_insert_with(|| {
+                let mut bind_entries: Vec<wgpu::BindGroupEntry> = Vec::new();
+                bind_entries.push(wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.default_sampler),
+                });
+                input_textures.iter()
+                    .enumerate()
+                    .for_each(|(index, tex)| {
+                        bind_entries.push(wgpu::BindGroupEntry {
+                            binding: index as u32 + 1,
+                            resource: wgpu::BindingResource::TextureView(&tex.texture.view),
+                        });
+                    });
+
+                let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &shader.bind_group_layout,
+                    entries: bind_entries.as_slice(),
+                    label: None,
+                });
+                self.bind_group_creation_count.set(self.bind_group_creation_count.get() + 1);
+
+                CachedBindGroup {
+                    bind_group: Rc::new(bind_group),
+                    resources: std::iter::once(Rc::downgrade(&shader.alive))
+                        .chain(input_textures.iter()
+                            .map(|tex| Rc::downgrade(&tex.texture.alive)))
+                        .collect(),
+                }
+            });
+
+        Rc::clone(&cached.bind_group)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn run_shader(
         &self,
         encoder: &mut wgpu::CommandEncoder,
         shader: &Shader,
         shader_entry_name: &str,
+        label: &str,
         input_textures: &[&TextureWithTransform],
         output_texture: &Texture,
-        push_constant: &[u8],
+        params: ShaderParams,
+        clear_color: Option<ClearColor>,
     ) {
         assert_eq!(input_textures.len() as u32, shader.input_texture_count);
-        assert_eq!(
-            shader.fragment_push_constant_size + shader.vertex_push_constant_size,
-            push_constant.len() as u32
-        );
+        if let ShaderParams::PushConstant(push_constant) = &params {
+            assert_eq!(
+                shader.fragment_push_constant_size + shader.vertex_push_constant_size,
+                push_constant.len() as u32
+            );
+        }
 
         let device = &self.device;
 
-        let mut bind_entries: Vec<wgpu::BindGroupEntry> = Vec::new();
-        bind_entries.push(wgpu::BindGroupEntry {
-            binding: 0,
-            resource: wgpu::BindingResource::Sampler(&self.default_sampler),
-        });
-        input_textures.iter()
-            .enumerate()
-            .for_each(|(index, tex)| {
-                bind_entries.push(wgpu::BindGroupEntry {
-                    binding: index as u32 + 1,
-                    resource: wgpu::BindingResource::TextureView(&tex.texture.view),
-                });
-            });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &shader.bind_group_layout,
-            entries: bind_entries.as_slice(),
-            label: None,
-        });
+        let bind_group = self.get_bind_group(shader, input_textures);
         let pipeline = shader.get_pipeline(
             device,
-            &self.common_vertex_shader_module,
+            match params {
+                ShaderParams::PushConstant(_) => self.common_vertex_shader_module.as_ref().unwrap(),
+                ShaderParams::Uniform { .. } => &self.common_vertex_shader_ubo_module,
+            },
             shader_entry_name,
-            &output_texture.desc.color_format(),
+            output_texture.format,
         );
 
+        // used to be hardcoded green to spot undrawn areas while debugging,
+        // which is meaningless for single channel and float targets
+        let clear_color = clear_color
+            .map_or(wgpu::Color::TRANSPARENT, |clear_color| {
+                clear_color.to_wgpu(&output_texture.desc.color_format(), output_texture.format)
+            });
+
+        let timed_pass = self.timestamp_query
+            .as_ref()
+            .and_then(|timestamp_query| timestamp_query.begin(encoder, label));
+
         {
             let mut render_pass = encoder.begin_render_pass(
                 &wgpu::RenderPassDescriptor {
@@ -344,7 +1090,7 @@ impl WgpuContext {
                             view: &output_texture.view,
                             resolve_target: None,
                             ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                                load: wgpu::LoadOp::Clear(clear_color),
                                 store: true,
                             },
                         }),
@@ -357,11 +1103,29 @@ impl WgpuContext {
 
             render_pass.set_pipeline(&pipeline);
             render_pass.set_bind_group(0, &bind_group, &[]);
-            render_pass.set_push_constants(
-                wgpu::ShaderStages::VERTEX,
-                0,
-                push_constant,
-            );
+            match params {
+                ShaderParams::PushConstant(push_constant) => {
+                    // vertex transforms first, fragment params right after them,
+                    // each range is only visible to its own stage
+                    let (vertex_push_constant, fragment_push_constant) =
+                        push_constant.split_at(shader.vertex_push_constant_size as usize);
+                    render_pass.set_push_constants(
+                        wgpu::ShaderStages::VERTEX,
+                        0,
+                        vertex_push_constant,
+                    );
+                    if !fragment_push_constant.is_empty() {
+                        render_pass.set_push_constants(
+                            wgpu::ShaderStages::FRAGMENT,
+                            shader.vertex_push_constant_size,
+                            fragment_push_constant,
+                        );
+                    }
+                }
+                ShaderParams::Uniform { bind_group, offsets } => {
+                    render_pass.set_bind_group(1, bind_group, offsets);
+                }
+            }
 
             render_pass.pop_debug_group();
 
@@ -369,6 +1133,69 @@ impl WgpuContext {
             render_pass.set_vertex_buffer(0, self.rect_one_vb.slice(..));
             render_pass.draw(0..self.rect_one_vb.vert_count, 0..1);
         }
+
+        if let (Some(timestamp_query), Some(timed_pass)) = (&self.timestamp_query, timed_pass) {
+            timestamp_query.end(encoder, timed_pass);
+        }
+    }
+}
+
+impl WgpuContext {
+    #[allow(clippy::too_many_arguments)]
+    fn run_compute(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        shader: &ComputeShader,
+        label: &str,
+        inputs: &[&Texture],
+        outputs: &[&Texture],
+        workgroups: [u32; 3],
+        push_constants: &[u8],
+    ) {
+        assert_eq!(inputs.len() as u32, shader.input_texture_count);
+        assert_eq!(outputs.len() as u32, shader.output_texture_count);
+        assert_eq!(push_constants.len() as u32, shader.push_constant_size);
+
+        let output_formats = outputs
+            .iter()
+            .map(|texture| texture.format)
+            .collect::<Vec<wgpu::TextureFormat>>();
+        let pipeline = shader.get_pipeline(&self.device, output_formats);
+
+        let bind_entries = inputs
+            .iter()
+            .chain(outputs.iter())
+            .enumerate()
+            .map(|(index, texture)| wgpu::BindGroupEntry {
+                binding: index as u32,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            })
+            .collect::<Vec<wgpu::BindGroupEntry>>();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &pipeline.bind_group_layout,
+            entries: bind_entries.as_slice(),
+            label: None,
+        });
+
+        let timed_pass = self.timestamp_query
+            .as_ref()
+            .and_then(|timestamp_query| timestamp_query.begin(encoder, label));
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+            });
+            compute_pass.set_pipeline(&pipeline.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            if !push_constants.is_empty() {
+                compute_pass.set_push_constants(0, push_constants);
+            }
+            compute_pass.dispatch_workgroups(workgroups[0], workgroups[1], workgroups[2]);
+        }
+
+        if let (Some(timestamp_query), Some(timed_pass)) = (&self.timestamp_query, timed_pass) {
+            timestamp_query.end(encoder, timed_pass);
+        }
     }
 }
 
@@ -381,6 +1208,63 @@ impl Drop for WgpuContext {
 }
 
 
+impl TimestampQuery {
+    const TIMESTAMP_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, capacity: u32, keeps_all_passes: bool) -> TimestampQuery {
+        let query_count = capacity * 2;
+        let size = query_count as u64 * Self::TIMESTAMP_SIZE;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: None,
+            ty: wgpu::QueryType::Timestamp,
+            count: query_count,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        TimestampQuery {
+            query_set,
+            resolve_buffer,
+            read_buffer,
+            period: queue.get_timestamp_period(),
+            capacity,
+            keeps_all_passes,
+            labels: RefCell::new(Vec::new()),
+        }
+    }
+
+    // index of the timed pass, none once all slots are used
+    fn begin(&self, encoder: &mut wgpu::CommandEncoder, label: &str) -> Option<u32> {
+        let mut labels = self.labels.borrow_mut();
+        if !self.keeps_all_passes {
+            labels.clear();
+        }
+        let index = labels.len() as u32;
+        if index == self.capacity {
+            return None;
+        }
+
+        labels.push(label.to_string());
+        encoder.write_timestamp(&self.query_set, index * 2);
+
+        Some(index)
+    }
+    fn end(&self, encoder: &mut wgpu::CommandEncoder, index: u32) {
+        encoder.write_timestamp(&self.query_set, index * 2 + 1);
+    }
+}
+
+
 pub(crate) struct VertexBuffer {
     pub(crate) buffer: wgpu::Buffer,
     pub(crate) vert_count: u32,
@@ -419,9 +1303,155 @@ impl VertexBuffer {
     }
 }
 
+impl<'a> Action<'a> {
+    // input formats must match the sample type the shader was created for,
+    // mismatches are rejected here instead of failing on the device
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        let (shader, input_textures) = match self {
+            Action::RunShader { shader, input_textures, .. }
+            | Action::RunShaderUbo { shader, input_textures, .. } => (shader, input_textures),
+            Action::RunCompute { shader, inputs, outputs, push_constants, .. } =>
+                return Self::validate_compute(shader, inputs, outputs, push_constants),
+            Action::ImgToTex(_) | Action::TexToImg(_) => return Ok(()),
+        };
+
+        if input_textures.len() as u32 != shader.input_count() {
+            return Err(anyhow::anyhow!(
+                "Shader expects {} input textures, got {}",
+                shader.input_count(),
+                input_textures.len()
+            ));
+        }
+        for (index, input) in input_textures.iter().enumerate() {
+            if sample_type(input.texture.format) != shader.input_sample_type {
+                return Err(anyhow::anyhow!(
+                    "Input texture {} has format {:?} which can't be sampled as {}",
+                    index,
+                    input.texture.format,
+                    sample_type_name(shader.input_sample_type)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+    fn validate_compute(
+        shader: &ComputeShader,
+        inputs: &[&Texture],
+        outputs: &[&Texture],
+        push_constants: &[u8],
+    ) -> anyhow::Result<()> {
+        if inputs.len() as u32 != shader.input_count() || outputs.len() as u32 != shader.output_count() {
+            return Err(anyhow::anyhow!(
+                "Compute shader expects {} input and {} output textures, got {} and {}",
+                shader.input_count(),
+                shader.output_count(),
+                inputs.len(),
+                outputs.len()
+            ));
+        }
+        if push_constants.len() as u32 != shader.push_constant_size() {
+            return Err(anyhow::anyhow!(
+                "Compute shader expects {} bytes of push constants, got {}",
+                shader.push_constant_size(),
+                push_constants.len()
+            ));
+        }
+        for (index, input) in inputs.iter().enumerate() {
+            if sample_type(input.format) != FLOAT_SAMPLE_TYPE {
+                return Err(anyhow::anyhow!(
+                    "Input texture {} has format {:?} which can't be sampled as {}",
+                    index,
+                    input.format,
+                    sample_type_name(FLOAT_SAMPLE_TYPE)
+                ));
+            }
+        }
+        for (index, output) in outputs.iter().enumerate() {
+            if !is_storage_format(output.format) {
+                return Err(anyhow::anyhow!(
+                    "Output texture {} has format {:?} which can't be used as a storage texture",
+                    index,
+                    output.format
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // input textures followed by the output ones
+    pub(crate) fn textures(&self) -> Vec<&'a Texture> {
+        match self {
+            Action::RunShader { input_textures, output_texture, .. }
+            | Action::RunShaderUbo { input_textures, output_texture, .. } => {
+                input_textures
+                    .iter()
+                    .map(|input| &input.texture)
+                    .chain(std::iter::once(*output_texture))
+                    .collect()
+            }
+            Action::RunCompute { inputs, outputs, .. } => inputs.iter().chain(outputs.iter()).copied().collect(),
+            Action::ImgToTex(images) => images.iter().map(|(_, texture)| *texture).collect(),
+            Action::TexToImg(images) => images.iter().map(|(texture, _)| *texture).collect(),
+        }
+    }
+}
+
+impl ClearColor {
+    fn to_wgpu(self, color_format: &ColorFormat, format: wgpu::TextureFormat) -> wgpu::Color {
+        let (min, max) = match color_format.channel_type {
+            ChannelType::UInt => (0.0, 1.0),
+            ChannelType::Int => (-1.0, 1.0),
+            ChannelType::Float => (f64::MIN, f64::MAX),
+        };
+
+        let color = match (self, color_format.channel_count) {
+            (ClearColor::Gray(value), ChannelCount::Gray) => wgpu::Color {
+                r: value,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            },
+            (ClearColor::Rgba(r, g, b, a), ChannelCount::Rgba) => wgpu::Color { r, g, b, a },
+            _ => panic!("Clear color {:?} does not match target format {:?}", self, color_format),
+        };
+
+        assert!(
+            [color.r, color.g, color.b, color.a]
+                .iter()
+                .all(|value| (min..=max).contains(value)),
+            "Clear color {:?} is out of range for target format {:?}", self, color_format
+        );
+
+        // integer targets take raw values, the normalized range maps to the full integer range
+        let bits = color_format.channel_size.byte_count() * 8;
+        let scale = match sample_type(format) {
+            wgpu::TextureSampleType::Uint => (u64::MAX >> (64 - bits)) as f64,
+            wgpu::TextureSampleType::Sint => (u64::MAX >> (65 - bits)) as f64,
+            _ => return color,
+        };
+        wgpu::Color {
+            r: color.r * scale,
+            g: color.g * scale,
+            b: color.b * scale,
+            a: color.a * scale,
+        }
+    }
+}
+
+// fragment module and the pipelines built from it, replaced when a file-backed shader is reloaded
+struct ShaderFragment {
+    module: RefCell<wgpu::ShaderModule>,
+    pipeline_cache: RefCell<HashMap<(String, wgpu::TextureFormat), Rc<wgpu::RenderPipeline>>>,
+}
+
 pub(crate) struct Shader {
-    pub(crate) module: wgpu::ShaderModule,
+    fragment: Rc<ShaderFragment>,
     bind_group_layout: wgpu::BindGroupLayout,
+    input_sample_type: wgpu::TextureSampleType,
+    // set for shaders taking parameters from a uniform buffer
+    uniform_bind_group_layout: Option<wgpu::BindGroupLayout>,
     pipeline_layout: wgpu::PipelineLayout,
     input_texture_count: u32,
     vertex_push_constant_size: u32,
@@ -429,15 +1459,48 @@ pub(crate) struct Shader {
     vertex_layout: Vec<wgpu::VertexFormat>,
     vertex_stride: u64,
     vertex_attributes: Vec<wgpu::VertexAttribute>,
-    pipeline_cache: RefCell<HashMap<(String, ColorFormat), Rc<wgpu::RenderPipeline>>>,
+    // lets caches notice the shader was dropped
+    alive: Rc<()>,
 }
 
 impl Shader {
+    pub(crate) fn input_count(&self) -> u32 {
+        self.input_texture_count
+    }
+    pub(crate) fn fragment_push_constant_size(&self) -> u32 {
+        self.fragment_push_constant_size
+    }
+    // created with create_shader_ubo, run with Action::RunShaderUbo
+    pub(crate) fn uses_uniform_buffer(&self) -> bool {
+        self.uniform_bind_group_layout.is_some()
+    }
+    // vertex transforms and fragment params, zero for shaders taking a uniform buffer
+    pub(crate) fn push_constant_size(&self) -> u32 {
+        if self.uniform_bind_group_layout.is_some() {
+            return 0;
+        }
+        self.vertex_push_constant_size + self.fragment_push_constant_size
+    }
+    // one per entry point and output format used so far
+    pub(crate) fn cached_pipeline_count(&self) -> usize {
+        self.fragment.pipeline_cache.borrow().len()
+    }
+    pub(crate) fn push_constant_stages(&self) -> wgpu::ShaderStages {
+        if self.uniform_bind_group_layout.is_some() {
+            wgpu::ShaderStages::NONE
+        } else if self.fragment_push_constant_size > 0 {
+            wgpu::ShaderStages::VERTEX_FRAGMENT
+        } else {
+            wgpu::ShaderStages::VERTEX
+        }
+    }
     pub(crate) fn new(
         device: &wgpu::Device,
         shader: &str,
         input_texture_count: u32,
         fragment_push_constant_size: u32,
+        use_uniform_buffer: bool,
+        input_sample_type: wgpu::TextureSampleType,
     ) -> Shader {
         let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
@@ -459,7 +1522,7 @@ impl Shader {
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            sample_type: input_sample_type,
                             view_dimension: wgpu::TextureViewDimension::D2,
                         },
                         count: None,
@@ -473,12 +1536,44 @@ impl Shader {
                 label: None,
             });
 
-        let vertex_push_constant_size = input_texture_count * std::mem::size_of::<Transform2D>() as u32;
+        // the common vertex shaders read a fixed number of transforms, unused ones are zeroed
+        let vertex_push_constant_size =
+            input_texture_count.max(COMMON_VERTEX_TRANSFORM_COUNT) * std::mem::size_of::<Transform2D>() as u32;
 
-        let pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[
+        let uniform_bind_group_layout = use_uniform_buffer.then(|| {
+            let uniform_entries = [
+                (0, wgpu::ShaderStages::VERTEX, vertex_push_constant_size),
+                (1, wgpu::ShaderStages::FRAGMENT, fragment_push_constant_size),
+            ]
+                .iter()
+                .filter(|(_, _, size)| *size > 0)
+                .map(|&(binding, visibility, size)| wgpu::BindGroupLayoutEntry {
+                    binding,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(size as u64),
+                    },
+                    count: None,
+                })
+                .collect::<Vec<wgpu::BindGroupLayoutEntry>>();
+
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &uniform_entries,
+                label: None,
+            })
+        });
+
+        let pipeline_layout = match &uniform_bind_group_layout {
+            Some(uniform_bind_group_layout) =>
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    bind_group_layouts: &[&bind_group_layout, uniform_bind_group_layout],
+                    push_constant_ranges: &[],
+                    label: None,
+                }),
+            None => {
+                let push_constant_ranges = [
                     wgpu::PushConstantRange {
                         stages: wgpu::ShaderStages::VERTEX,
                         range: 0..vertex_push_constant_size,
@@ -486,9 +1581,18 @@ impl Shader {
                     wgpu::PushConstantRange {
                         stages: wgpu::ShaderStages::FRAGMENT,
                         range: vertex_push_constant_size..fragment_push_constant_size + vertex_push_constant_size,
-                    }],
-                label: None,
-            });
+                    }]
+                    .into_iter()
+                    .filter(|push_constant_range| !push_constant_range.range.is_empty())
+                    .collect::<Vec<wgpu::PushConstantRange>>();
+
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &push_constant_ranges,
+                    label: None,
+                })
+            }
+        };
 
         let vertex_layout =
             vec![wgpu::VertexFormat::Float32x2, wgpu::VertexFormat::Float32x2];
@@ -505,8 +1609,13 @@ impl Shader {
 
 
         Shader {
-            module,
+            fragment: Rc::new(ShaderFragment {
+                module: RefCell::new(module),
+                pipeline_cache: RefCell::default(),
+            }),
             bind_group_layout,
+            input_sample_type,
+            uniform_bind_group_layout,
             pipeline_layout,
             input_texture_count,
             vertex_push_constant_size,
@@ -514,7 +1623,7 @@ impl Shader {
             vertex_layout,
             vertex_stride,
             vertex_attributes,
-            pipeline_cache: RefCell::default(),
+            alive: Rc::new(()),
         }
     }
 
@@ -523,11 +1632,13 @@ impl Shader {
         device: &wgpu::Device,
         vertex_shader: &wgpu::ShaderModule,
         shader_entry_name: &str,
-        color_format: &ColorFormat,
+        format: wgpu::TextureFormat,
     ) -> Rc<wgpu::RenderPipeline> {
-        self.pipeline_cache
+        let module = self.fragment.module.borrow();
+
+        self.fragment.pipeline_cache
             .borrow_mut()
-            .entry((shader_entry_name.to_string(), *color_format))
+            .entry((shader_entry_name.to_string(), format))
             .or_insert_with(|| Rc::from(
                 device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                     layout: Some(&self.pipeline_layout),
@@ -541,10 +1652,10 @@ impl Shader {
                         }],
                     },
                     fragment: Some(wgpu::FragmentState {
-                        module: &self.module,
+                        module: &module,
                         entry_point: shader_entry_name,
                         targets: &[Some(wgpu::ColorTargetState {
-                            format: wgpu::TextureFormat::from(color_format),
+                            format,
                             blend: None,
                             write_mask: wgpu::ColorWrites::ALL,
                         })],
@@ -563,18 +1674,430 @@ impl Shader {
     }
 }
 
+pub(crate) const COMPUTE_SHADER_ENTRY_NAME: &str = "cs_main";
+
+pub(crate) struct ComputeShader {
+    module: wgpu::ShaderModule,
+    input_texture_count: u32,
+    output_texture_count: u32,
+    push_constant_size: u32,
+    // storage texture formats are part of the layout, pipelines are built per list of output formats
+    pipeline_cache: RefCell<HashMap<Vec<wgpu::TextureFormat>, Rc<ComputePipeline>>>,
+}
+
+struct ComputePipeline {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl ComputeShader {
+    pub(crate) fn input_count(&self) -> u32 {
+        self.input_texture_count
+    }
+    pub(crate) fn output_count(&self) -> u32 {
+        self.output_texture_count
+    }
+    pub(crate) fn push_constant_size(&self) -> u32 {
+        self.push_constant_size
+    }
+
+    fn get_pipeline(&self, device: &wgpu::Device, output_formats: Vec<wgpu::TextureFormat>) -> Rc<ComputePipeline> {
+        self.pipeline_cache
+            .borrow_mut()
+            .entry(output_formats)
+            .or_insert_with_key(|output_formats| {
+                let layout_entries = (0..self.input_texture_count)
+                    .map(|_| wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: FLOAT_SAMPLE_TYPE,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    })
+                    .chain(output_formats
+                        .iter()
+                        .map(|&format| wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        }))
+                    .enumerate()
+                    .map(|(index, ty)| wgpu::BindGroupLayoutEntry {
+                        binding: index as u32,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty,
+                        count: None,
+                    })
+                    .collect::<Vec<wgpu::BindGroupLayoutEntry>>();
+                let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &layout_entries,
+                    label: None,
+                });
+
+                let push_constant_ranges = (self.push_constant_size > 0)
+                    .then_some(wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStages::COMPUTE,
+                        range: 0..self.push_constant_size,
+                    })
+                    .into_iter()
+                    .collect::<Vec<wgpu::PushConstantRange>>();
+                let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &push_constant_ranges,
+                    label: None,
+                });
+
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    layout: Some(&pipeline_layout),
+                    module: &self.module,
+                    entry_point: COMPUTE_SHADER_ENTRY_NAME,
+                    label: None,
+                });
+
+                Rc::new(ComputePipeline {
+                    bind_group_layout,
+                    pipeline,
+                })
+            })
+            .clone()
+    }
+}
+
 pub(crate) struct Texture {
     pub desc: ImageDesc,
+    // From<&ColorFormat> of the desc format, or its integer variant
+    pub format: wgpu::TextureFormat,
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub extent: wgpu::Extent3d,
+    // lets caches notice the texture was dropped
+    alive: Rc<()>,
 }
 
-impl Texture {}
+impl Texture {
+    pub(crate) fn image_desc(&self) -> ImageDesc {
+        self.desc.clone()
+    }
+    // zeroed image to read the texture back into
+    pub(crate) fn new_image(&self) -> Image {
+        Image::new_empty(self.image_desc())
+            .expect("Texture was created with an invalid image format")
+    }
+    // submits pending work and the copy, the future completes once the staging buffer is mapped;
+    // the device is polled for the copy on a background thread, which wakes the task
+    pub(crate) fn read_async<'a>(&'a self, context: &'a WgpuContext) -> impl Future<Output=anyhow::Result<Image>> + 'a {
+        let (buffer, padded_stride, submission_index) = self.copy_to_buffer(context);
+
+        let state: Arc<Mutex<ReadbackState>> = Arc::default();
+        {
+            let state = Arc::clone(&state);
+            buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                let mut state = state.lock().unwrap();
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+        }
+
+        let device = Arc::clone(&context.device);
+        thread::spawn(move || {
+            device.poll(wgpu::Maintain::WaitForSubmissionIndex(submission_index));
+        });
+
+        TextureReadback {
+            image: Some(self.new_image()),
+            buffer,
+            padded_stride,
+            state,
+        }
+    }
+    // submits pending work and the copy, then waits for the device
+    pub(crate) fn read(&self, context: &WgpuContext) -> anyhow::Result<Image> {
+        let (buffer, padded_stride, _) = self.copy_to_buffer(context);
+
+        let (sender, receiver) = channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        context.device.poll(wgpu::Maintain::Wait);
+
+        receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Readback buffer was not mapped"))?
+            .map_err(|err| anyhow::anyhow!("Failed to map the readback buffer: {}", err))?;
+
+        Ok(read_mapped(&buffer, padded_stride, self.new_image()))
+    }
+
+    // staging buffer with padded rows and the submission copying the texture into it
+    fn copy_to_buffer(&self, context: &WgpuContext) -> (wgpu::Buffer, u32, wgpu::SubmissionIndex) {
+        let padded_stride = self.desc.stride().next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            size: padded_stride as wgpu::BufferAddress * self.desc.height() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            label: None,
+        });
+
+        {
+            let mut encoder_temp = context.encoder.borrow_mut();
+            let encoder = encoder_temp
+                .get_or_insert_with(|| context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: None,
+                }));
+            encoder.copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: Default::default(),
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_stride),
+                        rows_per_image: Some(self.desc.height()),
+                    },
+                },
+                self.extent,
+            );
+        }
+        let submission_index = context.submit_without_wait().unwrap();
+
+        (buffer, padded_stride, submission_index)
+    }
+    // reads the texture back and saves it with Image::save_file;
+    // signed formats are saved as unsigned, jpeg drops alpha, hdr formats convert themselves
+    pub(crate) fn save_file(&self, context: &WgpuContext, filename: &str) -> anyhow::Result<()> {
+        let mut image = self.new_image();
+        context.perform(&[Action::TexToImg(vec![(self, RefCell::new(&mut image))])]);
+        context.sync()?;
+
+        let extension = get_file_extension(filename)?;
+        let mut color_format = image.desc.color_format();
+        if !matches!(extension, "exr" | "hdr") && color_format.channel_type == ChannelType::Int {
+            color_format.channel_type = ChannelType::UInt;
+        }
+        if matches!(extension, "jpeg" | "jpg") {
+            color_format.channel_count = match color_format.channel_count {
+                ChannelCount::GrayAlpha => ChannelCount::Gray,
+                ChannelCount::Rgba => ChannelCount::Rgb,
+                channel_count => channel_count,
+            };
+        }
+
+        if color_format != image.desc.color_format() {
+            image = image.convert(color_format)?;
+        }
+
+        image.save_file(filename)
+    }
+}
+
+// texture allocated on first use, so textures of nodes that never run cost no memory
+pub(crate) struct LazyTexture {
+    desc: ImageDesc,
+    texture: OnceCell<Texture>,
+}
+
+impl LazyTexture {
+    pub(crate) fn desc(&self) -> &ImageDesc {
+        &self.desc
+    }
+    pub(crate) fn is_allocated(&self) -> bool {
+        self.texture.get().is_some()
+    }
+    pub(crate) fn get(&self, context: &WgpuContext) -> &Texture {
+        self.texture
+            .get_or_init(|| context.create_texture(self.desc.clone()))
+    }
+}
+
+struct TextureReadback {
+    // taken when the future completes
+    image: Option<Image>,
+    buffer: wgpu::Buffer,
+    padded_stride: u32,
+    state: Arc<Mutex<ReadbackState>>,
+}
+
+// shared with the map callback of a TextureReadback
+#[derive(Default)]
+struct ReadbackState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    // of the last poll, woken by the map callback
+    waker: Option<Waker>,
+}
+
+impl Future for TextureReadback {
+    type Output = anyhow::Result<Image>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let readback = self.get_mut();
+        let mut state = readback.state.lock().unwrap();
+
+        match state.result.take() {
+            Some(Ok(())) => {
+                drop(state);
+                let image = readback.image.take().expect("Readback polled after completion");
+
+                Poll::Ready(Ok(read_mapped(&readback.buffer, readback.padded_stride, image)))
+            }
+            Some(Err(err)) => Poll::Ready(Err(anyhow::anyhow!("Failed to map the readback buffer: {}", err))),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// fills the image from a mapped readback buffer and unmaps it
+fn read_mapped(buffer: &wgpu::Buffer, padded_stride: u32, mut image: Image) -> Image {
+    let data = buffer.slice(..).get_mapped_range();
+    image.bytes = unpad_rows(&data, padded_stride, image.desc.stride());
+    drop(data);
+    buffer.unmap();
+
+    image
+}
 
 struct BufferImage {
     buffer: wgpu::Buffer,
     image_index: (usize, usize), // action index, index of (tex, img) inside action vec
+    padded_stride: u32,
+}
+
+// rows of a readback buffer without the padding added for COPY_BYTES_PER_ROW_ALIGNMENT
+fn unpad_rows(data: &[u8], padded_stride: u32, stride: u32) -> Vec<u8> {
+    if padded_stride == stride {
+        data.to_vec()
+    } else {
+        data
+            .chunks_exact(padded_stride as usize)
+            .flat_map(|row| &row[..stride as usize])
+            .copied()
+            .collect()
+    }
+}
+
+// rotating staging buffers for streaming frames off the GPU: the copy of a pushed frame is submitted
+// without waiting, so it completes while the next frames render, and its buffer is reused once the
+// image is sent; images arrive on the receiver from create_readback_ring in the order they were pushed
+pub(crate) struct ReadbackRing {
+    desc: ImageDesc,
+    padded_stride: u32,
+    capacity: usize,
+    free_buffers: Vec<wgpu::Buffer>,
+    // oldest first
+    pending: VecDeque<PendingReadback>,
+    buffer_allocation_count: usize,
+    sender: Sender<Image>,
+}
+
+struct PendingReadback {
+    buffer: wgpu::Buffer,
+    submission_index: wgpu::SubmissionIndex,
+    is_mapped: Arc<AtomicBool>,
+}
+
+impl ReadbackRing {
+    // waits only if all buffers are in flight
+    pub(crate) fn push(&mut self, context: &WgpuContext, texture: &Texture) {
+        if texture.desc != self.desc {
+            panic!("Texture and readback ring must have the same dimensions");
+        }
+
+        context.device.poll(wgpu::Maintain::Poll);
+        self.send_mapped();
+        if self.pending.len() == self.capacity {
+            let oldest = self.pending.pop_front().unwrap();
+            context.device.poll(wgpu::Maintain::WaitForSubmissionIndex(oldest.submission_index));
+            self.send(oldest.buffer);
+        }
+
+        let buffer = self.free_buffers
+            .pop()
+            .unwrap_or_else(|| {
+                self.buffer_allocation_count += 1;
+                context.device.create_buffer(&wgpu::BufferDescriptor {
+                    size: self.padded_stride as wgpu::BufferAddress * self.desc.height() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                    label: None,
+                })
+            });
+
+        context.encoder
+            .borrow_mut()
+            .get_or_insert_with(|| context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: None,
+            }))
+            .copy_texture_to_buffer(
+                wgpu::ImageCopyTexture {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: Default::default(),
+                },
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(self.padded_stride),
+                        rows_per_image: Some(self.desc.height()),
+                    },
+                },
+                texture.extent,
+            );
+        let submission_index = context.submit_without_wait().unwrap();
+
+        let is_mapped = Arc::new(AtomicBool::new(false));
+        let is_mapped_clone = is_mapped.clone();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            result.unwrap();
+            is_mapped_clone.store(true, Ordering::Release);
+        });
+
+        self.pending.push_back(PendingReadback {
+            buffer,
+            submission_index,
+            is_mapped,
+        });
+    }
+
+    // waits for all pushed frames to be sent
+    pub(crate) fn flush(&mut self, context: &WgpuContext) {
+        context.device.poll(wgpu::Maintain::Wait);
+        self.send_mapped();
+        assert!(self.pending.is_empty());
+    }
+
+    pub(crate) fn buffer_allocation_count(&self) -> usize {
+        self.buffer_allocation_count
+    }
+
+    fn send_mapped(&mut self) {
+        while self.pending
+            .front()
+            .is_some_and(|pending| pending.is_mapped.load(Ordering::Acquire)) {
+            let pending = self.pending.pop_front().unwrap();
+            self.send(pending.buffer);
+        }
+    }
+    fn send(&mut self, buffer: wgpu::Buffer) {
+        let data = buffer.slice(..).get_mapped_range();
+        let bytes = unpad_rows(&data, self.padded_stride, self.desc.stride());
+        drop(data);
+        buffer.unmap();
+        self.free_buffers.push(buffer);
+
+        // frames are dropped once the receiver is gone
+        let _ = self.sender.send(Image {
+            desc: self.desc.clone(),
+            bytes,
+        });
+    }
 }
 
 pub(crate) struct TextureWithTransform {
@@ -45,6 +45,7 @@ functions = {
     {
         id = "432b9bf1-f478-476c-a9c9-9a6e190124fc",
         name = "mult",
+        desc = "Multiplies two integers",
         inputs = {
             { "a", "int" },
             { "b", "int" }
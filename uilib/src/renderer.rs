@@ -3,6 +3,7 @@ use std::mem;
 
 use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec3};
+use pollster::FutureExt;
 use wgpu::*;
 use wgpu::util::DeviceExt;
 
@@ -117,7 +118,7 @@ impl WgpuRenderer {
         queue: &Queue,
         surface_config: &SurfaceConfiguration,
         window_size: UVec2,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         let vertex_size = mem::size_of::<Vertex>();
         let vertex_data = create_vertices();
 
@@ -243,10 +244,15 @@ impl WgpuRenderer {
             label: None,
         });
 
+        // WGSL errors would otherwise reach the uncaptured error handler and abort
+        device.push_error_scope(ErrorFilter::Validation);
         let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: None,
             source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
         });
+        if let Some(error) = device.pop_error_scope().block_on() {
+            return Err(anyhow::anyhow!("Shader creation failed: {}", error));
+        }
 
         let vertex_buffers = [VertexBufferLayout {
             array_stride: vertex_size as BufferAddress,
@@ -310,7 +316,7 @@ impl WgpuRenderer {
 
         result.resize(device, queue, window_size);
 
-        result
+        Ok(result)
     }
 
 
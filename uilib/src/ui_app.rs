@@ -20,7 +20,8 @@ impl App for UiApp {
             queue: &Queue,
             surface_config: &SurfaceConfiguration) -> Self {
         let window_size = UVec2::new(surface_config.width, surface_config.height);
-        let renderer = WgpuRenderer::new(device, queue, surface_config, window_size);
+        let renderer = WgpuRenderer::new(device, queue, surface_config, window_size)
+            .expect("Failed to create the renderer.");
 
         let mut result = Self {
             window_size: UVec2::new(0, 0),